@@ -0,0 +1,9 @@
+fn main() {
+    // the gRPC service, with a protoc of its own so builds need nothing installed
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+        tonic_prost_build::compile_protos("proto/cygaz.proto").expect("failed to compile proto/cygaz.proto");
+    }
+}
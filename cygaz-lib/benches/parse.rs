@@ -0,0 +1,88 @@
+//! Parse time of a country sized prices table, the scraper runs once per petroleum type on
+//! every refresh. Also reports the allocations of a single parse, which criterion does not.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use cygaz_lib::parse_prices;
+use url::Url;
+
+static ENDPOINT: &str = "https://eforms.eservices.cyprus.gov.cy/MCIT/MCIT/PetroleumPrices";
+
+// roughly the number of stations listed for the whole country
+const STATIONS: usize = 400;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn country_table() -> String {
+    let mut html = String::from(
+        "<html><body><table id=\"petroleumPriceDetailsFootable\" class=\"table\"><tbody>\n",
+    );
+    for idx in 0..STATIONS {
+        let offline = if idx % 25 == 0 { " class=\"isOffLine\"" } else { "" };
+        html.push_str(&format!(
+            "<tr>\
+                <td{}>Brand {}</td>\
+                <td>Company {} Ltd</td>\
+                <td><a href=\"Map?coordinates=35.{:04},33.{:04}\">Λεωφόρος Μακαρίου {}</a></td>\
+                <td>Στρόβολος</td>\
+                <td>1.{:03}</td>\
+            </tr>\n",
+            offline,
+            idx % 12,
+            idx,
+            idx,
+            STATIONS - idx,
+            idx,
+            300 + idx % 200,
+        ));
+    }
+    html.push_str("</tbody></table></body></html>");
+    html
+}
+
+fn parse(c: &mut Criterion) {
+    let endpoint = Url::parse(ENDPOINT).unwrap();
+    let html = country_table();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let result = parse_prices(&endpoint, &html);
+    assert_eq!(result.stations.len(), STATIONS);
+    println!(
+        "parse_prices: {} allocations, {} bytes for {} stations",
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+        STATIONS
+    );
+
+    let mut group = c.benchmark_group("parse_prices");
+    group.throughput(Throughput::Bytes(html.len() as u64));
+    group.bench_function("country", |b| {
+        b.iter(|| parse_prices(black_box(&endpoint), black_box(&html)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
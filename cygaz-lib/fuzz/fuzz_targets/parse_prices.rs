@@ -0,0 +1,15 @@
+#![no_main]
+
+use cygaz_lib::parse_prices;
+use libfuzzer_sys::fuzz_target;
+use url::Url;
+
+static ENDPOINT: &str = "https://eforms.eservices.cyprus.gov.cy/MCIT/MCIT/PetroleumPrices";
+
+// malformed rows must end up as warnings, never as a panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(body) = std::str::from_utf8(data) {
+        let endpoint = Url::parse(ENDPOINT).unwrap();
+        let _ = parse_prices(&endpoint, body);
+    }
+});
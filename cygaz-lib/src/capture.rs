@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{CyGazError, District, PetroleumType, PriceResult};
+
+/// A prices response exactly as upstream returned it.
+#[non_exhaustive]
+pub struct RawResponse<'a> {
+    pub petroleum_type: PetroleumType,
+    pub district: District,
+    pub status: u16,
+    pub body: &'a str,
+    // None when upstream rejected the request
+    pub result: Option<&'a PriceResult>,
+}
+
+// numbers captures within the same millisecond, every page of a listing among them
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+pub type CaptureCallback = Arc<dyn Fn(&RawResponse) + Send + Sync>;
+
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum RawCapture {
+    /// Writes `<millis>-<sequence>-<fuel>-<district>.html` and the matching `.json` parse
+    /// result.
+    Directory(PathBuf),
+    Callback(CaptureCallback),
+}
+
+impl RawCapture {
+    pub fn directory(path: impl Into<PathBuf>) -> Result<Self, CyGazError> {
+        let path = path.into();
+        fs::create_dir_all(&path)
+            .map_err(|err| CyGazError(format!("Capture directory {:?}: {}", path, err)))?;
+        Ok(RawCapture::Directory(path))
+    }
+
+    pub(crate) fn capture(&self, response: &RawResponse) {
+        match self {
+            RawCapture::Callback(callback) => callback(response),
+            RawCapture::Directory(path) => {
+                let millis = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis())
+                    .unwrap_or_default();
+                let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+                let name = format!(
+                    "{}-{}-{:?}-{:?}",
+                    millis, sequence, response.petroleum_type, response.district
+                );
+
+                // capturing is a debugging aid and must never fail the fetch itself
+                let _ = fs::write(path.join(format!("{}.html", name)), response.body);
+                if let Some(result) = response.result {
+                    if let Ok(json) = serde_json::to_string_pretty(result) {
+                        let _ = fs::write(path.join(format!("{}.json", name)), json);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    use crate::capture::{RawCapture, RawResponse};
+    use crate::{District, PetroleumStation, PetroleumType, PriceResult};
+
+    fn response<'a>(body: &'a str, result: Option<&'a PriceResult>) -> RawResponse<'a> {
+        RawResponse {
+            petroleum_type: PetroleumType::Kerosene,
+            district: District::Paphos,
+            status: 200,
+            body,
+            result,
+        }
+    }
+
+    #[test]
+    fn writes_the_response_next_to_its_parse_result() {
+        let path = std::env::temp_dir().join(format!("cygaz-capture-{}", std::process::id()));
+        let capture = RawCapture::directory(&path).unwrap();
+        let result = PriceResult {
+            stations: vec![PetroleumStation {
+                station_id: "a".to_string(),
+                ..Default::default()
+            }],
+            warnings: vec![],
+            total_rows: 1,
+        };
+        capture.capture(&response("<table></table>", Some(&result)));
+        capture.capture(&response("rejected", None));
+
+        let mut names = fs::read_dir(&path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        let bodies = names
+            .iter()
+            .map(|name| fs::read_to_string(path.join(name)).unwrap())
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&path).unwrap();
+
+        let htmls = names
+            .iter()
+            .filter(|name| name.ends_with("-Kerosene-Paphos.html"));
+        assert_eq!(htmls.count(), 2);
+        let jsons = names
+            .iter()
+            .zip(&bodies)
+            .filter(|(name, _)| name.ends_with("-Kerosene-Paphos.json"))
+            .collect::<Vec<_>>();
+        assert_eq!(jsons.len(), 1);
+        assert!(jsons[0].1.contains("\"station_id\": \"a\""));
+        assert!(bodies.iter().any(|body| body == "<table></table>"));
+    }
+
+    #[test]
+    fn hands_the_response_to_the_callback() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let capture = RawCapture::Callback({
+            let seen = seen.clone();
+            Arc::new(move |response: &RawResponse| {
+                seen.lock()
+                    .unwrap()
+                    .push((response.status, response.body.to_string()))
+            })
+        });
+        capture.capture(&response("<table></table>", None));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [(200, "<table></table>".to_string())]
+        );
+    }
+
+    #[test]
+    fn fails_on_a_directory_it_cannot_create() {
+        let file = std::env::temp_dir().join(format!("cygaz-capture-file-{}", std::process::id()));
+        fs::write(&file, "").unwrap();
+        let created = RawCapture::directory(file.join("captures"));
+        fs::remove_file(&file).unwrap();
+        assert!(created.is_err());
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT};
+use scraper::Html;
+use url::Url;
+
+use crate::{
+    next_page, parse_document, selectors, AreasByDistrict, CyGazError, District, PetroleumStation,
+    PetroleumType, PriceResult, PETROLEUM_PRICES_ENDPOINT, USER_AGENT_VALUE,
+};
+use crate::capture::{RawCapture, RawResponse};
+use crate::conditional::{body_hash, Fetched, Validator, Validators};
+use crate::details::parse_station_details;
+use crate::throttle::Throttle;
+
+// sold by practically every station, so its listing covers every area
+const AREAS_PETROLEUM_TYPE: PetroleumType = PetroleumType::Unlead95;
+
+// upper bound for following next page links, in case upstream links pages in a loop
+const MAX_PAGES: usize = 50;
+
+struct Posted {
+    status: u16,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// Upstream session that keeps its cookies and verification token between calls,
+/// so consecutive fetches only need the POST.
+pub struct CyGazClient {
+    client: Client,
+    endpoint: Url,
+    token: Mutex<Option<String>>,
+    throttle: Arc<Throttle>,
+    capture: Option<RawCapture>,
+    validators: Arc<Validators>,
+}
+
+impl CyGazClient {
+    pub fn new() -> Result<Self, CyGazError> {
+        let client = Client::builder()
+            .cookie_store(true)
+            .build()
+            .map_err(|err| CyGazError(err.to_string()))?;
+
+        Ok(CyGazClient {
+            client,
+            endpoint: Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap(),
+            token: Mutex::new(None),
+            throttle: Arc::new(Throttle::default()),
+            capture: None,
+            validators: Arc::new(Validators::default()),
+        })
+    }
+
+    /// Shares `throttle` with other clients so their combined request rate stays polite.
+    pub fn with_throttle(mut self, throttle: Arc<Throttle>) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Shares the conditional request validators between clients, so a fresh session
+    /// can still tell whether a listing changed since the previous one.
+    pub fn with_validators(mut self, validators: Arc<Validators>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Hands every raw prices response, together with its parse result, to `capture`.
+    pub fn with_capture(mut self, capture: RawCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    fn fetch_token(&self) -> Result<String, CyGazError> {
+        self.throttle.wait();
+        let body = self
+            .client
+            .get(PETROLEUM_PRICES_ENDPOINT)
+            .header(USER_AGENT, USER_AGENT_VALUE)
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|err| CyGazError(err.to_string()))?;
+
+        let document = Html::parse_fragment(body.as_str());
+        document
+            .select(&selectors().token)
+            .next()
+            .and_then(|el| el.value().attr("value"))
+            .map(|token| token.to_string())
+            .ok_or_else(|| CyGazError("Verification token not found".to_string()))
+    }
+
+    fn token(&self) -> Result<String, CyGazError> {
+        let mut token = self.token.lock().unwrap();
+        if let Some(cached) = token.as_ref() {
+            return Ok(cached.clone());
+        }
+
+        let fresh = self.fetch_token()?;
+        *token = Some(fresh.clone());
+        Ok(fresh)
+    }
+
+    fn invalidate_token(&self) {
+        *self.token.lock().unwrap() = None;
+    }
+
+    fn post_prices(
+        &self,
+        token: &str,
+        petroleum_type: PetroleumType,
+        district: District,
+        validator: Option<&Validator>,
+    ) -> Result<Posted, CyGazError> {
+        let form_data = [
+            ("__RequestVerificationToken", token.to_string()),
+            ("Entity.StationCityEnum", district.form_value().to_string()),
+            ("Entity.PetroleumType", format!("{}", petroleum_type as i32)),
+            ("Entity.StationDistrict", "".to_string()),
+        ];
+
+        let mut request = self
+            .client
+            .post(PETROLEUM_PRICES_ENDPOINT)
+            .header(USER_AGENT, USER_AGENT_VALUE)
+            .form(&form_data);
+        if let Some(validator) = validator {
+            if let Some(etag) = &validator.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validator.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        self.throttle.wait();
+        let response = request
+            .send()
+            .map_err(|err| CyGazError(err.to_string()))?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let status = response.status().as_u16();
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response
+            .text()
+            .map_err(|err| CyGazError(err.to_string()))?;
+
+        Ok(Posted {
+            status,
+            etag,
+            last_modified,
+            body,
+        })
+    }
+
+    // None when upstream rejected the token, otherwise the page and the link to the next one
+    fn accept(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+        status: u16,
+        body: &str,
+    ) -> Option<(PriceResult, Option<Url>)> {
+        // the document is parsed once, both to tell a rejection apart and to read the stations
+        let page = match (200..300).contains(&status) {
+            true => {
+                let document = Html::parse_fragment(body);
+                let accepted = document.select(&selectors().prices).next().is_some();
+                accepted.then(|| {
+                    let next = next_page(&self.endpoint, &document);
+                    (parse_document(&self.endpoint, document), next)
+                })
+            }
+            false => None,
+        };
+
+        if let Some(capture) = &self.capture {
+            capture.capture(&RawResponse {
+                petroleum_type,
+                district,
+                status,
+                body,
+                result: page.as_ref().map(|(result, _)| result),
+            });
+        }
+
+        page
+    }
+
+    fn get_page(&self, url: &Url) -> Result<(u16, String), CyGazError> {
+        self.throttle.wait();
+        let response = self
+            .client
+            .get(url.as_str())
+            .header(USER_AGENT, USER_AGENT_VALUE)
+            .send()
+            .map_err(|err| CyGazError(err.to_string()))?;
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .map_err(|err| CyGazError(err.to_string()))?;
+        Ok((status, body))
+    }
+
+    /// Appends the following pages to `result` while upstream links to a next one.
+    fn follow_pages(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+        mut result: PriceResult,
+        mut next: Option<Url>,
+    ) -> Result<PriceResult, CyGazError> {
+        let mut visited = BTreeSet::new();
+        while let Some(url) = next.take() {
+            if !visited.insert(url.clone()) || visited.len() > MAX_PAGES {
+                break;
+            }
+
+            let (status, body) = self.get_page(&url)?;
+            let (page, following) = self
+                .accept(petroleum_type, district, status, &body)
+                .ok_or_else(|| CyGazError(format!("Prices page {} failed with status {}", url, status)))?;
+            result.append(page);
+            next = following;
+        }
+        Ok(result)
+    }
+
+    fn fetch(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+        conditional: bool,
+    ) -> Result<Fetched, CyGazError> {
+        let fetched = self.try_fetch(petroleum_type, district, conditional);
+        if fetched.is_err() {
+            // the caller is left without a listing, so the next fetch has to be a full one
+            self.validators.forget(petroleum_type, district);
+        }
+        fetched
+    }
+
+    fn try_fetch(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+        conditional: bool,
+    ) -> Result<Fetched, CyGazError> {
+        let validator = if conditional {
+            self.validators.get(petroleum_type, district)
+        } else {
+            None
+        };
+
+        let mut status = 0;
+        for attempt in 0..2 {
+            if attempt > 0 {
+                // the cached token or session expired, start over with a fresh one
+                self.invalidate_token();
+            }
+
+            let token = self.token()?;
+            let posted = self.post_prices(&token, petroleum_type, district, validator.as_ref())?;
+            status = posted.status;
+            if status == 304 {
+                return Ok(Fetched::NotModified);
+            }
+
+            let hash = body_hash(&posted.body);
+            if validator.as_ref().is_some_and(|v| v.body_hash == hash) {
+                return Ok(Fetched::NotModified);
+            }
+
+            if let Some((result, next)) = self.accept(petroleum_type, district, status, &posted.body) {
+                let result = self.follow_pages(petroleum_type, district, result, next)?;
+                self.validators.store(
+                    petroleum_type,
+                    district,
+                    Validator {
+                        etag: posted.etag,
+                        last_modified: posted.last_modified,
+                        body_hash: hash,
+                    },
+                );
+                return Ok(Fetched::Modified(result));
+            }
+        }
+
+        Err(CyGazError(format!(
+            "Prices request rejected for {:?} with status {}",
+            petroleum_type, status
+        )))
+    }
+
+    pub fn fetch_prices(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+    ) -> Result<PriceResult, CyGazError> {
+        match self.fetch(petroleum_type, district, false)? {
+            Fetched::Modified(result) => Ok(result),
+            Fetched::NotModified => Err(CyGazError(
+                "Unexpected not modified response".to_string(),
+            )),
+        }
+    }
+
+    /// Like [`CyGazClient::fetch_prices`], but answers [`Fetched::NotModified`] without
+    /// parsing when the listing did not change since the previous fetch.
+    pub fn fetch_prices_if_modified(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+    ) -> Result<Fetched, CyGazError> {
+        self.fetch(petroleum_type, district, true)
+    }
+
+    /// Fills in the opening hours, telephone and services of `station` from the page its
+    /// address links to. One extra request per station, so use it sparingly.
+    pub fn fetch_station_details(&self, station: &mut PetroleumStation) -> Result<(), CyGazError> {
+        let url = station
+            .details_url
+            .clone()
+            .ok_or_else(|| CyGazError(format!("No details link for {:?}", station.address)))?;
+
+        let (status, body) = self.get_page(&url)?;
+        if !(200..300).contains(&status) {
+            return Err(CyGazError(format!(
+                "Station details request failed with status {}",
+                status
+            )));
+        }
+
+        station.details = Some(parse_station_details(&body));
+        Ok(())
+    }
+
+    /// Area names of every district, fetched in this one session.
+    pub fn fetch_all_areas(&self) -> Result<AreasByDistrict, CyGazError> {
+        let mut areas = BTreeMap::new();
+        for district in District::DISTRICTS {
+            let result = self.fetch_prices(AREAS_PETROLEUM_TYPE, district)?;
+            let names = result
+                .stations
+                .into_iter()
+                .map(|station| station.area)
+                .filter(|area| !area.is_empty())
+                .collect::<BTreeSet<_>>();
+            areas.insert(district, names.into_iter().collect());
+        }
+        Ok(areas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{CyGazClient, District, PetroleumType, RawCapture};
+
+    #[test]
+    fn captures_accepted_and_rejected_responses() {
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink = captured.clone();
+        let client = CyGazClient::new()
+            .unwrap()
+            .with_capture(RawCapture::Callback(Arc::new(move |raw| {
+                sink.lock()
+                    .unwrap()
+                    .push((raw.status, raw.body.to_string(), raw.result.is_some()));
+            })));
+
+        let table = r#"<table id="petroleumPriceDetailsFootable"><tbody></tbody></table>"#;
+        let accepted = client.accept(PetroleumType::Kerosene, District::All, 200, table);
+        let rejected = client.accept(PetroleumType::Kerosene, District::All, 500, "error");
+
+        assert!(accepted.is_some());
+        assert!(rejected.is_none());
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0], (200, table.to_string(), true));
+        assert_eq!(captured[1], (500, "error".to_string(), false));
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use crate::{District, PetroleumType, PriceResult};
+
+#[non_exhaustive]
+pub enum Fetched {
+    Modified(PriceResult),
+    // upstream answered 304 or returned the exact same table as last time
+    NotModified,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Validator {
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+    pub(crate) body_hash: u64,
+}
+
+/// Last seen `ETag`, `Last-Modified` and body hash per listing.
+#[derive(Default)]
+pub struct Validators {
+    validators: Mutex<HashMap<(PetroleumType, District), Validator>>,
+}
+
+impl Validators {
+    pub(crate) fn get(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+    ) -> Option<Validator> {
+        let validators = self.validators.lock().unwrap();
+        validators.get(&(petroleum_type, district)).cloned()
+    }
+
+    pub(crate) fn store(
+        &self,
+        petroleum_type: PetroleumType,
+        district: District,
+        validator: Validator,
+    ) {
+        let mut validators = self.validators.lock().unwrap();
+        validators.insert((petroleum_type, district), validator);
+    }
+
+    pub(crate) fn forget(&self, petroleum_type: PetroleumType, district: District) {
+        let mut validators = self.validators.lock().unwrap();
+        validators.remove(&(petroleum_type, district));
+    }
+
+    pub fn clear(&self) {
+        self.validators.lock().unwrap().clear();
+    }
+}
+
+pub(crate) fn body_hash(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conditional::{body_hash, Validator, Validators};
+    use crate::{District, PetroleumType};
+
+    #[test]
+    fn keeps_a_validator_per_listing() {
+        let validators = Validators::default();
+        let validator = Validator {
+            etag: Some("\"a\"".to_string()),
+            last_modified: None,
+            body_hash: body_hash("<table></table>"),
+        };
+        validators.store(PetroleumType::Kerosene, District::Paphos, validator);
+
+        let stored = validators
+            .get(PetroleumType::Kerosene, District::Paphos)
+            .unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("\"a\""));
+        assert_eq!(stored.body_hash, body_hash("<table></table>"));
+        assert!(validators
+            .get(PetroleumType::Kerosene, District::All)
+            .is_none());
+        assert!(validators
+            .get(PetroleumType::Unlead95, District::Paphos)
+            .is_none());
+
+        validators.forget(PetroleumType::Kerosene, District::Paphos);
+        assert!(validators
+            .get(PetroleumType::Kerosene, District::Paphos)
+            .is_none());
+
+        validators.store(PetroleumType::Unlead95, District::All, Validator::default());
+        validators.clear();
+        assert!(validators
+            .get(PetroleumType::Unlead95, District::All)
+            .is_none());
+    }
+
+    #[test]
+    fn hashes_bodies_by_content() {
+        assert_eq!(body_hash("<table></table>"), body_hash("<table></table>"));
+        assert_ne!(body_hash("<table></table>"), body_hash("<table> </table>"));
+    }
+}
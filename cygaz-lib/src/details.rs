@@ -0,0 +1,115 @@
+use std::sync::OnceLock;
+
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::normalize::fold;
+
+/// Extra station metadata from the page the address links to.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct StationDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opening_hours: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telephone: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+}
+
+// label prefixes in Greek and English, compared folded
+static OPENING_HOURS_LABELS: [&str; 3] = ["ωραριο", "opening hours", "hours"];
+static TELEPHONE_LABELS: [&str; 3] = ["τηλ", "telephone", "phone"];
+static SERVICES_LABELS: [&str; 3] = ["υπηρεσιες", "παροχες", "services"];
+
+// label and value pairs, whether laid out as table rows, definition lists or form groups
+struct DetailSelectors {
+    pairs: [(Selector, Selector, Selector); 3],
+    item: Selector,
+}
+
+fn detail_selectors() -> &'static DetailSelectors {
+    static SELECTORS: OnceLock<DetailSelectors> = OnceLock::new();
+    let parse = |css| Selector::parse(css).unwrap();
+    SELECTORS.get_or_init(|| DetailSelectors {
+        pairs: [
+            (parse("tr"), parse("th"), parse("td")),
+            (parse("dl"), parse("dt"), parse("dd")),
+            (parse(".form-group"), parse("label"), parse(".form-control-static")),
+        ],
+        item: parse("li"),
+    })
+}
+
+fn text(element: &ElementRef) -> String {
+    element
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn labelled(label: &str, labels: &[&str]) -> bool {
+    let label = fold(label.trim_end_matches(':'));
+    labels.iter().any(|prefix| label.starts_with(&fold(prefix)))
+}
+
+pub(crate) fn parse_station_details(body: &str) -> StationDetails {
+    let selectors = detail_selectors();
+    let document = Html::parse_document(body);
+    let mut details = StationDetails::default();
+
+    let pairs = selectors.pairs.iter().flat_map(|(container, label, value)| {
+        document
+            .select(container)
+            .flat_map(|pair| pair.select(label).zip(pair.select(value)).collect::<Vec<_>>())
+    });
+
+    for (label, value) in pairs {
+        let label = text(&label);
+
+        if labelled(&label, &OPENING_HOURS_LABELS) {
+            details.opening_hours.get_or_insert(text(&value));
+        } else if labelled(&label, &TELEPHONE_LABELS) {
+            details.telephone.get_or_insert(text(&value));
+        } else if labelled(&label, &SERVICES_LABELS) && details.services.is_empty() {
+            let items = value.select(&selectors.item).map(|li| text(&li)).collect::<Vec<_>>();
+            details.services = match items.is_empty() {
+                true => text(&value).split(',').map(|s| s.trim().to_string()).collect(),
+                false => items,
+            };
+            details.services.retain(|service| !service.is_empty());
+        }
+    }
+
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::details::{parse_station_details, StationDetails};
+
+    #[test]
+    fn reads_greek_table_and_english_definition_list() {
+        let greek = r#"<table>
+            <tr><th>Ωράριο λειτουργίας:</th><td>06:00 - 21:00</td></tr>
+            <tr><th>Τηλέφωνο</th><td>22 123456</td></tr>
+            <tr><th>Υπηρεσίες</th><td><ul><li>Πλυντήριο</li><li>Mini market</li></ul></td></tr>
+        </table>"#;
+        assert_eq!(
+            parse_station_details(greek),
+            StationDetails {
+                opening_hours: Some("06:00 - 21:00".to_string()),
+                telephone: Some("22 123456".to_string()),
+                services: vec!["Πλυντήριο".to_string(), "Mini market".to_string()],
+            }
+        );
+
+        let english = r#"<dl><dt>Services</dt><dd>Car wash, ATM</dd><dt>Phone</dt><dd>25 000000</dd></dl>"#;
+        let details = parse_station_details(english);
+        assert_eq!(details.services, vec!["Car wash", "ATM"]);
+        assert_eq!(details.telephone.as_deref(), Some("25 000000"));
+        assert!(details.opening_hours.is_none());
+    }
+}
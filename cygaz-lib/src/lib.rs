@@ -1,13 +1,34 @@
 extern crate core;
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::sync::OnceLock;
 
-use reqwest::header::USER_AGENT;
+use ego_tree::NodeId;
 use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-#[derive(Debug, Copy, Clone, Serialize)]
+mod capture;
+mod client;
+mod conditional;
+mod details;
+pub mod normalize;
+pub mod prelude;
+pub mod quality;
+mod throttle;
+mod vat;
+
+pub use capture::{CaptureCallback, RawCapture, RawResponse};
+pub use client::CyGazClient;
+pub use conditional::{Fetched, Validators};
+pub use details::StationDetails;
+pub use throttle::{Throttle, DEFAULT_MIN_INTERVAL};
+pub use vat::{VatBreakdown, VatRate, VatTable};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub enum PetroleumType {
     Unlead95 = 1,
     Unlead98 = 2,
@@ -16,15 +37,184 @@ pub enum PetroleumType {
     Kerosene = 5,
 }
 
-static USER_AGENT_VALUE: &'static str =
+impl PetroleumType {
+    pub const ALL: [PetroleumType; 5] = [
+        PetroleumType::Unlead95,
+        PetroleumType::Unlead98,
+        PetroleumType::DieselHeat,
+        PetroleumType::DieselAuto,
+        PetroleumType::Kerosene,
+    ];
+
+    pub fn from_id(id: i32) -> Option<PetroleumType> {
+        PetroleumType::ALL
+            .into_iter()
+            .find(|petroleum_type| *petroleum_type as i32 == id)
+    }
+
+    /// Petroleum type named like its variant, ignoring case and `_` or `-`, e.g. `diesel_auto`.
+    pub fn from_name(name: &str) -> Option<PetroleumType> {
+        let name = name.replace(['_', '-'], "").to_lowercase();
+        PetroleumType::ALL
+            .into_iter()
+            .find(|petroleum_type| format!("{:?}", petroleum_type).to_lowercase() == name)
+    }
+
+    /// Name in snake case, e.g. `diesel_auto`, as `from_name` takes it.
+    pub fn key(&self) -> &'static str {
+        match self {
+            PetroleumType::Unlead95 => "unlead95",
+            PetroleumType::Unlead98 => "unlead98",
+            PetroleumType::DieselHeat => "diesel_heat",
+            PetroleumType::DieselAuto => "diesel_auto",
+            PetroleumType::Kerosene => "kerosene",
+        }
+    }
+
+    /// Fuels for heating rather than for vehicles.
+    pub fn is_heating(&self) -> bool {
+        matches!(self, PetroleumType::DieselHeat | PetroleumType::Kerosene)
+    }
+
+    /// Name as the upstream site shows it.
+    pub fn label_el(&self) -> &'static str {
+        match self {
+            PetroleumType::Unlead95 => "Αμόλυβδη 95",
+            PetroleumType::Unlead98 => "Αμόλυβδη 98",
+            PetroleumType::DieselHeat => "Πετρέλαιο Θέρμανσης",
+            PetroleumType::DieselAuto => "Πετρέλαιο Κίνησης",
+            PetroleumType::Kerosene => "Κηροζίνη",
+        }
+    }
+
+    pub fn label_en(&self) -> &'static str {
+        match self {
+            PetroleumType::Unlead95 => "Unleaded 95",
+            PetroleumType::Unlead98 => "Unleaded 98",
+            PetroleumType::DieselHeat => "Heating diesel",
+            PetroleumType::DieselAuto => "Diesel",
+            PetroleumType::Kerosene => "Kerosene",
+        }
+    }
+}
+
+/// Currency of every upstream price.
+pub static CURRENCY: &str = "EUR";
+
+/// Quantity a price is quoted for.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum PriceUnit {
+    #[default]
+    #[serde(rename = "litre")]
+    Litre,
+    // how heating fuel is often ordered
+    #[serde(rename = "1000_litres")]
+    ThousandLitres,
+}
+
+impl PriceUnit {
+    /// Litres a price is quoted for.
+    pub fn litres(&self) -> f32 {
+        match self {
+            PriceUnit::Litre => 1.0,
+            PriceUnit::ThousandLitres => 1000.0,
+        }
+    }
+}
+
+/// Cyprus districts as understood by the upstream `StationCityEnum` filter.
+/// `All` is the synthetic nationwide district.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum District {
+    All,
+    Nicosia,
+    Limassol,
+    Larnaca,
+    Paphos,
+    Famagusta,
+}
+
+impl District {
+    pub const DISTRICTS: [District; 5] = [
+        District::Nicosia,
+        District::Limassol,
+        District::Larnaca,
+        District::Paphos,
+        District::Famagusta,
+    ];
+
+    /// District named like its variant, ignoring case, e.g. `nicosia`.
+    pub fn from_name(name: &str) -> Option<District> {
+        [District::All]
+            .into_iter()
+            .chain(District::DISTRICTS)
+            .find(|district| district.form_value().eq_ignore_ascii_case(name))
+    }
+
+    /// Name in lower case, e.g. `nicosia`, as `from_name` takes it.
+    pub fn key(&self) -> &'static str {
+        match self {
+            District::All => "all",
+            District::Nicosia => "nicosia",
+            District::Limassol => "limassol",
+            District::Larnaca => "larnaca",
+            District::Paphos => "paphos",
+            District::Famagusta => "famagusta",
+        }
+    }
+
+    fn form_value(&self) -> &'static str {
+        match self {
+            District::All => "All",
+            District::Nicosia => "Nicosia",
+            District::Limassol => "Limassol",
+            District::Larnaca => "Larnaca",
+            District::Paphos => "Paphos",
+            District::Famagusta => "Famagusta",
+        }
+    }
+}
+
+static USER_AGENT_VALUE: &str =
     "Mozilla/4.0 (compatible; MSIE 8.0; Windows NT 6.1; Trident/4.0)";
 
-static PETROLEUM_PRICES_ENDPOINT: &'static str =
+static PETROLEUM_PRICES_ENDPOINT: &str =
     "https://eforms.eservices.cyprus.gov.cy/MCIT/MCIT/PetroleumPrices";
 
-static TOKEN_SELECTOR: &'static str = "input[name=\"__RequestVerificationToken\"]";
+static TOKEN_SELECTOR: &str = "input[name=\"__RequestVerificationToken\"]";
 
-static PRICES_SELECTOR: &'static str = "#petroleumPriceDetailsFootable";
+static PRICES_SELECTOR: &str = "#petroleumPriceDetailsFootable";
+
+// server side pagers, footable's own pager only hides rows that are all in the markup already
+static NEXT_PAGE_SELECTOR: &str = ".pagination a[rel=\"next\"], .pagination .next a, .footable-paging [data-page=\"next\"] a";
+
+const SNIPPET_LENGTH: usize = 200;
+
+// compiled once instead of on every parse
+pub(crate) struct Selectors {
+    pub(crate) token: Selector,
+    pub(crate) prices: Selector,
+    tbody: Selector,
+    tr: Selector,
+    td: Selector,
+    a: Selector,
+    next_page: Selector,
+}
+
+pub(crate) fn selectors() -> &'static Selectors {
+    static SELECTORS: OnceLock<Selectors> = OnceLock::new();
+    SELECTORS.get_or_init(|| Selectors {
+        token: Selector::parse(TOKEN_SELECTOR).unwrap(),
+        prices: Selector::parse(PRICES_SELECTOR).unwrap(),
+        tbody: Selector::parse("tbody").unwrap(),
+        tr: Selector::parse("tr").unwrap(),
+        td: Selector::parse("td").unwrap(),
+        a: Selector::parse("a").unwrap(),
+        next_page: Selector::parse(NEXT_PAGE_SELECTOR).unwrap(),
+    })
+}
 
 #[derive(Clone, Debug)]
 pub struct CyGazError(String);
@@ -35,27 +225,178 @@ impl Display for CyGazError {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+/// Availability of a station, inferred from how long upstream has been flagging it offline.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum StationStatus {
+    Open,
+    TemporarilyOffline,
+    Closed,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[non_exhaustive]
 pub struct PetroleumStation {
-    brand: String,
-    offline: bool,
-    company: String,
-    address: String,
-    latitude: String,
-    longitude: String,
-    area: String,
-    price: f32,
-}
-
-fn extract_address(endpoint: &Url, fragment: &ElementRef) -> Result<(String, String, String), CyGazError> {
-    let a_selector = match Selector::parse("a") {
-        Ok(selector) => selector,
-        Err(err) => {
-            return Err(CyGazError(format!("Parse error {}", err)));
+    #[serde(default)]
+    pub station_id: String,
+    pub brand: String,
+    pub offline: bool,
+    pub company: String,
+    pub address: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub area: String,
+    pub price: f32,
+    // not part of the upstream listing, filled in by whoever tracks the station over time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StationStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_since: Option<u128>,
+    // since when upstream has been flagging it offline, None while it is not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_since: Option<u128>,
+    // the listed price was unusable, this is the last valid one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub carried_forward: bool,
+    // the price is far off the district median, see quality::flag_outliers
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub outlier: bool,
+    // only after CyGazClient::fetch_station_details
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<StationDetails>,
+    // only when the service is asked to break prices down
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vat: Option<VatBreakdown>,
+    // page the address links to
+    #[serde(skip)]
+    pub details_url: Option<Url>,
+}
+
+impl PetroleumStation {
+    /// A station listed at `price`, the rest blank until given with the `with_` methods.
+    pub fn new(station_id: impl Into<String>, price: f32) -> Self {
+        PetroleumStation {
+            station_id: station_id.into(),
+            price,
+            ..Default::default()
         }
-    };
+    }
+
+    pub fn with_brand(mut self, brand: impl Into<String>) -> Self {
+        self.brand = brand.into();
+        self
+    }
+
+    pub fn with_company(mut self, company: impl Into<String>) -> Self {
+        self.company = company.into();
+        self
+    }
+
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    pub fn with_area(mut self, area: impl Into<String>) -> Self {
+        self.area = area.into();
+        self
+    }
+
+    /// Coordinates as upstream lists them, in degrees.
+    pub fn with_coordinates(
+        mut self,
+        latitude: impl Into<String>,
+        longitude: impl Into<String>,
+    ) -> Self {
+        self.latitude = latitude.into();
+        self.longitude = longitude.into();
+        self
+    }
+
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// `status` since `since`, in milliseconds.
+    pub fn with_status(mut self, status: StationStatus, since: u128) -> Self {
+        self.status = Some(status);
+        self.status_since = Some(since);
+        self
+    }
+}
+
+pub type AreasByDistrict = BTreeMap<District, Vec<String>>;
+
+/// Identifier of a station that stays the same across fuel types, refreshes and restarts,
+/// derived from its case and accent folded brand, company and address.
+pub fn station_id(brand: &str, company: &str, address: &str) -> String {
+    // FNV-1a, unlike the std hasher its output is fixed across Rust releases
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in [brand, company, address] {
+        for byte in normalize::fold(part).bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
 
-    let a_tag = match fragment.select(&a_selector).next() {
+/// A table row that was skipped while parsing, with enough context to debug the upstream markup.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[non_exhaustive]
+pub struct ParseWarning {
+    pub row: usize,
+    pub reason: String,
+    pub snippet: String,
+    // set when the row named a station but its price was unusable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub station_id: Option<String>,
+}
+
+impl ParseWarning {
+    pub fn new(
+        row: usize,
+        reason: impl Into<String>,
+        snippet: impl Into<String>,
+        station_id: Option<String>,
+    ) -> Self {
+        ParseWarning {
+            row,
+            reason: reason.into(),
+            snippet: snippet.into(),
+            station_id,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[non_exhaustive]
+pub struct PriceResult {
+    pub stations: Vec<PetroleumStation>,
+    pub warnings: Vec<ParseWarning>,
+    // table rows seen over every page, parsed or not
+    #[serde(default)]
+    pub total_rows: usize,
+}
+
+impl PriceResult {
+    /// Adds the rows of the following page, numbering its warnings after the rows seen so far.
+    pub(crate) fn append(&mut self, page: PriceResult) {
+        let offset = self.total_rows;
+        self.stations.extend(page.stations);
+        self.warnings
+            .extend(page.warnings.into_iter().map(|warning| ParseWarning {
+                row: warning.row + offset,
+                ..warning
+            }));
+        self.total_rows += page.total_rows;
+    }
+}
+
+fn extract_address(endpoint: &Url, fragment: &ElementRef) -> Result<(String, String, String, Url), CyGazError> {
+    let a_tag = match fragment.select(&selectors().a).next() {
         Some(addr) => addr,
         None => {
             return Err(CyGazError(format!("Select error for address {:?}", fragment.clone())));
@@ -63,157 +404,437 @@ fn extract_address(endpoint: &Url, fragment: &ElementRef) -> Result<(String, Str
     };
 
     let address = a_tag.inner_html();
-    let href = a_tag.value().attr("href").unwrap();
-    let url = Url::parse(endpoint.join(href).unwrap().as_str()).unwrap();
-    let qs = url.query_pairs().collect::<Vec<_>>();
-    let (_key, val) = qs
-        .into_iter()
+    let href = a_tag
+        .value()
+        .attr("href")
+        .ok_or_else(|| CyGazError(format!("Missing link for address {:?}", address)))?;
+    let url = endpoint
+        .join(href)
+        .map_err(|err| CyGazError(format!("Invalid link {:?}: {}", href, err)))?;
+    let (_key, val) = url
+        .query_pairs()
         .find(|(key, _v)| key == "coordinates")
-        .unwrap();
+        .ok_or_else(|| CyGazError(format!("Missing coordinates in link {:?}", href)))?;
     let mut coordinates = val.split(",").collect::<Vec<_>>();
     if coordinates.len() == 1 {
         coordinates = val.split(" ").collect::<Vec<_>>();
     }
 
-    Ok((
-        address,
-        coordinates[0].to_string(),
-        coordinates[1].to_string(),
-    ))
+    match coordinates[..] {
+        [lat, lon] if lat.trim().parse::<f64>().is_ok() && lon.trim().parse::<f64>().is_ok() => {
+            Ok((address, lat.to_string(), lon.to_string(), url))
+        }
+        _ => Err(CyGazError(format!("Invalid coordinates {:?}", val))),
+    }
 }
 
+// kept for callers from before CyGazClient, every call builds a new client and session
+
+/// Nationwide stations of `petroleum_type`, as [`CyGazClient::fetch_prices`] for [`District::All`].
+#[deprecated(note = "use CyGazClient::fetch_prices, which also returns the parse warnings")]
 pub fn fetch_prices(petroleum_type: PetroleumType) -> Result<Vec<PetroleumStation>, CyGazError> {
-    let client = reqwest::blocking::Client::builder()
-        .cookie_store(true)
-        .build()
-        .unwrap();
-
-    let response = client
-        .get(PETROLEUM_PRICES_ENDPOINT)
-        .header(USER_AGENT, USER_AGENT_VALUE)
-        .send();
-    if response.is_err() {
-        return Err(CyGazError(response.unwrap_err().to_string()));
-    }
-
-    let body = response.unwrap().text();
-    if body.is_err() {
-        return Err(CyGazError(body.unwrap_err().to_string()));
-    }
-
-    let document = Html::parse_fragment(body.unwrap().as_str());
-    let token_selector = Selector::parse(TOKEN_SELECTOR).unwrap();
-    let el = document.select(&token_selector).next().unwrap();
-    let token = el.value().attr("value").unwrap();
-
-    let form_data = [
-        ("__RequestVerificationToken", &token.to_string()),
-        ("Entity.StationCityEnum", &"All".to_string()),
-        (
-            "Entity.PetroleumType",
-            &format!("{}", petroleum_type as i32),
-        ),
-        ("Entity.StationDistrict", &"".to_string()),
-    ];
+    let client = CyGazClient::new()?;
+    nationwide_stations(petroleum_type, |petroleum_type, district| {
+        client.fetch_prices(petroleum_type, district)
+    })
+}
+
+// what `fetch_prices` answers with from `fetch`, the client normally
+fn nationwide_stations(
+    petroleum_type: PetroleumType,
+    fetch: impl FnOnce(PetroleumType, District) -> Result<PriceResult, CyGazError>,
+) -> Result<Vec<PetroleumStation>, CyGazError> {
+    fetch(petroleum_type, District::All).map(|result| result.stations)
+}
+
+/// Same as [`CyGazClient::fetch_prices`] on a new client.
+#[deprecated(note = "use CyGazClient::fetch_prices")]
+pub fn fetch_prices_for_district(
+    petroleum_type: PetroleumType,
+    district: District,
+) -> Result<PriceResult, CyGazError> {
+    CyGazClient::new()?.fetch_prices(petroleum_type, district)
+}
+
+/// Same as [`CyGazClient::fetch_all_areas`] on a new client.
+#[deprecated(note = "use CyGazClient::fetch_all_areas")]
+pub fn fetch_all_areas() -> Result<AreasByDistrict, CyGazError> {
+    CyGazClient::new()?.fetch_all_areas()
+}
 
-    let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+struct RowError {
+    reason: CyGazError,
+    // known when only the price was unusable
+    station_id: Option<String>,
+}
 
-    let prices_response = client
-        .post(PETROLEUM_PRICES_ENDPOINT)
-        .header(USER_AGENT, USER_AGENT_VALUE)
-        .form(&form_data)
-        .send();
-    if prices_response.is_err() {
-        return Err(CyGazError(prices_response.unwrap_err().to_string()));
+impl From<CyGazError> for RowError {
+    fn from(reason: CyGazError) -> Self {
+        RowError {
+            reason,
+            station_id: None,
+        }
     }
+}
 
-    let prices_body = prices_response.unwrap().text();
-    if prices_body.is_err() {
-        return Err(CyGazError(prices_body.unwrap_err().to_string()));
+fn parse_price(price_txt: &str) -> Result<f32, CyGazError> {
+    let price = price_txt
+        .parse::<f32>()
+        .map_err(|err| CyGazError(format!("Invalid price {:?}: {}", price_txt, err)))?;
+    if !price.is_finite() || price <= 0.0 {
+        return Err(CyGazError(format!("Invalid price {:?}", price_txt)));
     }
+    Ok(price)
+}
+
+fn parse_row(endpoint: &Url, tr: &ElementRef) -> Result<PetroleumStation, RowError> {
+    let mut tds = tr.select(&selectors().td);
+
+    let mut next_td = |name: &str| {
+        tds.next()
+            .ok_or_else(|| CyGazError(format!("Missing {} column", name)))
+    };
+
+    let brand = next_td("brand")?;
+    let offline = brand.value().classes().any(|c| c == "isOffLine");
+    let company = next_td("company")?;
+    let address = next_td("address")?;
+    let area = next_td("area")?;
+    let price = next_td("price")?;
+
+    let (address_txt, address_lat, address_lon, details_url) = extract_address(endpoint, &address)?;
+
+    let brand = trimmed(brand.inner_html());
+    let company = trimmed(company.inner_html());
+    let station_id = station_id(&brand, &company, &address_txt);
+
+    let price = match parse_price(text(&price).trim()) {
+        Ok(price) => price,
+        Err(reason) => {
+            return Err(RowError {
+                reason,
+                station_id: Some(station_id),
+            })
+        }
+    };
 
-    let mut stations: Vec<PetroleumStation> = Vec::new();
+    Ok(PetroleumStation {
+        station_id,
+        brand,
+        offline,
+        company,
+        address: address_txt,
+        latitude: address_lat,
+        longitude: address_lon,
+        area: trimmed(area.inner_html()),
+        price,
+        status: None,
+        status_since: None,
+        offline_since: None,
+        carried_forward: false,
+        outlier: false,
+        details: None,
+        vat: None,
+        details_url: Some(details_url),
+    })
+}
 
-    let prices_document = Html::parse_fragment(prices_body.unwrap().as_str());
-    let table_selector = Selector::parse(PRICES_SELECTOR).unwrap();
-    let table_tbody_select = Selector::parse("tbody").unwrap();
-    let table_tr_select = Selector::parse("tr").unwrap();
-    let table_td_select = Selector::parse("td").unwrap();
-    for table in prices_document.select(&table_selector) {
-        for tbody in table.select(&table_tbody_select) {
-            for tr in tbody.select(&table_tr_select) {
-                let mut tds = tr.select(&table_td_select);
+// text of a cell, borrowed from the document unless it is split over several nodes
+fn text<'a>(element: &ElementRef<'a>) -> Cow<'a, str> {
+    let mut texts = element.text();
+    match (texts.next(), texts.next()) {
+        (None, _) => Cow::Borrowed(""),
+        (Some(first), None) => Cow::Borrowed(first),
+        (Some(first), Some(second)) => Cow::Owned([first, second].into_iter().chain(texts).collect()),
+    }
+}
 
-                let brand = tds.next().unwrap();
-                // println!("brand {}", brand.inner_html().trim());
+// trims without copying when there is nothing to trim
+fn trimmed(mut html: String) -> String {
+    let end = html.trim_end().len();
+    html.truncate(end);
+    match html.len() - html.trim_start().len() {
+        0 => html,
+        start => html.split_off(start),
+    }
+}
 
-                let offline = brand.value().classes().find(|c| *c == "isOffLine");
-                // println!("offline {}", offline.is_some());
+fn snippet(html: &str) -> String {
+    match html.char_indices().nth(SNIPPET_LENGTH) {
+        Some((idx, _)) => format!("{}...", &html[..idx]),
+        None => html.to_string(),
+    }
+}
 
-                let company = tds.next().unwrap();
-                // println!("company {}", company.inner_html().trim());
+struct PriceRows {
+    endpoint: Url,
+    document: Html,
+    rows: std::vec::IntoIter<NodeId>,
+    row: usize,
+}
 
-                let address = tds.next().unwrap();
-                let (address_txt, address_lat, address_lon) = match extract_address(&endpoint, &address) {
-                    Ok(result) => result,
-                    Err(_) => {
-                        // println!("error {}", err);
-                        continue;
-                    }
-                };
+impl Iterator for PriceRows {
+    type Item = Result<PetroleumStation, ParseWarning>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.rows.next()?;
+        let row = self.row;
+        self.row += 1;
+
+        let tr = self.document.tree.get(id).and_then(ElementRef::wrap)?;
+        Some(parse_row(&self.endpoint, &tr).map_err(|err| ParseWarning {
+            row,
+            reason: err.reason.0,
+            snippet: snippet(tr.html().as_str()),
+            station_id: err.station_id,
+        }))
+    }
+}
 
-                let area = tds.next().unwrap();
-                // println!("area {}", area.inner_html().trim());
+/// Rows of the prices table, turned into stations one at a time as the iterator advances.
+/// The markup itself is parsed upfront.
+pub fn parse_prices_iter(
+    endpoint: &Url,
+    body: &str,
+) -> impl Iterator<Item = Result<PetroleumStation, ParseWarning>> {
+    price_rows(endpoint, Html::parse_fragment(body))
+}
 
-                let price = tds.next().unwrap();
-                // println!("price {}", price.inner_html().trim().parse::<f32>().unwrap());
+fn price_rows(endpoint: &Url, document: Html) -> PriceRows {
+    let selectors = selectors();
+    let rows = document
+        .select(&selectors.prices)
+        .flat_map(|table| table.select(&selectors.tbody))
+        .flat_map(|tbody| tbody.select(&selectors.tr))
+        .map(|tr| tr.id())
+        .collect::<Vec<_>>();
+
+    PriceRows {
+        endpoint: endpoint.clone(),
+        document,
+        rows: rows.into_iter(),
+        row: 0,
+    }
+}
 
-                let station = PetroleumStation {
-                    brand: brand.inner_html().trim().to_string(),
-                    offline: offline.is_some(),
-                    company: company.inner_html().trim().to_string(),
-                    address: address_txt,
-                    latitude: address_lat,
-                    longitude: address_lon,
-                    area: area.inner_html().trim().to_string(),
-                    price: price.inner_html().trim().parse::<f32>().unwrap(),
-                };
+/// Link to the next page of a paginated prices table.
+pub(crate) fn next_page(endpoint: &Url, document: &Html) -> Option<Url> {
+    document
+        .select(&selectors().next_page)
+        .filter_map(|a| a.value().attr("href"))
+        .map(str::trim)
+        .find(|href| !href.is_empty() && !href.starts_with('#') && !href.starts_with("javascript:"))
+        .and_then(|href| endpoint.join(href).ok())
+}
 
-                stations.push(station);
-            }
+pub(crate) fn parse_document(endpoint: &Url, document: Html) -> PriceResult {
+    let mut result = PriceResult::default();
+    for row in price_rows(endpoint, document) {
+        result.total_rows += 1;
+        match row {
+            Ok(station) => result.stations.push(station),
+            Err(warning) => result.warnings.push(warning),
         }
     }
+    result
+}
 
-    Ok(stations)
+/// Parses the prices table, collecting a warning for every row that could not be turned into a station.
+pub fn parse_prices(endpoint: &Url, body: &str) -> PriceResult {
+    parse_document(endpoint, Html::parse_fragment(body))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{fetch_prices, PetroleumType};
+    use url::Url;
+
+    use scraper::Html;
+
+    #[allow(deprecated)]
+    use crate::fetch_prices;
+    use crate::{
+        nationwide_stations, next_page, parse_prices, parse_prices_iter, CyGazError, District,
+        PetroleumType, PETROLEUM_PRICES_ENDPOINT,
+    };
+
+    static PARTIAL_TABLE: &str = r#"
+        <table id="petroleumPriceDetailsFootable"><tbody>
+            <tr>
+                <td class="isOffLine">EKO</td>
+                <td>Company LTD</td>
+                <td><a href="Map?coordinates=35.1,33.3">Street 1</a></td>
+                <td>Strovolos</td>
+                <td>1.389</td>
+            </tr>
+            <tr>
+                <td>BP</td>
+                <td>Other LTD</td>
+                <td>No link</td>
+                <td>Aglantzia</td>
+                <td>1.399</td>
+            </tr>
+            <tr>
+                <td>Esso</td>
+            </tr>
+        </tbody></table>"#;
+
+    static MALFORMED_LINKS: &str = r#"
+        <table id="petroleumPriceDetailsFootable"><tbody>
+            <tr><td>EKO</td><td>A</td><td><a>No href</a></td><td>X</td><td>1.3</td></tr>
+            <tr><td>EKO</td><td>A</td><td><a href="Map?zoom=3">No coordinates</a></td><td>X</td><td>1.3</td></tr>
+            <tr><td>EKO</td><td>A</td><td><a href="Map?coordinates=35.1">One part</a></td><td>X</td><td>1.3</td></tr>
+            <tr><td>EKO</td><td>A</td><td><a href="Map?coordinates=a,b">Not numbers</a></td><td>X</td><td>1.3</td></tr>
+            <tr><td>EKO</td><td>A</td><td><a href="http://[::1">Bad url</a></td><td>X</td><td>1.3</td></tr>
+        </tbody></table>"#;
+
+    #[test]
+    fn parse_collects_row_warnings() {
+        let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+        let result = parse_prices(&endpoint, PARTIAL_TABLE);
+
+        assert_eq!(result.stations.len(), 1);
+        assert!(result.stations[0].offline);
+        assert_eq!(result.stations[0].latitude, "35.1");
+        assert_eq!(result.stations[0].price, 1.389);
+
+        assert_eq!(result.warnings.len(), 2);
+        assert_eq!(result.warnings[0].row, 1);
+        assert!(result.warnings[0].snippet.contains("Other LTD"));
+        assert_eq!(result.warnings[1].row, 2);
+        assert_eq!(result.warnings[1].reason, "Missing company column");
+    }
+
+    #[test]
+    fn follows_next_page_links() {
+        let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+
+        let paged = Html::parse_fragment(
+            r#"<ul class="pagination"><li><a href="?page=1">1</a></li><li class="next"><a href="?page=2">»</a></li></ul>"#,
+        );
+        assert_eq!(
+            next_page(&endpoint, &paged).unwrap().as_str(),
+            "https://eforms.eservices.cyprus.gov.cy/MCIT/MCIT/PetroleumPrices?page=2"
+        );
+
+        let last = Html::parse_fragment(
+            r##"<ul class="pagination"><li class="next"><a href="#">»</a></li></ul>"##,
+        );
+        assert!(next_page(&endpoint, &last).is_none());
+
+        let mut result = parse_prices(&endpoint, PARTIAL_TABLE);
+        assert_eq!(result.total_rows, 3);
+        result.append(parse_prices(&endpoint, PARTIAL_TABLE));
+        assert_eq!(result.total_rows, 6);
+        assert_eq!(result.stations.len(), 2);
+        assert_eq!(
+            result.warnings.iter().map(|w| w.row).collect::<Vec<_>>(),
+            vec![1, 2, 4, 5]
+        );
+    }
+
+    #[test]
+    fn iterator_stops_early() {
+        let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+        let mut rows = parse_prices_iter(&endpoint, PARTIAL_TABLE);
+
+        assert_eq!(rows.next().unwrap().unwrap().brand, "EKO");
+        assert_eq!(rows.next().unwrap().unwrap_err().row, 1);
+    }
+
+    #[test]
+    fn invalid_prices_name_the_station() {
+        let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+        let table = ["-", "NaN", "0", "inf"]
+            .map(|price| {
+                format!(
+                    r#"<tr><td>EKO</td><td>A</td><td><a href="Map?coordinates=35.1,33.3">Street 1</a></td><td>X</td><td>{}</td></tr>"#,
+                    price
+                )
+            })
+            .join("");
+        let result = parse_prices(
+            &endpoint,
+            &format!(r#"<table id="petroleumPriceDetailsFootable"><tbody>{}</tbody></table>"#, table),
+        );
+
+        assert!(result.stations.is_empty());
+        assert_eq!(result.warnings.len(), 4);
+        assert!(result.warnings.iter().all(|w| w.station_id.is_some()));
+    }
+
+    #[test]
+    fn parse_reports_malformed_links() {
+        let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+        let result = parse_prices(&endpoint, MALFORMED_LINKS);
+
+        assert!(result.stations.is_empty());
+        let reasons = result.warnings.iter().map(|w| w.reason.as_str()).collect::<Vec<_>>();
+        assert!(reasons[0].starts_with("Missing link"));
+        assert!(reasons[1].starts_with("Missing coordinates"));
+        assert!(reasons[2].starts_with("Invalid coordinates"));
+        assert!(reasons[3].starts_with("Invalid coordinates"));
+        assert!(reasons[4].starts_with("Invalid link"));
+    }
+
+    #[test]
+    fn fetch_prices_answers_with_the_nationwide_stations() {
+        let endpoint = Url::parse(PETROLEUM_PRICES_ENDPOINT).unwrap();
+        let parsed = parse_prices(&endpoint, PARTIAL_TABLE);
+        assert!(!parsed.warnings.is_empty());
+
+        let stations = nationwide_stations(PetroleumType::Kerosene, |petroleum_type, district| {
+            assert_eq!(
+                (petroleum_type, district),
+                (PetroleumType::Kerosene, District::All)
+            );
+            Ok(parse_prices(&endpoint, PARTIAL_TABLE))
+        });
+        assert_eq!(stations.unwrap(), parsed.stations);
+
+        let failed = nationwide_stations(PetroleumType::Kerosene, |_, _| {
+            Err(CyGazError("timed out".to_string()))
+        });
+        assert_eq!(failed.unwrap_err().to_string(), "timed out");
+    }
+
+    #[test]
+    fn keys_are_names_from_name_takes() {
+        for petroleum_type in PetroleumType::ALL {
+            assert_eq!(PetroleumType::from_name(petroleum_type.key()), Some(petroleum_type));
+        }
+        for district in [District::All].into_iter().chain(District::DISTRICTS) {
+            assert_eq!(District::from_name(district.key()), Some(district));
+        }
+    }
 
     #[test]
+    #[allow(deprecated)]
     fn e2e_unlead_95_prices_for_cyprus() {
         let stations = fetch_prices(PetroleumType::Unlead95).unwrap_or_default();
-        assert!(stations.len() > 0);
+        assert!(!stations.is_empty());
     }
     #[test]
+    #[allow(deprecated)]
     fn e2e_unlead_98_prices_for_cyprus() {
         let stations = fetch_prices(PetroleumType::Unlead98).unwrap_or_default();
-        assert!(stations.len() > 0);
+        assert!(!stations.is_empty());
     }
     #[test]
+    #[allow(deprecated)]
     fn e2e_diesel_heat_prices_for_cyprus() {
         let stations = fetch_prices(PetroleumType::DieselHeat).unwrap_or_default();
-        assert!(stations.len() > 0);
+        assert!(!stations.is_empty());
     }
     #[test]
+    #[allow(deprecated)]
     fn e2e_diesel_auto_prices_for_cyprus() {
         let stations = fetch_prices(PetroleumType::DieselAuto).unwrap_or_default();
-        assert!(stations.len() > 0);
+        assert!(!stations.is_empty());
     }
     #[test]
+    #[allow(deprecated)]
     fn e2e_kerosene_prices_for_cyprus() {
         let stations = fetch_prices(PetroleumType::Kerosene).unwrap_or_default();
-        assert!(stations.len() > 0);
+        assert!(!stations.is_empty());
     }
 }
@@ -0,0 +1,523 @@
+//! Greek aware text normalization used for matching, searching and slugs.
+
+use std::collections::HashMap;
+
+fn is_greek_vowel(c: char) -> bool {
+    matches!(
+        c,
+        'α' | 'ε' | 'η' | 'ι' | 'ο' | 'υ' | 'ω' | 'Α' | 'Ε' | 'Η' | 'Ι' | 'Ο' | 'Υ' | 'Ω'
+    )
+}
+
+/// Removes the tonos from a letter, keeping the dialytika.
+fn strip_tonos(c: char) -> char {
+    match c {
+        'ά' => 'α',
+        'έ' => 'ε',
+        'ή' => 'η',
+        'ί' => 'ι',
+        'ό' => 'ο',
+        'ύ' => 'υ',
+        'ώ' => 'ω',
+        'ΐ' => 'ϊ',
+        'ΰ' => 'ϋ',
+        'Ά' => 'Α',
+        'Έ' => 'Ε',
+        'Ή' => 'Η',
+        'Ί' => 'Ι',
+        'Ό' => 'Ο',
+        'Ύ' => 'Υ',
+        'Ώ' => 'Ω',
+        _ => c,
+    }
+}
+
+/// Removes both tonos and dialytika from a letter.
+fn strip_diacritics(c: char) -> char {
+    match strip_tonos(c) {
+        'ϊ' => 'ι',
+        'ϋ' => 'υ',
+        'Ϊ' => 'Ι',
+        'Ϋ' => 'Υ',
+        other => other,
+    }
+}
+
+pub fn strip_accents(value: &str) -> String {
+    value.chars().map(strip_diacritics).collect()
+}
+
+/// Lowercase with a final `ς` at the end of every word.
+pub fn to_lower(value: &str) -> String {
+    let chars = value.to_lowercase().chars().collect::<Vec<_>>();
+    let mut lower = String::with_capacity(value.len());
+    for (idx, c) in chars.iter().enumerate() {
+        let previous_is_letter = idx > 0 && chars[idx - 1].is_alphabetic();
+        let next_is_letter = chars.get(idx + 1).is_some_and(|n| n.is_alphabetic());
+        match c {
+            'σ' if previous_is_letter && !next_is_letter => lower.push('ς'),
+            'ς' if next_is_letter => lower.push('σ'),
+            _ => lower.push(*c),
+        }
+    }
+    lower
+}
+
+/// Uppercase the way Greek is written in capitals: no tonos, but a dialytika where
+/// the accent used to split a diphthong (`Κάιρο` becomes `ΚΑΪΡΟ`).
+pub fn to_upper(value: &str) -> String {
+    let chars = value.chars().collect::<Vec<_>>();
+    let mut upper = String::with_capacity(value.len());
+    for (idx, c) in chars.iter().enumerate() {
+        let previous = idx.checked_sub(1).map(|p| chars[p]);
+        let splits_diphthong = previous.is_some_and(|p| strip_tonos(p) != p && is_greek_vowel(strip_tonos(p)));
+        let c = match c {
+            'ι' if splits_diphthong => 'ϊ',
+            'υ' if splits_diphthong => 'ϋ',
+            _ => strip_tonos(*c),
+        };
+        upper.extend(c.to_uppercase());
+    }
+    upper
+}
+
+/// Comparison key: lowercase, accents removed and every sigma in its medial form,
+/// so that `ΛΕΜΕΣΟΣ`, `Λεμεσός` and `λεμεσοσ` all compare equal.
+pub fn fold(value: &str) -> String {
+    strip_accents(value.trim())
+        .to_lowercase()
+        .replace('ς', "σ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// URL safe identifier built from the folded form.
+pub fn slug(value: &str) -> String {
+    let mut slug = String::with_capacity(value.len());
+    for c in fold(value).chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+fn romanize(c: char) -> Option<&'static str> {
+    Some(match c {
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' => "e",
+        'ζ' => "z",
+        'η' => "i",
+        'θ' => "th",
+        'ι' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' => "o",
+        _ => return None,
+    })
+}
+
+/// Letter by letter Latin rendering of Greek text, keeping the capitalization.
+pub fn transliterate(value: &str) -> String {
+    let chars = strip_accents(value).chars().collect::<Vec<_>>();
+    let mut latin = String::with_capacity(value.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let next = chars.get(idx + 1).and_then(|n| n.to_lowercase().next());
+
+        let (mapped, consumed) = match (lower, next) {
+            ('ο', Some('υ')) => (Some("ou"), 2),
+            _ => (romanize(lower), 1),
+        };
+
+        match mapped {
+            Some(mapped) if c.is_uppercase() => {
+                let mut letters = mapped.chars();
+                latin.extend(letters.next().unwrap().to_uppercase());
+                latin.push_str(letters.as_str());
+            }
+            Some(mapped) => latin.push_str(mapped),
+            None => latin.push(c),
+        }
+        idx += consumed;
+    }
+    latin
+}
+
+// written alike in Greeklish, longest first
+static GREEKLISH_DIGRAPHS: &[(&str, &str)] = &[
+    ("th", "8"),
+    ("ch", "x"),
+    ("ph", "f"),
+    ("ks", "x"),
+    ("mp", "b"),
+    ("ou", "u"),
+    ("ai", "e"),
+    ("ei", "i"),
+    ("oi", "i"),
+];
+
+/// Search key that Greek and its usual Greeklish spellings share: transliterated, with the
+/// letters Greeklish writes in several ways merged and doubled letters single, so that
+/// `Αμμόχωστος`, `Ammochostos` and `ammoxwstos` all have the key `amoxostos`.
+pub fn greeklish_key(value: &str) -> String {
+    let mut latin = transliterate(&fold(value)).to_lowercase();
+    for (from, to) in GREEKLISH_DIGRAPHS {
+        latin = latin.replace(from, to);
+    }
+    let mut key = String::with_capacity(latin.len());
+    for c in latin.chars() {
+        let c = match c {
+            'h' | 'y' => 'i',
+            'w' => 'o',
+            'b' => 'v',
+            'c' => 'k',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        };
+        if !key.ends_with(c) || c == ' ' {
+            key.push(c);
+        }
+    }
+    key.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// letters after which αυ, ευ and ηυ read av, ev and iv rather than af, ef and if
+fn is_voiced(c: Option<char>) -> bool {
+    matches!(c, Some('β' | 'γ' | 'δ' | 'ζ' | 'λ' | 'μ' | 'ν' | 'ρ')) || c.is_some_and(is_greek_vowel)
+}
+
+/// Latin rendering of Greek text following ELOT 743, the romanization of Greek road signs
+/// and official documents: `ευ` before a voiced sound is `ev` and `ef` otherwise, `γγ` is
+/// `ng`, `μπ` is `b` at either end of a word, and capitals stay capitals (`ΘΕΣΗ` is `THESI`,
+/// `Θέση` is `Thesi`).
+pub fn transliterate_elot743(value: &str) -> String {
+    let chars = value.chars().collect::<Vec<_>>();
+    // lowercase without tonos, keeping the dialytika that splits a diphthong
+    let base = chars
+        .iter()
+        .map(|c| strip_tonos(c.to_lowercase().next().unwrap_or(*c)))
+        .collect::<Vec<_>>();
+    let is_letter = |idx: Option<usize>| idx.and_then(|idx| base.get(idx)).is_some_and(|c| c.is_alphabetic());
+
+    let mut latin = String::with_capacity(value.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        let next = base.get(idx + 1).copied();
+        let after = base.get(idx + 2).copied();
+        let at_word_edge = !is_letter(idx.checked_sub(1)) || !is_letter(Some(idx + 2));
+
+        let (mapped, consumed) = match (base[idx], next) {
+            ('ο', Some('υ')) => ("ou".to_string(), 2),
+            (vowel @ ('α' | 'ε' | 'η'), Some('υ')) => {
+                let vowel = romanize(vowel).unwrap_or_default();
+                let consonant = if is_voiced(after) { "v" } else { "f" };
+                (format!("{}{}", vowel, consonant), 2)
+            }
+            ('γ', Some('γ')) => ("ng".to_string(), 2),
+            ('γ', Some('ξ')) => ("nx".to_string(), 2),
+            ('γ', Some('χ')) => ("nch".to_string(), 2),
+            ('μ', Some('π')) if at_word_edge => ("b".to_string(), 2),
+            (c, _) => match romanize(strip_diacritics(c)) {
+                Some(mapped) => (mapped.to_string(), 1),
+                None => (chars[idx].to_string(), 1),
+            },
+        };
+
+        let capital = chars[idx].is_uppercase() && base[idx] != chars[idx];
+        let neighbour_capital = |idx: Option<usize>| {
+            idx.and_then(|idx| chars.get(idx))
+                .is_some_and(|c| c.is_uppercase())
+        };
+        match capital {
+            true if neighbour_capital(idx.checked_sub(1)) || neighbour_capital(Some(idx + consumed)) => {
+                latin.push_str(&mapped.to_uppercase())
+            }
+            true => {
+                let mut letters = mapped.chars();
+                latin.extend(letters.next().into_iter().flat_map(char::to_uppercase));
+                latin.push_str(letters.as_str());
+            }
+            false => latin.push_str(&mapped),
+        }
+        idx += consumed;
+    }
+    latin
+}
+
+/// Words and phrases with a fixed Latin rendering, looked up by their folded form.
+#[derive(Clone, Debug, Default)]
+pub struct Dictionary {
+    entries: HashMap<String, String>,
+}
+
+impl Dictionary {
+    pub fn new(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        Dictionary {
+            entries: entries
+                .into_iter()
+                .map(|(greek, latin)| (fold(&greek), latin))
+                .collect(),
+        }
+    }
+
+    fn get(&self, value: &str) -> Option<&str> {
+        self.entries.get(&fold(value)).map(String::as_str)
+    }
+}
+
+/// How Greek names are rendered in Latin letters.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum Transliteration {
+    /// `transliterate`, letter by letter.
+    #[default]
+    Letters,
+    /// `transliterate_elot743`.
+    Elot743,
+    /// The dictionary rendering of the whole value or else of every word, letter by letter
+    /// for words it does not know.
+    Dictionary(Dictionary),
+    /// `any_ascii`, the closest ASCII of every character.
+    AnyAscii,
+    /// ICU's `Greek-Latin/UNGEGN` transform, the UN recommendation based on ELOT 743, without
+    /// the accents it leaves.
+    #[cfg(feature = "icu")]
+    Icu,
+}
+
+impl Transliteration {
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Transliteration::Letters => transliterate(value),
+            Transliteration::Elot743 => transliterate_elot743(value),
+            Transliteration::Dictionary(dictionary) => match dictionary.get(value) {
+                Some(latin) => latin.to_string(),
+                None => value
+                    .split(' ')
+                    .map(|word| match dictionary.get(word) {
+                        Some(latin) => latin.to_string(),
+                        None => transliterate(word),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+            Transliteration::AnyAscii => any_ascii::any_ascii(value),
+            #[cfg(feature = "icu")]
+            Transliteration::Icu => transliterate_icu(value),
+        }
+    }
+}
+
+#[cfg(feature = "icu")]
+static ICU_TRANSFORM: &str = "Greek-Latin/UNGEGN; Latin-ASCII";
+
+/// `ICU_TRANSFORM` of `value`, letter by letter should ICU not have the transform.
+#[cfg(feature = "icu")]
+fn transliterate_icu(value: &str) -> String {
+    use rust_icu_sys::UTransDirection;
+    use rust_icu_utrans::UTransliterator;
+
+    thread_local! {
+        // ICU transliterators cannot move between threads, every thread builds its own
+        static TRANSLITERATOR: Option<UTransliterator> =
+            UTransliterator::new(ICU_TRANSFORM, None, UTransDirection::UTRANS_FORWARD).ok();
+    }
+    TRANSLITERATOR
+        .with(|transliterator| transliterator.as_ref()?.transliterate(value).ok())
+        .unwrap_or_else(|| transliterate(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normalize::{
+        fold, greeklish_key, slug, strip_accents, to_lower, to_upper, transliterate, transliterate_elot743,
+        Dictionary, Transliteration,
+    };
+
+    // (as written upstream, capitals, lowercase, folded)
+    static PLACES: &[(&str, &str, &str, &str)] = &[
+        ("Λευκωσία", "ΛΕΥΚΩΣΙΑ", "λευκωσία", "λευκωσια"),
+        ("Λεμεσός", "ΛΕΜΕΣΟΣ", "λεμεσός", "λεμεσοσ"),
+        ("Λάρνακα", "ΛΑΡΝΑΚΑ", "λάρνακα", "λαρνακα"),
+        ("Πάφος", "ΠΑΦΟΣ", "πάφος", "παφοσ"),
+        ("Αμμόχωστος", "ΑΜΜΟΧΩΣΤΟΣ", "αμμόχωστος", "αμμοχωστοσ"),
+        ("Στρόβολος", "ΣΤΡΟΒΟΛΟΣ", "στρόβολος", "στροβολοσ"),
+        ("Αγλαντζιά", "ΑΓΛΑΝΤΖΙΑ", "αγλαντζιά", "αγλαντζια"),
+        ("Έγκωμη", "ΕΓΚΩΜΗ", "έγκωμη", "εγκωμη"),
+        ("Λακατάμεια", "ΛΑΚΑΤΑΜΕΙΑ", "λακατάμεια", "λακαταμεια"),
+        ("Λατσιά", "ΛΑΤΣΙΑ", "λατσιά", "λατσια"),
+        ("Γέρι", "ΓΕΡΙ", "γέρι", "γερι"),
+        ("Δάλι", "ΔΑΛΙ", "δάλι", "δαλι"),
+        ("Τσέρι", "ΤΣΕΡΙ", "τσέρι", "τσερι"),
+        ("Κάτω Πολεμίδια", "ΚΑΤΩ ΠΟΛΕΜΙΔΙΑ", "κάτω πολεμίδια", "κατω πολεμιδια"),
+        ("Άγιος Αθανάσιος", "ΑΓΙΟΣ ΑΘΑΝΑΣΙΟΣ", "άγιος αθανάσιος", "αγιοσ αθανασιοσ"),
+        ("Γερμασόγεια", "ΓΕΡΜΑΣΟΓΕΙΑ", "γερμασόγεια", "γερμασογεια"),
+        ("Μέσα Γειτονιά", "ΜΕΣΑ ΓΕΙΤΟΝΙΑ", "μέσα γειτονιά", "μεσα γειτονια"),
+        ("Ύψωνας", "ΥΨΩΝΑΣ", "ύψωνας", "υψωνασ"),
+        ("Επισκοπή", "ΕΠΙΣΚΟΠΗ", "επισκοπή", "επισκοπη"),
+        ("Αραδίππου", "ΑΡΑΔΙΠΠΟΥ", "αραδίππου", "αραδιππου"),
+        ("Λιβάδια", "ΛΙΒΑΔΙΑ", "λιβάδια", "λιβαδια"),
+        ("Δρομολαξιά", "ΔΡΟΜΟΛΑΞΙΑ", "δρομολαξιά", "δρομολαξια"),
+        ("Αθηένου", "ΑΘΗΕΝΟΥ", "αθηένου", "αθηενου"),
+        ("Ορόκλινη", "ΟΡΟΚΛΙΝΗ", "ορόκλινη", "οροκλινη"),
+        ("Γεροσκήπου", "ΓΕΡΟΣΚΗΠΟΥ", "γεροσκήπου", "γεροσκηπου"),
+        ("Πέγεια", "ΠΕΓΕΙΑ", "πέγεια", "πεγεια"),
+        ("Πόλις Χρυσοχούς", "ΠΟΛΙΣ ΧΡΥΣΟΧΟΥΣ", "πόλις χρυσοχούς", "πολισ χρυσοχουσ"),
+        ("Έμπα", "ΕΜΠΑ", "έμπα", "εμπα"),
+        ("Παραλίμνι", "ΠΑΡΑΛΙΜΝΙ", "παραλίμνι", "παραλιμνι"),
+        ("Αγία Νάπα", "ΑΓΙΑ ΝΑΠΑ", "αγία νάπα", "αγια ναπα"),
+        ("Δερύνεια", "ΔΕΡΥΝΕΙΑ", "δερύνεια", "δερυνεια"),
+        ("Σωτήρα", "ΣΩΤΗΡΑ", "σωτήρα", "σωτηρα"),
+        ("Ξυλοφάγου", "ΞΥΛΟΦΑΓΟΥ", "ξυλοφάγου", "ξυλοφαγου"),
+        ("Λιοπέτρι", "ΛΙΟΠΕΤΡΙ", "λιοπέτρι", "λιοπετρι"),
+        ("Κακοπετριά", "ΚΑΚΟΠΕΤΡΙΑ", "κακοπετριά", "κακοπετρια"),
+        ("Ευρύχου", "ΕΥΡΥΧΟΥ", "ευρύχου", "ευρυχου"),
+        ("Πλάτρες", "ΠΛΑΤΡΕΣ", "πλάτρες", "πλατρεσ"),
+        ("Πελένδρι", "ΠΕΛΕΝΔΡΙ", "πελένδρι", "πελενδρι"),
+        ("Κοκκινοτριμιθιά", "ΚΟΚΚΙΝΟΤΡΙΜΙΘΙΑ", "κοκκινοτριμιθιά", "κοκκινοτριμιθια"),
+        ("Περιστερώνα", "ΠΕΡΙΣΤΕΡΩΝΑ", "περιστερώνα", "περιστερωνα"),
+        ("Αγρός", "ΑΓΡΟΣ", "αγρός", "αγροσ"),
+        ("Πισσούρι", "ΠΙΣΣΟΥΡΙ", "πισσούρι", "πισσουρι"),
+        ("Καϊμακλί", "ΚΑΪΜΑΚΛΙ", "καϊμακλί", "καιμακλι"),
+        ("Αρχάγγελος", "ΑΡΧΑΓΓΕΛΟΣ", "αρχάγγελος", "αρχαγγελοσ"),
+    ];
+
+    #[test]
+    fn corpus_uppercase() {
+        for (place, upper, _, _) in PLACES {
+            assert_eq!(to_upper(place), *upper, "{}", place);
+        }
+    }
+
+    #[test]
+    fn corpus_lowercase_keeps_final_sigma() {
+        for (place, upper, lower, _) in PLACES {
+            assert_eq!(to_lower(place), *lower, "{}", place);
+            assert_eq!(strip_accents(&to_lower(upper)), strip_accents(lower), "{}", upper);
+        }
+    }
+
+    #[test]
+    fn corpus_fold_is_case_and_accent_insensitive() {
+        for (place, upper, lower, folded) in PLACES {
+            assert_eq!(fold(place), *folded, "{}", place);
+            assert_eq!(fold(upper), *folded, "{}", upper);
+            assert_eq!(fold(lower), *folded, "{}", lower);
+        }
+    }
+
+    #[test]
+    fn uppercase_marks_split_diphthongs() {
+        assert_eq!(to_upper("Κάιρο"), "ΚΑΪΡΟ");
+        assert_eq!(to_upper("τρόλεϊ"), "ΤΡΟΛΕΪ");
+        assert_eq!(to_upper("Λεμεσού"), "ΛΕΜΕΣΟΥ");
+    }
+
+    #[test]
+    fn lowercase_fixes_misplaced_sigma() {
+        assert_eq!(to_lower("λεμεσοσ"), "λεμεσος");
+        assert_eq!(to_lower("ςτρόβολος"), "στρόβολος");
+        assert_eq!(to_lower("ΛΕΜΕΣΟΣ, ΠΑΦΟΣ"), "λεμεσος, παφος");
+    }
+
+    #[test]
+    fn transliterates_to_latin() {
+        assert_eq!(transliterate("Στρόβολος"), "Strovolos");
+        assert_eq!(transliterate("Αραδίππου"), "Aradippou");
+        assert_eq!(transliterate("Κάτω Πολεμίδια"), "Kato Polemidia");
+        assert_eq!(transliterate("Θέκλα 12"), "Thekla 12");
+        assert_eq!(transliterate("Limassol"), "Limassol");
+    }
+
+    #[test]
+    fn transliterates_following_elot743() {
+        let places = [
+            ("Λευκωσία", "Lefkosia"),
+            ("ΛΕΥΚΩΣΙΑ", "LEFKOSIA"),
+            ("Ευρύχου", "Evrychou"),
+            ("Αυγόρου", "Avgorou"),
+            ("Αρχάγγελος", "Archangelos"),
+            ("Έγκωμη", "Egkomi"),
+            ("Καϊμακλί", "Kaimakli"),
+            ("Αραδίππου", "Aradippou"),
+            ("Θέκλα 12", "Thekla 12"),
+            ("ΘΕΚΛΑ", "THEKLA"),
+            ("Μπαλάκας", "Balakas"),
+            ("Λαμπούσα", "Lampousa"),
+            ("Κάτω Πολεμίδια", "Kato Polemidia"),
+            ("Limassol", "Limassol"),
+        ];
+        for (place, latin) in places {
+            assert_eq!(transliterate_elot743(place), latin, "{}", place);
+        }
+    }
+
+    #[test]
+    fn dictionary_overrides_letters() {
+        let dictionary = Dictionary::new([
+            ("Λευκωσία".to_string(), "Nicosia".to_string()),
+            ("Κάτω Πολεμίδια".to_string(), "Kato Polemidia".to_string()),
+            ("Άγιος".to_string(), "Ayios".to_string()),
+        ]);
+        let transliteration = Transliteration::Dictionary(dictionary);
+        assert_eq!(transliteration.apply("ΛΕΥΚΩΣΙΑ"), "Nicosia");
+        assert_eq!(transliteration.apply("Κάτω Πολεμίδια"), "Kato Polemidia");
+        assert_eq!(transliteration.apply("Άγιος Αθανάσιος"), "Ayios Athanasios");
+        assert_eq!(Transliteration::Letters.apply("Άγιος Αθανάσιος"), "Agios Athanasios");
+    }
+
+    #[test]
+    fn any_ascii_renders_every_letter() {
+        assert_eq!(Transliteration::AnyAscii.apply("Ευρύχου"), "Eyrychoy");
+        assert_eq!(Transliteration::AnyAscii.apply("ΠΕΤΡΟΛΙΝΑ"), "PETROLINA");
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn icu_follows_ungegn() {
+        assert_eq!(Transliteration::Icu.apply("Ευρύχου"), "Evrychou");
+        assert_eq!(Transliteration::Icu.apply("Αρχάγγελος Πέγεια"), "Archangelos Pegeia");
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slug("Κάτω Πολεμίδια"), "κατω-πολεμιδια");
+        assert_eq!(slug("  Αγία Νάπα (Κέντρο) "), "αγια-ναπα-κεντρο");
+        assert_eq!(slug("Limassol - Old Port"), "limassol-old-port");
+        assert_eq!(slug("ΛΕΜΕΣΟΣ"), slug("Λεμεσός"));
+    }
+
+    #[test]
+    fn greeklish_spellings_share_a_key() {
+        assert_eq!(greeklish_key("Αμμόχωστος"), "amoxostos");
+        for spelling in ["Ammochostos", "ammoxwstos", "AMMOXOSTOS"] {
+            assert_eq!(greeklish_key(spelling), "amoxostos");
+        }
+        assert_eq!(greeklish_key("Αθηένου"), greeklish_key("A8hienou"));
+        assert_eq!(greeklish_key("Ύψωνας"), greeklish_key("ipsonas"));
+        assert_eq!(greeklish_key("Γερμασόγεια"), greeklish_key("germasogia"));
+        assert_eq!(greeklish_key("  Αγία Νάπα (Κέντρο) "), "agia napa kentro");
+        assert_ne!(greeklish_key("Λεμεσός"), greeklish_key("Λευκωσία"));
+    }
+}
@@ -0,0 +1,12 @@
+//! The types most users of the crate need, for a single glob import.
+//!
+//! ```
+//! use cygaz_lib::prelude::*;
+//!
+//! assert_eq!(PetroleumType::from_name("diesel_auto"), Some(PetroleumType::DieselAuto));
+//! ```
+
+pub use crate::{
+    AreasByDistrict, CyGazClient, CyGazError, District, ParseWarning, PetroleumStation, PetroleumType,
+    PriceResult, PriceUnit, StationStatus, CURRENCY,
+};
@@ -0,0 +1,150 @@
+use serde::Serialize;
+
+use crate::{PetroleumStation, PetroleumType, StationStatus};
+
+/// Scaled median absolute deviations a price may be off the median before it is flagged.
+pub const DEFAULT_OUTLIER_THRESHOLD: f32 = 5.0;
+
+// makes the median absolute deviation comparable to a standard deviation
+const MAD_SCALE: f32 = 1.4826;
+
+// the deviation never counts as smaller than this share of the median, or a district of
+// identical prices would flag a station that is a cent cheaper
+const MIN_SPREAD: f32 = 0.01;
+
+/// A station price far off the median of the stations it was compared with, most likely a
+/// typo upstream such as 0.139 or 13.9 for 1.39.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Outlier {
+    pub station_id: String,
+    pub petroleum_type: PetroleumType,
+    pub price: f32,
+    pub median: f32,
+    // scaled median absolute deviations the price is off the median
+    pub deviations: f32,
+}
+
+/// The prices medians and statistics are taken over, leaving out closed stations and those
+/// already flagged as outliers.
+pub fn counted_prices<'a>(stations: impl IntoIterator<Item = &'a PetroleumStation>) -> Vec<f32> {
+    stations
+        .into_iter()
+        .filter(|station| !station.outlier && station.status != Some(StationStatus::Closed))
+        .map(|station| station.price)
+        .collect()
+}
+
+/// The median of `values`, sorting them.
+pub fn median(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    let middle = values.len() / 2;
+    Some(match values.len() % 2 {
+        0 => (values[middle - 1] + values[middle]) / 2.0,
+        _ => values[middle],
+    })
+}
+
+/// Flags the `stations` whose price is more than `DEFAULT_OUTLIER_THRESHOLD` deviations off
+/// their median. Meant for the stations of one district.
+pub fn flag_outliers(stations: &[PetroleumStation], petroleum_type: PetroleumType) -> Vec<Outlier> {
+    flag_outliers_with(stations, petroleum_type, DEFAULT_OUTLIER_THRESHOLD)
+}
+
+/// Like `flag_outliers`, with the number of deviations a price may be off the median.
+pub fn flag_outliers_with(
+    stations: &[PetroleumStation],
+    petroleum_type: PetroleumType,
+    threshold: f32,
+) -> Vec<Outlier> {
+    let stations = stations.iter().collect::<Vec<_>>();
+    flag_outliers_among(&stations, petroleum_type, threshold)
+}
+
+/// Like `flag_outliers_with`, for stations picked out of a longer list.
+pub fn flag_outliers_among(
+    stations: &[&PetroleumStation],
+    petroleum_type: PetroleumType,
+    threshold: f32,
+) -> Vec<Outlier> {
+    let mut prices = counted_prices(stations.iter().copied());
+    let Some(median_price) = median(&mut prices) else {
+        return vec![];
+    };
+    let mut distances = prices.iter().map(|price| (price - median_price).abs()).collect::<Vec<_>>();
+    let mad = median(&mut distances).unwrap_or_default();
+    let spread = (mad * MAD_SCALE).max(median_price * MIN_SPREAD);
+
+    stations
+        .iter()
+        .filter_map(|station| {
+            let deviations = (station.price - median_price).abs() / spread;
+            (deviations > threshold).then(|| Outlier {
+                station_id: station.station_id.clone(),
+                petroleum_type,
+                price: station.price,
+                median: median_price,
+                deviations,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::quality::{counted_prices, flag_outliers, flag_outliers_with, median};
+    use crate::{PetroleumStation, PetroleumType, StationStatus};
+
+    fn stations(prices: &[f32]) -> Vec<PetroleumStation> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| PetroleumStation {
+                station_id: i.to_string(),
+                price: *price,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flags_typo_prices() {
+        let stations = stations(&[1.389, 1.399, 0.139, 1.409, 13.9, 1.379, 1.395]);
+        let outliers = flag_outliers(&stations, PetroleumType::Unlead95);
+        assert_eq!(
+            outliers.iter().map(|o| o.station_id.as_str()).collect::<Vec<_>>(),
+            vec!["2", "4"]
+        );
+        assert_eq!(outliers[0].median, 1.395);
+
+        // identical prices do not make a cent of difference an outlier
+        let stations = self::stations(&[1.40, 1.40, 1.40, 1.39]);
+        assert!(flag_outliers(&stations, PetroleumType::Unlead95).is_empty());
+        assert_eq!(flag_outliers_with(&stations, PetroleumType::Unlead95, 0.5).len(), 1);
+
+        assert!(flag_outliers(&[], PetroleumType::Unlead95).is_empty());
+    }
+
+    #[test]
+    fn counts_open_stations_only() {
+        let mut stations = stations(&[1.40, 1.30, 0.14, 1.10, 1.50]);
+        stations[2].outlier = true;
+        stations[3].status = Some(StationStatus::Closed);
+        let mut prices = counted_prices(&stations);
+        assert_eq!(prices, vec![1.40, 1.30, 1.50]);
+        assert_eq!(median(&mut prices), Some(1.40));
+        assert_eq!(prices, vec![1.30, 1.40, 1.50]);
+
+        // a closed station is still flagged, off the median of the open ones
+        stations[3].price = 13.9;
+        let outliers = flag_outliers(&stations, PetroleumType::Unlead95);
+        assert_eq!(
+            outliers.iter().map(|o| o.station_id.as_str()).collect::<Vec<_>>(),
+            vec!["2", "3"]
+        );
+        assert_eq!(outliers[0].median, 1.40);
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Spaces out upstream requests by at least `min_interval`, across every thread sharing it.
+pub struct Throttle {
+    min_interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    pub fn new(min_interval: Duration) -> Self {
+        Throttle {
+            min_interval,
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    // reserves the next free slot and returns how long the caller has to wait for it
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = match *next_slot {
+            Some(next) if next > now => next,
+            _ => now,
+        };
+        *next_slot = Some(slot + self.min_interval);
+        slot - now
+    }
+
+    /// Blocks until the caller is allowed to hit upstream.
+    pub fn wait(&self) {
+        if self.min_interval.is_zero() {
+            return;
+        }
+
+        let delay = self.reserve(Instant::now());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Throttle::new(DEFAULT_MIN_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::Throttle;
+
+    #[test]
+    fn reserves_consecutive_slots() {
+        let throttle = Throttle::new(Duration::from_millis(100));
+        let now = Instant::now();
+
+        assert_eq!(throttle.reserve(now), Duration::ZERO);
+        assert_eq!(throttle.reserve(now), Duration::from_millis(100));
+        assert_eq!(throttle.reserve(now), Duration::from_millis(200));
+        assert_eq!(
+            throttle.reserve(now + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::PetroleumType;
+
+/// VAT rate in effect from `effective_from` (milliseconds) on, for one petroleum type or,
+/// without one, for every type.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct VatRate {
+    pub effective_from: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub petroleum_type: Option<PetroleumType>,
+    pub rate: f32,
+}
+
+impl VatRate {
+    pub fn new(effective_from: u128, petroleum_type: Option<PetroleumType>, rate: f32) -> Self {
+        VatRate {
+            effective_from,
+            petroleum_type,
+            rate,
+        }
+    }
+}
+
+/// Split of a gross price into its net part and VAT.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct VatBreakdown {
+    pub rate: f32,
+    pub net: f32,
+    pub vat: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct VatTable {
+    rates: Vec<VatRate>,
+}
+
+impl VatTable {
+    pub fn new(mut rates: Vec<VatRate>) -> Self {
+        rates.sort_by_key(|rate| rate.effective_from);
+        VatTable { rates }
+    }
+
+    /// Standard Cypriot VAT rates, which fuel is charged at.
+    pub fn cyprus() -> Self {
+        let rate = |effective_from, rate| VatRate::new(effective_from, None, rate);
+        VatTable::new(vec![
+            // 2012-03-01
+            rate(1330560000000, 0.17),
+            // 2013-01-14
+            rate(1358121600000, 0.18),
+            // 2014-01-13
+            rate(1389571200000, 0.19),
+        ])
+    }
+
+    /// Latest rate in effect at `at`, one for `petroleum_type` taking precedence over the
+    /// general one.
+    pub fn rate_at(&self, petroleum_type: PetroleumType, at: u128) -> Option<f32> {
+        let in_effect = |specific: bool| {
+            self.rates
+                .iter()
+                .rev()
+                .find(|rate| {
+                    rate.effective_from <= at
+                        && match rate.petroleum_type {
+                            Some(rate_type) => specific && rate_type == petroleum_type,
+                            None => !specific,
+                        }
+                })
+                .map(|rate| rate.rate)
+        };
+        in_effect(true).or_else(|| in_effect(false))
+    }
+
+    /// Breaks down the `gross` price of `petroleum_type` at `at`, rounded to the 3 decimals
+    /// prices are listed with.
+    pub fn breakdown(&self, gross: f32, petroleum_type: PetroleumType, at: u128) -> Option<VatBreakdown> {
+        let rate = self.rate_at(petroleum_type, at)?;
+        let round = |value: f32| (value * 1000.0).round() / 1000.0;
+        let net = round(gross / (1.0 + rate));
+        Some(VatBreakdown {
+            rate,
+            net,
+            vat: round(gross - net),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vat::{VatBreakdown, VatRate, VatTable};
+    use crate::PetroleumType;
+
+    #[test]
+    fn picks_the_rate_in_effect() {
+        let table = VatTable::cyprus();
+        assert_eq!(table.rate_at(PetroleumType::Unlead95, 0), None);
+        assert_eq!(table.rate_at(PetroleumType::Unlead95, 1358121600000), Some(0.18));
+        assert_eq!(
+            table.breakdown(1.428, PetroleumType::Unlead95, 1723729592807),
+            Some(VatBreakdown {
+                rate: 0.19,
+                net: 1.2,
+                vat: 0.228,
+            })
+        );
+
+        let table = VatTable::new(vec![
+            VatRate {
+                effective_from: 0,
+                petroleum_type: None,
+                rate: 0.19,
+            },
+            VatRate {
+                effective_from: 10,
+                petroleum_type: Some(PetroleumType::DieselHeat),
+                rate: 0.09,
+            },
+        ]);
+        assert_eq!(table.rate_at(PetroleumType::DieselHeat, 5), Some(0.19));
+        assert_eq!(table.rate_at(PetroleumType::DieselHeat, 10), Some(0.09));
+        assert_eq!(table.rate_at(PetroleumType::Kerosene, 10), Some(0.19));
+    }
+}
@@ -0,0 +1,52 @@
+//! Feeds stored prices pages through the parser and compares the result with the
+//! expected `.json` next to every `.html` snapshot.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to (re)write the expected files after a deliberate change.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cygaz_lib::parse_prices;
+use serde_json::Value;
+use url::Url;
+
+static ENDPOINT: &str = "https://eforms.eservices.cyprus.gov.cy/MCIT/MCIT/PetroleumPrices";
+
+fn snapshots() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let mut snapshots = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect::<Vec<_>>();
+    snapshots.sort();
+    snapshots
+}
+
+#[test]
+fn snapshots_parse_to_expected_stations() {
+    let endpoint = Url::parse(ENDPOINT).unwrap();
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    let snapshots = snapshots();
+    assert!(!snapshots.is_empty(), "no snapshots found");
+
+    for html_path in snapshots {
+        let json_path = html_path.with_extension("json");
+        let html = fs::read_to_string(&html_path).unwrap();
+        let actual = serde_json::to_value(parse_prices(&endpoint, &html)).unwrap();
+
+        if update {
+            fs::write(&json_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            continue;
+        }
+
+        let expected: Value = serde_json::from_str(
+            &fs::read_to_string(&json_path)
+                .unwrap_or_else(|_| panic!("missing {:?}, run with UPDATE_SNAPSHOTS=1", json_path)),
+        )
+        .unwrap();
+
+        assert_eq!(actual, expected, "snapshot {:?}", html_path);
+    }
+}
@@ -0,0 +1,159 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::middleware::Next;
+use actix_web::{delete, get, post, web, Error, HttpResponse, Responder};
+use cygaz_lib::{District, PetroleumType};
+use serde::Deserialize;
+
+use crate::request_id;
+use crate::{refresh_districts, refresh_prices_retrying, SharedState, Upstream};
+
+/// Bearer tokens allowed on the admin endpoints. Without any, every admin request is rejected.
+#[derive(Clone, Default)]
+pub struct AdminKeys {
+    keys: Vec<String>,
+}
+
+impl AdminKeys {
+    /// Parses the comma separated `ADMIN_API_KEYS`.
+    pub fn parse(keys: &str) -> Self {
+        AdminKeys {
+            keys: keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn allows(&self, token: &str) -> bool {
+        self.keys.iter().any(|key| constant_time_eq(key.as_bytes(), token.as_bytes()))
+    }
+}
+
+// compares in the same time wherever the first difference is, so keys cannot be guessed byte by byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn bearer(req: &ServiceRequest) -> Option<&str> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// Answers `401 Unauthorized` unless the request carries `Authorization: Bearer` with one of
+/// the admin keys.
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let keys = req.app_data::<web::Data<AdminKeys>>().cloned().unwrap_or_default();
+    let authorized = bearer(&req).is_some_and(|token| keys.allows(token));
+    if !authorized {
+        let res = HttpResponse::Unauthorized()
+            .insert_header((WWW_AUTHENTICATE, "Bearer realm=\"cygaz admin\""))
+            .json(serde_json::json!({ "error": "Missing or invalid admin API key" }));
+        return Ok(req.into_response(res));
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[derive(Deserialize)]
+pub struct RefreshQuery {
+    // only the prices of this district, the districts themselves are not refreshed
+    pub district: Option<String>,
+}
+
+/// Refreshes the districts and prices now rather than at the next scheduled refresh, or the
+/// prices of one district only.
+#[post("/refresh")]
+pub async fn refresh(
+    data: web::Data<SharedState>,
+    upstream: web::Data<Upstream>,
+    query: web::Query<RefreshQuery>,
+) -> impl Responder {
+    let district = match &query.district {
+        None => District::All,
+        Some(name) => match District::from_name(name) {
+            Some(district) => district,
+            None => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({ "error": format!("Unknown district {}", name) }))
+            }
+        },
+    };
+    if district != District::All && !data.read().areas.contains_key(&district) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": format!("Areas of {:?} are not known yet", district) }));
+    }
+
+    let prices = data.clone();
+    let upstream = upstream.get_ref().clone();
+    let refreshed = request_id::block(move || {
+        if district == District::All {
+            refresh_districts(prices.clone(), upstream.clone());
+        }
+        refresh_prices_retrying(prices, upstream, &PetroleumType::ALL, district)
+    })
+    .await;
+    if refreshed.is_err() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({ "error": "Refresh failed" }));
+    }
+
+    let state = data.read();
+    HttpResponse::Ok().json(state.summaries.latest())
+}
+
+#[derive(Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    // signed with WEBHOOK_SECRET when not given
+    pub secret: Option<String>,
+}
+
+#[get("/webhooks")]
+pub async fn list_webhooks(data: web::Data<SharedState>) -> impl Responder {
+    let webhooks = data.read().webhooks.clone();
+    HttpResponse::Ok().json(webhooks.list())
+}
+
+#[post("/webhooks")]
+pub async fn add_webhook(data: web::Data<SharedState>, webhook: web::Json<NewWebhook>) -> impl Responder {
+    let webhooks = data.read().webhooks.clone();
+    let webhook = webhook.into_inner();
+    match webhooks.add(&webhook.url, webhook.secret, "admin") {
+        Ok(webhook) => HttpResponse::Created().json(webhook),
+        Err(err) => HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    }
+}
+
+#[delete("/webhooks/{id}")]
+pub async fn remove_webhook(data: web::Data<SharedState>, id: web::Path<String>) -> impl Responder {
+    let webhooks = data.read().webhooks.clone();
+    if !webhooks.remove(&id) {
+        return HttpResponse::NotFound().json(serde_json::json!({ "error": "Unknown webhook" }));
+    }
+    HttpResponse::NoContent().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::AdminKeys;
+
+    #[test]
+    fn allows_configured_keys_only() {
+        let keys = AdminKeys::parse(" first, second,,");
+        assert_eq!(keys.len(), 2);
+        assert!(keys.allows("second"));
+        assert!(!keys.allows("secon"));
+        assert!(!keys.allows(""));
+        assert!(!AdminKeys::parse("").allows(""));
+    }
+}
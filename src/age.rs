@@ -0,0 +1,55 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::{web, HttpRequest};
+
+pub static DATA_UPDATED_AT: HeaderName = HeaderName::from_static("x-data-updated-at");
+pub static DATA_AGE_SECONDS: HeaderName = HeaderName::from_static("x-data-age-seconds");
+
+/// Seconds after which the prices of a response count as stale, `0` never.
+#[derive(Clone, Copy, Default)]
+pub struct StaleAfter(pub u64);
+
+/// How old the prices of a response are, for clients to tell whether they are fresh enough.
+pub struct DataAge {
+    pub updated_at: u128,
+    pub age_seconds: u64,
+    pub stale: bool,
+}
+
+impl DataAge {
+    pub fn new(updated_at: u128, now: u128, stale_after: StaleAfter) -> Self {
+        let age_seconds = (now.saturating_sub(updated_at) / 1000) as u64;
+        DataAge {
+            updated_at,
+            age_seconds,
+            stale: stale_after.0 > 0 && age_seconds > stale_after.0,
+        }
+    }
+
+    /// Age of prices refreshed at `updated_at`, stale past the `STALE_AFTER` of the app.
+    pub fn of(req: &HttpRequest, updated_at: u128) -> Self {
+        let stale_after = req.app_data::<web::Data<StaleAfter>>().map(|data| ***data).unwrap_or_default();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+        DataAge::new(updated_at, now, stale_after)
+    }
+
+    pub fn insert(&self, headers: &mut HeaderMap) {
+        headers.insert(DATA_UPDATED_AT.clone(), HeaderValue::from(self.updated_at as u64));
+        headers.insert(DATA_AGE_SECONDS.clone(), HeaderValue::from(self.age_seconds));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::age::{DataAge, StaleAfter};
+
+    #[test]
+    fn stale_past_the_threshold_only() {
+        let age = DataAge::new(1_000, 61_999, StaleAfter(60));
+        assert_eq!(age.age_seconds, 60);
+        assert!(!age.stale);
+        assert!(DataAge::new(1_000, 62_000, StaleAfter(60)).stale);
+        assert!(!DataAge::new(0, 1_000_000_000, StaleAfter(0)).stale);
+    }
+}
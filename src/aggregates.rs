@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use cygaz_lib::{AreasByDistrict, District, PetroleumStation, PetroleumType};
+
+use crate::cheapest::cheapest;
+use crate::nationwide::{self, NationwidePriceList};
+use crate::status::StationFilter;
+use crate::PriceList;
+
+/// Results of the popular queries, computed once per refresh instead of on every request.
+#[derive(Clone, Default)]
+pub struct Aggregates {
+    // every station eligible for /prices/cheapest, cheapest first
+    cheapest: BTreeMap<(PetroleumType, District), Vec<PetroleumStation>>,
+    // every price list merged, without and with closed stations
+    open: Option<NationwidePriceList>,
+    all: Option<NationwidePriceList>,
+}
+
+impl Aggregates {
+    pub fn update(&mut self, lists: &[&PriceList], areas: &AreasByDistrict) {
+        self.cheapest.clear();
+        for list in lists {
+            let districts = [District::All].into_iter().chain(areas.keys().copied());
+            for district in districts {
+                let stations = cheapest(&list.stations, areas, district, usize::MAX);
+                self.cheapest.insert((list.petroleum_type, district), stations);
+            }
+        }
+
+        let open = lists
+            .iter()
+            .map(|list| StationFilter::default().apply(list))
+            .collect::<Vec<_>>();
+        self.open = Some(nationwide::merge(&open.iter().collect::<Vec<_>>()));
+        self.all = Some(nationwide::merge(lists));
+    }
+
+    pub fn cheapest(&self, petroleum_type: PetroleumType, district: District, limit: usize) -> Vec<PetroleumStation> {
+        self.cheapest
+            .get(&(petroleum_type, district))
+            .map(|stations| stations.iter().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Every price list merged, None until the first refresh.
+    pub fn nationwide(&self, include_closed: bool) -> Option<&NationwidePriceList> {
+        match include_closed {
+            true => self.all.as_ref(),
+            false => self.open.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType, StationStatus};
+
+    use crate::aggregates::Aggregates;
+    use crate::fixtures::{list, station};
+
+    #[test]
+    fn precomputes_cheapest_and_nationwide() {
+        let areas = AreasByDistrict::from([(District::Nicosia, vec!["Strovolos".to_string()])]);
+        let stations = vec![
+            station("a", "Strovolos", 1.40),
+            station("b", "Peyia", 1.30),
+            station("c", "Strovolos", 1.20).with_status(StationStatus::Closed, 0),
+        ];
+        let list = list(PetroleumType::DieselAuto, 0, stations);
+        let mut aggregates = Aggregates::default();
+        assert!(aggregates.nationwide(false).is_none());
+        aggregates.update(&[&list], &areas);
+
+        let nicosia = aggregates.cheapest(PetroleumType::DieselAuto, District::Nicosia, 10);
+        assert_eq!(nicosia.iter().map(|s| s.station_id.as_str()).collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(aggregates.cheapest(PetroleumType::DieselAuto, District::All, 1)[0].station_id, "b");
+        assert_eq!(aggregates.nationwide(false).unwrap().stations.len(), 2);
+        assert_eq!(aggregates.nationwide(true).unwrap().stations.len(), 3);
+    }
+}
@@ -0,0 +1,528 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::{AreasByDistrict, District, PetroleumStation, PetroleumType};
+use serde::{Deserialize, Serialize};
+use log::warn;
+use uuid::Uuid;
+
+use crate::admin::AdminKeys;
+use crate::mail::{self, Mailer};
+use crate::nearest::{default_radius_km, distance_km};
+use crate::webhooks::{self, Webhooks};
+use crate::{PriceList, SharedState};
+
+static API_KEY_HEADER: &str = "X-API-Key";
+
+/// `X-API-Key` values allowed to manage alerts. Without any, every alerts request is rejected.
+#[derive(Clone, Default)]
+pub struct AlertKeys(AdminKeys);
+
+impl AlertKeys {
+    /// Parses the comma separated `ALERT_API_KEYS`.
+    pub fn parse(keys: &str) -> Self {
+        AlertKeys(AdminKeys::parse(keys))
+    }
+}
+
+/// Around where a rule watches for the cheapest station to change.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Nearby {
+    pub lat: f64,
+    pub lon: f64,
+    #[serde(default = "default_radius_km")]
+    pub radius_km: f64,
+}
+
+/// Where a triggered rule is delivered, besides the log.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertTarget {
+    // posted as webhooks are, signed with the secret of the rule or `WEBHOOK_SECRET`
+    Webhook(String),
+    Email(String),
+}
+
+#[derive(Deserialize)]
+pub struct NewAlertRule {
+    pub petroleum_type: PetroleumType,
+    pub district: Option<District>,
+    pub station_id: Option<String>,
+    // fires when the price drops below, or when the cheapest station `nearby` changes
+    pub below: Option<f32>,
+    pub nearby: Option<Nearby>,
+    pub target: Option<AlertTarget>,
+    pub secret: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AlertRule {
+    pub id: String,
+    #[serde(skip)]
+    pub api_key: String,
+    pub petroleum_type: PetroleumType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub district: Option<District>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub station_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub below: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nearby: Option<Nearby>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<AlertTarget>,
+    #[serde(skip)]
+    pub secret: Option<String>,
+    pub created_at: u128,
+    // set while the price stays below the threshold, so a rule fires once per drop, and when
+    // the cheapest station nearby last changed
+    pub triggered_at: Option<u128>,
+    // the cheapest station nearby as of the latest refresh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cheapest_station_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AlertMatch {
+    pub event: &'static str,
+    pub rule_id: String,
+    pub petroleum_type: PetroleumType,
+    pub station_id: String,
+    pub price: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub below: Option<f32>,
+    // the station that was cheapest nearby before
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_station_id: Option<String>,
+    pub triggered_at: u128,
+    #[serde(skip)]
+    pub target: Option<AlertTarget>,
+    #[serde(skip)]
+    pub secret: Option<String>,
+}
+
+impl AlertRule {
+    fn targets(&self, station: &PetroleumStation, areas: &AreasByDistrict) -> bool {
+        match (&self.station_id, self.district, self.nearby) {
+            (_, _, Some(nearby)) => {
+                let latitude = station.latitude.trim().parse::<f64>();
+                let longitude = station.longitude.trim().parse::<f64>();
+                match (latitude, longitude) {
+                    (Ok(latitude), Ok(longitude)) => {
+                        distance_km((nearby.lat, nearby.lon), (latitude, longitude)) <= nearby.radius_km
+                    }
+                    _ => false,
+                }
+            }
+            (Some(station_id), _, _) => &station.station_id == station_id,
+            (None, Some(District::All), _) | (None, None, _) => true,
+            (None, Some(district), _) => areas
+                .get(&district)
+                .is_some_and(|district_areas| district_areas.contains(&station.area)),
+        }
+    }
+
+    fn matched(&self, station: &PetroleumStation, previous_station_id: Option<String>, now: u128) -> AlertMatch {
+        AlertMatch {
+            event: "price_alert",
+            rule_id: self.id.clone(),
+            petroleum_type: self.petroleum_type,
+            station_id: station.station_id.clone(),
+            price: station.price,
+            below: self.below,
+            previous_station_id,
+            triggered_at: now,
+            target: self.target.clone(),
+            secret: self.secret.clone(),
+        }
+    }
+}
+
+/// Price threshold rules, owned by the API key that registered them.
+#[derive(Clone, Default)]
+pub struct AlertRules {
+    rules: Vec<AlertRule>,
+}
+
+impl AlertRules {
+    pub fn add(&mut self, api_key: &str, rule: NewAlertRule, now: u128) -> AlertRule {
+        let rule = AlertRule {
+            id: Uuid::new_v4().to_string(),
+            api_key: api_key.to_string(),
+            petroleum_type: rule.petroleum_type,
+            district: rule.district,
+            station_id: rule.station_id,
+            below: rule.below,
+            nearby: rule.nearby,
+            target: rule.target,
+            secret: rule.secret,
+            created_at: now,
+            triggered_at: None,
+            cheapest_station_id: None,
+        };
+        self.rules.push(rule.clone());
+        rule
+    }
+
+    pub fn list(&self, api_key: &str) -> Vec<AlertRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.api_key == api_key)
+            .cloned()
+            .collect()
+    }
+
+    /// False when `api_key` owns no rule with `id`.
+    pub fn remove(&mut self, api_key: &str, id: &str) -> bool {
+        let before = self.rules.len();
+        self.rules
+            .retain(|rule| !(rule.api_key == api_key && rule.id == id));
+        self.rules.len() != before
+    }
+
+    /// Checks every rule against the latest prices and returns the rules whose price just
+    /// dropped below their threshold or whose cheapest station nearby changed, with the cheapest
+    /// targeted station.
+    pub fn evaluate(
+        &mut self,
+        lists: &[&PriceList],
+        areas: &AreasByDistrict,
+        now: u128,
+    ) -> Vec<AlertMatch> {
+        let mut matches = vec![];
+        for rule in self.rules.iter_mut() {
+            let cheapest = lists
+                .iter()
+                .filter(|list| list.petroleum_type == rule.petroleum_type)
+                .flat_map(|list| list.stations.iter())
+                .filter(|station| !station.outlier && rule.targets(station, areas))
+                .filter(|station| rule.below.is_none_or(|below| station.price < below))
+                .min_by(|a, b| a.price.total_cmp(&b.price));
+
+            if rule.below.is_none() {
+                // the first refresh only tells which station is cheapest, and one without
+                // stations nearby changes nothing
+                let Some(station) = cheapest else {
+                    continue;
+                };
+                let previous = rule.cheapest_station_id.replace(station.station_id.clone());
+                if previous.as_ref().is_some_and(|previous| previous != &station.station_id) {
+                    rule.triggered_at = Some(now);
+                    matches.push(rule.matched(station, previous, now));
+                }
+                continue;
+            }
+            match (cheapest, rule.triggered_at) {
+                (Some(station), None) => {
+                    rule.triggered_at = Some(now);
+                    matches.push(rule.matched(station, None, now));
+                }
+                (Some(_), Some(_)) => {}
+                (None, _) => rule.triggered_at = None,
+            }
+        }
+        matches
+    }
+}
+
+fn message(alert: &AlertMatch) -> (String, String) {
+    let subject = match &alert.previous_station_id {
+        Some(_) => format!("{:?} is cheapest at station {} nearby", alert.petroleum_type, alert.station_id),
+        None => format!("{:?} at {} at station {}", alert.petroleum_type, alert.price, alert.station_id),
+    };
+    let body = match (&alert.previous_station_id, alert.below) {
+        (Some(previous), _) => format!(
+            "Station {} sells {:?} at {}, cheaper than station {} that was the cheapest nearby.\n\nAlert {}",
+            alert.station_id, alert.petroleum_type, alert.price, previous, alert.rule_id
+        ),
+        (None, below) => format!(
+            "Station {} sells {:?} at {}, below {}.\n\nAlert {}",
+            alert.station_id,
+            alert.petroleum_type,
+            alert.price,
+            below.unwrap_or_default(),
+            alert.rule_id
+        ),
+    };
+    (subject, body)
+}
+
+/// Delivers every triggered rule with a target to it, off the calling thread.
+pub fn deliver(matches: &[AlertMatch], webhooks: &Webhooks, mailer: Option<&Mailer>) {
+    for alert in matches {
+        let delivered = match &alert.target {
+            None => Ok(()),
+            Some(AlertTarget::Webhook(url)) => webhooks
+                .target(url, alert.secret.clone(), "alert")
+                .and_then(|webhook| {
+                    let body = serde_json::to_string(alert).map_err(|err| err.to_string())?;
+                    webhooks::send(webhook, Arc::new(body));
+                    Ok(())
+                }),
+            Some(AlertTarget::Email(address)) => match mailer {
+                Some(mailer) => mail::mailbox(address).map(|to| {
+                    let (subject, body) = message(alert);
+                    mailer.send(to, subject, body);
+                }),
+                None => Err("SMTP_URL not set".to_string()),
+            },
+        };
+        if let Err(err) = delivered {
+            warn!("alert {} not delivered: {}", alert.rule_id, err);
+        }
+    }
+}
+
+// the `X-API-Key` of the request, if one of the alert keys
+fn api_key(req: &HttpRequest) -> Result<String, String> {
+    let keys = req.app_data::<web::Data<AlertKeys>>().cloned().unwrap_or_default();
+    let api_key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty());
+    match api_key {
+        Some(api_key) if keys.0.allows(api_key) => Ok(api_key.to_string()),
+        Some(_) => Err(format!("Invalid {} header", API_KEY_HEADER)),
+        None => Err(format!("Missing {} header", API_KEY_HEADER)),
+    }
+}
+
+fn unauthorized(error: &str) -> HttpResponse {
+    HttpResponse::Unauthorized().json(serde_json::json!({ "error": error }))
+}
+
+fn bad_request(error: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({ "error": error }))
+}
+
+#[post("/alerts")]
+pub async fn create_alert(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    rule: web::Json<NewAlertRule>,
+) -> impl Responder {
+    let api_key = match api_key(&req) {
+        Ok(api_key) => api_key,
+        Err(err) => return unauthorized(&err),
+    };
+    let rule = rule.into_inner();
+
+    match (rule.below, rule.nearby) {
+        (None, None) => return bad_request("either below or nearby is required"),
+        (Some(_), Some(_)) => return bad_request("below and nearby cannot be combined"),
+        (Some(below), None) if below <= 0.0 => return bad_request("below has to be a positive price"),
+        (None, Some(_)) if rule.district.is_some() || rule.station_id.is_some() => {
+            return bad_request("nearby cannot be combined with district or station_id")
+        }
+        (None, Some(nearby))
+            if !(-90.0..=90.0).contains(&nearby.lat)
+                || !(-180.0..=180.0).contains(&nearby.lon)
+                || nearby.radius_km <= 0.0 =>
+        {
+            return bad_request("lat, lon or radius_km out of range")
+        }
+        _ => {}
+    }
+
+    let mut state = data.write();
+    let target = match &rule.target {
+        None => Ok(()),
+        Some(AlertTarget::Webhook(url)) => state.webhooks.target(url, rule.secret.clone(), "alert").map(|_| ()),
+        Some(AlertTarget::Email(_)) if state.mailer.is_none() => {
+            Err("email targets need SMTP_URL, which is not set".to_string())
+        }
+        Some(AlertTarget::Email(address)) => mail::mailbox(address).map(|_| ()),
+    };
+    if let Err(err) = target {
+        return bad_request(&err);
+    }
+    if let Some(station_id) = &rule.station_id {
+        if rule.district.is_some() {
+            return bad_request("station_id and district cannot be combined");
+        }
+        let known = state
+            .price_list(rule.petroleum_type)
+            .is_some_and(|list| list.stations.iter().any(|station| &station.station_id == station_id));
+        if !known {
+            return bad_request("Unknown station_id for this petroleum_type");
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    HttpResponse::Created().json(state.alerts.add(&api_key, rule, now))
+}
+
+#[get("/alerts")]
+pub async fn list_alerts(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+) -> impl Responder {
+    let api_key = match api_key(&req) {
+        Ok(api_key) => api_key,
+        Err(err) => return unauthorized(&err),
+    };
+    let state = data.read();
+    HttpResponse::Ok().json(state.alerts.list(&api_key))
+}
+
+#[delete("/alerts/{id}")]
+pub async fn delete_alert(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    id: web::Path<String>,
+) -> impl Responder {
+    let api_key = match api_key(&req) {
+        Ok(api_key) => api_key,
+        Err(err) => return unauthorized(&err),
+    };
+    let mut state = data.write();
+    match state.alerts.remove(&api_key, &id) {
+        true => HttpResponse::NoContent().finish(),
+        false => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType};
+
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+
+    use crate::alerts::{api_key, AlertKeys, AlertRules, AlertTarget, Nearby, NewAlertRule};
+    use crate::fixtures::prices;
+
+    #[test]
+    fn station_rule_fires_once_per_drop() {
+        let mut rules = AlertRules::default();
+        let rule = rules.add(
+            "key",
+            NewAlertRule {
+                petroleum_type: PetroleumType::Unlead95,
+                district: None,
+                station_id: Some("local".to_string()),
+                below: Some(1.40),
+                nearby: None,
+                target: None,
+                secret: None,
+            },
+            0,
+        );
+        let areas = AreasByDistrict::new();
+
+        let above = prices(PetroleumType::Unlead95, &[("local", "Strovolos", 1.45), ("other", "Strovolos", 1.30)]);
+        assert!(rules.evaluate(&[&above], &areas, 1).is_empty());
+
+        let below = prices(PetroleumType::Unlead95, &[("local", "Strovolos", 1.39), ("other", "Strovolos", 1.30)]);
+        let matches = rules.evaluate(&[&below], &areas, 2);
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].station_id.as_str(), matches[0].price), ("local", 1.39));
+        assert!(rules.evaluate(&[&below], &areas, 3).is_empty());
+
+        assert!(rules.evaluate(&[&above], &areas, 4).is_empty());
+        assert_eq!(rules.evaluate(&[&below], &areas, 5).len(), 1);
+
+        assert!(rules.list("other key").is_empty());
+        assert!(!rules.remove("other key", &rule.id));
+        assert!(rules.remove("key", &rule.id));
+        assert!(rules.list("key").is_empty());
+    }
+
+    #[test]
+    fn district_rule_picks_cheapest_station_in_district() {
+        let mut rules = AlertRules::default();
+        rules.add(
+            "key",
+            NewAlertRule {
+                petroleum_type: PetroleumType::Unlead95,
+                district: Some(District::Limassol),
+                station_id: None,
+                below: Some(1.40),
+                nearby: None,
+                target: None,
+                secret: None,
+            },
+            0,
+        );
+        let areas = AreasByDistrict::from([(District::Limassol, vec!["Germasogeia".to_string()])]);
+
+        let prices = prices(PetroleumType::Unlead95, &[
+            ("nicosia", "Strovolos", 1.20),
+            ("limassol", "Germasogeia", 1.35),
+            ("limassol cheaper", "Germasogeia", 1.33),
+        ]);
+        let matches = rules.evaluate(&[&prices], &areas, 1);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].station_id, "limassol cheaper");
+    }
+
+    #[test]
+    fn nearby_rule_fires_when_the_cheapest_station_changes() {
+        let mut rules = AlertRules::default();
+        rules.add(
+            "key",
+            NewAlertRule {
+                petroleum_type: PetroleumType::Unlead95,
+                district: None,
+                station_id: None,
+                below: None,
+                nearby: Some(Nearby {
+                    lat: 35.17,
+                    lon: 33.36,
+                    radius_km: 5.0,
+                }),
+                target: Some(AlertTarget::Email("me@example.com".to_string())),
+                secret: None,
+            },
+            0,
+        );
+        let areas = AreasByDistrict::new();
+        let located = |listed: &[(&str, &str, f32)], latitude: &str| {
+            let mut list = prices(PetroleumType::Unlead95, listed);
+            for station in list.stations.iter_mut() {
+                station.latitude = latitude.to_string();
+                station.longitude = "33.36".to_string();
+            }
+            list
+        };
+
+        // the first refresh only tells which one is cheapest
+        let prices = located(&[("a", "Strovolos", 1.40), ("b", "Strovolos", 1.42)], "35.17");
+        assert!(rules.evaluate(&[&prices], &areas, 1).is_empty());
+        assert!(rules.evaluate(&[&prices], &areas, 2).is_empty());
+
+        let prices = located(&[("a", "Strovolos", 1.40), ("b", "Strovolos", 1.38)], "35.17");
+        let matches = rules.evaluate(&[&prices], &areas, 3);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].station_id, "b");
+        assert_eq!(matches[0].previous_station_id.as_deref(), Some("a"));
+        assert_eq!(matches[0].target, Some(AlertTarget::Email("me@example.com".to_string())));
+
+        // too far away to count
+        let far = located(&[("c", "Paralimni", 1.20)], "35.04");
+        assert!(rules.evaluate(&[&far], &areas, 4).is_empty());
+    }
+
+    #[test]
+    fn allows_configured_api_keys_only() {
+        let req = |api_key: Option<&str>| {
+            let req = TestRequest::default().app_data(web::Data::new(AlertKeys::parse("first, second")));
+            match api_key {
+                Some(api_key) => req.insert_header(("X-API-Key", api_key)),
+                None => req,
+            }
+            .to_http_request()
+        };
+        assert_eq!(api_key(&req(Some("second"))).ok().as_deref(), Some("second"));
+        assert_eq!(api_key(&req(Some("third"))), Err("Invalid X-API-Key header".to_string()));
+        assert_eq!(api_key(&req(None)), Err("Missing X-API-Key header".to_string()));
+
+        let req = TestRequest::default().insert_header(("X-API-Key", "first")).to_http_request();
+        assert!(api_key(&req).is_err());
+    }
+}
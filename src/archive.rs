@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use cygaz_lib::normalize::Transliteration;
+use hmac::{Hmac, KeyInit, Mac};
+use log::{debug, warn};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::request_id;
+use crate::snapshot::Snapshot;
+use crate::AppStateWithPrices;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// the payload is hashed and signed along with the headers
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    // `Snapshot`, what SNAPSHOT_FILE holds
+    #[default]
+    Json,
+    // the nationwide list, one row per station
+    Csv,
+}
+
+impl ArchiveFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Json => "json",
+            ArchiveFormat::Csv => "csv",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Json => "application/json",
+            ArchiveFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// Uploads timestamped snapshots of the prices to an S3 compatible bucket.
+pub struct Archive {
+    // the bucket, path style, and a prefix the objects go under if any
+    url: Url,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    format: ArchiveFormat,
+    // of the names in the CSV, as TRANSLITERATION configures them
+    #[cfg_attr(not(feature = "exports"), allow(dead_code))]
+    transliteration: Transliteration,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The AWS Signature Version 4 key of `date`, `YYYYMMDD`, for `service` in `region`.
+pub fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    hmac(&key, "aws4_request")
+}
+
+/// Where under the bucket the snapshot of prices refreshed at `updated_at` goes, a folder per day.
+pub fn object_key(updated_at: u128, format: ArchiveFormat) -> String {
+    let at = DateTime::<Utc>::from_timestamp_millis(updated_at as i64).unwrap_or_default();
+    format!("{}.{}", at.format("%Y/%m/%d/prices-%Y%m%dT%H%M%SZ"), format.extension())
+}
+
+impl Archive {
+    /// Takes `ARCHIVE_URL`, like `https://s3.eu-west-1.amazonaws.com/bucket/cygaz`, and the
+    /// credentials uploads are signed with.
+    pub fn new(
+        url: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+        format: ArchiveFormat,
+        transliteration: Transliteration,
+    ) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|err| format!("{}: {}", url, err))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(format!("{}: expected an http(s) bucket URL", url));
+        }
+        if url.path().trim_matches('/').is_empty() {
+            return Err(format!("{}: expected the bucket in the path", url));
+        }
+        #[cfg(not(feature = "exports"))]
+        if format == ArchiveFormat::Csv {
+            return Err("csv needs the exports feature".to_string());
+        }
+        Ok(Archive {
+            url,
+            region: region.to_string(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            format,
+            transliteration,
+        })
+    }
+
+    /// The snapshot of `state` in the archive format, None before any prices.
+    pub fn body(&self, state: &AppStateWithPrices) -> Option<Vec<u8>> {
+        let snapshot = Snapshot::of(state);
+        if snapshot.lists.iter().all(|list| list.stations.is_empty()) {
+            return None;
+        }
+        match self.format {
+            ArchiveFormat::Json => serde_json::to_vec(&snapshot).ok(),
+            #[cfg(feature = "exports")]
+            ArchiveFormat::Csv => {
+                let list = state.aggregates.nationwide(true)?;
+                Some(crate::csv::nationwide_csv(list, crate::lang::Lang::En, &self.transliteration).into_bytes())
+            }
+            #[cfg(not(feature = "exports"))]
+            ArchiveFormat::Csv => None,
+        }
+    }
+
+    fn object_url(&self, key: &str) -> Url {
+        let mut url = self.url.clone();
+        url.set_path(&format!("{}/{}", self.url.path().trim_end_matches('/'), key));
+        url
+    }
+
+    // the `Authorization` header of a PUT of a body hashed to `payload_hash` to `url` at `at`
+    fn authorization(&self, url: &Url, payload_hash: &str, at: &DateTime<Utc>) -> String {
+        let date = at.format("%Y%m%d").to_string();
+        let timestamp = at.format("%Y%m%dT%H%M%SZ").to_string();
+        let host = match (url.host_str().unwrap_or_default(), url.port()) {
+            (host, Some(port)) => format!("{}:{}", host, port),
+            (host, None) => host.to_string(),
+        };
+        let canonical = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            url.path(),
+            host,
+            payload_hash,
+            timestamp,
+            SIGNED_HEADERS,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let signature = hex(&hmac(
+            &signing_key(&self.secret_access_key, &date, &self.region, "s3"),
+            &to_sign,
+        ));
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, SIGNED_HEADERS, signature
+        )
+    }
+
+    /// Uploads `body`, the prices refreshed at `updated_at`, blocking until done.
+    pub fn upload(&self, updated_at: u128, body: Vec<u8>) -> Result<String, String> {
+        let key = object_key(updated_at, self.format);
+        let url = self.object_url(&key);
+        let payload_hash = hex(&Sha256::digest(&body));
+        let now = Utc::now();
+        let client = reqwest::blocking::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        let res = client
+            .put(url.clone())
+            .header("Content-Type", self.format.content_type())
+            .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("Authorization", self.authorization(&url, &payload_hash, &now))
+            .body(body)
+            .send()
+            .map_err(|err| err.to_string())?;
+        match res.status().is_success() {
+            true => Ok(key),
+            false => Err(format!("{} status {}", key, res.status())),
+        }
+    }
+
+    /// Same as `upload`, off the calling thread, logging a failure.
+    pub fn save(self: &Arc<Self>, updated_at: u128, body: Vec<u8>) {
+        let archive = self.clone();
+        request_id::spawn(move || match archive.upload(updated_at, body) {
+            Ok(key) => debug!("archived prices to {}", key),
+            Err(err) => warn!("failed to archive prices {}", err),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use cygaz_lib::normalize::Transliteration;
+    use sha2::{Digest, Sha256};
+
+    use crate::archive::{hex, object_key, signing_key, Archive, ArchiveFormat};
+
+    #[test]
+    fn signs_uploads_with_signature_version_4() {
+        // the signing key example of the AWS Signature Version 4 documentation
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+
+        assert_eq!(object_key(1_760_436_900_000, ArchiveFormat::Json), "2025/10/14/prices-20251014T101500Z.json");
+
+        let archive = Archive::new(
+            "https://s3.eu-west-1.amazonaws.com/bucket/cygaz/",
+            "eu-west-1",
+            "AKIDEXAMPLE",
+            "secret",
+            ArchiveFormat::Json,
+            Transliteration::default(),
+        )
+        .unwrap();
+        let url = archive.object_url("2025/10/14/prices.json");
+        assert_eq!(url.as_str(), "https://s3.eu-west-1.amazonaws.com/bucket/cygaz/2025/10/14/prices.json");
+        let at = Utc.with_ymd_and_hms(2025, 10, 14, 10, 15, 0).unwrap();
+        let authorization = archive.authorization(&url, &hex(&Sha256::digest(b"")), &at);
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20251014/eu-west-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=479eaa2364525151fe7b823c1df56301d896ae023f2a8bb36fb8d6c4d2355688"
+        );
+
+        let archive = |url: &str| Archive::new(url, "us-east-1", "a", "b", ArchiveFormat::Json, Transliteration::default());
+        assert!(archive("https://s3.amazonaws.com").is_err());
+        assert!(archive("ftp://s3.amazonaws.com/bucket").is_err());
+    }
+}
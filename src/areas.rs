@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::normalize::greeklish_key;
+use cygaz_lib::{AreasByDistrict, District};
+use serde::Serialize;
+
+use crate::lang;
+use crate::nationwide::MergedStation;
+use crate::stations::{district_of, open_stations, priced, PricedStation};
+use crate::SharedState;
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct Area {
+    pub name: String,
+    // None for areas upstream lists under no district
+    pub district: Option<District>,
+    pub stations: usize,
+}
+
+#[derive(Serialize)]
+pub struct AreaPrices {
+    pub name: String,
+    pub district: Option<District>,
+    pub stations: Vec<PricedStation>,
+}
+
+/// Every area upstream lists under a district or some station is in, by the Greeklish key of
+/// its name, with how many of `stations` are there.
+pub fn areas(stations: &[MergedStation], areas: &AreasByDistrict) -> Vec<Area> {
+    let mut found: BTreeMap<String, Area> = BTreeMap::new();
+    let listed = areas
+        .values()
+        .flatten()
+        .map(|area| (area.as_str(), 0))
+        .chain(stations.iter().map(|station| (station.area.as_str(), 1)));
+    for (name, count) in listed {
+        let key = greeklish_key(name);
+        if key.is_empty() {
+            continue;
+        }
+        found
+            .entry(key)
+            .or_insert_with(|| Area {
+                name: name.trim().to_string(),
+                district: district_of(areas, name),
+                stations: 0,
+            })
+            .stations += count;
+    }
+    let mut found = found.into_values().collect::<Vec<_>>();
+    found.sort_by(|a, b| a.district.cmp(&b.district).then_with(|| a.name.cmp(&b.name)));
+    found
+}
+
+/// The stations of the area named `name`, as upstream spells it or in Greeklish, with their
+/// current prices. None for an unknown area.
+pub fn area_prices(stations: &[MergedStation], areas_by_district: &AreasByDistrict, name: &str) -> Option<AreaPrices> {
+    let key = greeklish_key(name);
+    let area = areas(stations, areas_by_district)
+        .into_iter()
+        .find(|area| greeklish_key(&area.name) == key)?;
+    let stations = stations
+        .iter()
+        .filter(|station| greeklish_key(&station.area) == key)
+        .map(|station| priced(station.clone(), areas_by_district))
+        .collect();
+    Some(AreaPrices {
+        name: area.name,
+        district: area.district,
+        stations,
+    })
+}
+
+#[get("/areas")]
+pub async fn list_areas(req: HttpRequest, data: web::Data<SharedState>) -> impl Responder {
+    let state = data.read();
+    HttpResponse::Ok().json(lang::localized(&req, areas(open_stations(&state), &state.areas)))
+}
+
+#[get("/areas/{name}/prices")]
+pub async fn get_area_prices(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    name: web::Path<String>,
+) -> impl Responder {
+    let state = data.read();
+    match area_prices(open_stations(&state), &state.areas, &name) {
+        Some(prices) => HttpResponse::Ok().json(lang::localized(&req, prices)),
+        None => HttpResponse::NotFound().json(serde_json::json!({ "error": "Unknown area" })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType};
+
+    use crate::areas::{area_prices, areas, Area};
+    use crate::lang::localized;
+    use crate::fixtures::merged;
+
+    #[test]
+    fn stations_and_prices_of_an_area() {
+        let by_district = AreasByDistrict::from([
+            (District::Nicosia, vec!["Στρόβολος".to_string(), "Λακατάμια".to_string()]),
+            (District::Paphos, vec!["Πέγεια".to_string()]),
+        ]);
+        let diesel = [(PetroleumType::DieselAuto, 1.42)];
+        let stations = vec![merged("a", "Στρόβολος", &diesel), merged("b", "Στρόβολος", &diesel), merged("c", "Κάπου", &diesel)];
+
+        let area = |name: &str, district, stations| Area {
+            name: name.to_string(),
+            district,
+            stations,
+        };
+        assert_eq!(
+            areas(&stations, &by_district),
+            vec![
+                area("Κάπου", None, 1),
+                area("Λακατάμια", Some(District::Nicosia), 0),
+                area("Στρόβολος", Some(District::Nicosia), 2),
+                area("Πέγεια", Some(District::Paphos), 0),
+            ]
+        );
+
+        let strovolos = area_prices(&stations, &by_district, "strovolos").unwrap();
+        assert_eq!(strovolos.name, "Στρόβολος");
+        assert_eq!(strovolos.district, Some(District::Nicosia));
+        let ids = strovolos.stations.iter().map(|s| s.station.station_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(area_prices(&stations, &by_district, "Λακατάμια").unwrap().stations.is_empty());
+        assert!(area_prices(&stations, &by_district, "Limassol").is_none());
+    }
+
+    #[test]
+    fn latin_names_with_lang_en() {
+        let by_district = AreasByDistrict::from([(District::Nicosia, vec!["Στρόβολος".to_string()])]);
+        let stations = vec![merged("a", "Στρόβολος", &[(PetroleumType::DieselAuto, 1.42)])];
+        let request = |uri: &str| TestRequest::get().uri(uri).to_http_request();
+
+        let listed = localized(&request("/areas?lang=en"), areas(&stations, &by_district));
+        assert_eq!(listed[0].name, "Strovolos");
+        let greek = localized(&request("/areas"), areas(&stations, &by_district));
+        assert_eq!(greek[0].name, "Στρόβολος");
+
+        let prices = area_prices(&stations, &by_district, "strovolos").unwrap();
+        let prices = localized(&request("/areas/strovolos/prices?lang=en"), prices);
+        assert_eq!(prices.name, "Strovolos");
+        assert_eq!(prices.stations[0].station.area, "Strovolos");
+        // lookups keep taking either spelling
+        assert_eq!(prices.district, Some(District::Nicosia));
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::normalize::{slug, transliterate};
+use cygaz_lib::AreasByDistrict;
+use serde::Serialize;
+
+use crate::lang;
+use crate::nationwide::MergedStation;
+use crate::stations::{open_stations, priced, PricedStation};
+use crate::SharedState;
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct Brand {
+    pub id: String,
+    // as most of its stations spell it
+    pub name: String,
+    pub stations: usize,
+}
+
+#[derive(Serialize)]
+pub struct BrandPrices {
+    pub id: String,
+    pub name: String,
+    pub stations: Vec<PricedStation>,
+}
+
+/// The same for every spelling of a brand, Greek or Latin, in any case, e.g. `eko` for `ΕΚΟ`
+/// and `Eko`.
+pub fn brand_id(brand: &str) -> String {
+    slug(&transliterate(brand))
+}
+
+/// Every brand of `stations` once, by id.
+pub fn brands(stations: &[MergedStation]) -> Vec<Brand> {
+    let mut spellings: BTreeMap<String, BTreeMap<&str, usize>> = BTreeMap::new();
+    for station in stations {
+        let id = brand_id(&station.brand);
+        if id.is_empty() {
+            continue;
+        }
+        *spellings.entry(id).or_default().entry(station.brand.trim()).or_default() += 1;
+    }
+    spellings
+        .into_iter()
+        .map(|(id, spellings)| Brand {
+            id,
+            stations: spellings.values().sum(),
+            // the first in order among as common ones, to not change between refreshes
+            name: spellings
+                .iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// The stations of the brand with `id` with their current prices, None for an unknown brand.
+pub fn brand_prices(stations: &[MergedStation], areas: &AreasByDistrict, id: &str) -> Option<BrandPrices> {
+    let brand = brands(stations).into_iter().find(|brand| brand.id == id)?;
+    let stations = stations
+        .iter()
+        .filter(|station| brand_id(&station.brand) == id)
+        .map(|station| priced(station.clone(), areas))
+        .collect();
+    Some(BrandPrices {
+        id: brand.id,
+        name: brand.name,
+        stations,
+    })
+}
+
+#[get("/brands")]
+pub async fn list_brands(data: web::Data<SharedState>) -> impl Responder {
+    let state = data.read();
+    HttpResponse::Ok().json(brands(open_stations(&state)))
+}
+
+#[get("/brands/{id}/prices")]
+pub async fn get_brand_prices(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    id: web::Path<String>,
+) -> impl Responder {
+    let state = data.read();
+    match brand_prices(open_stations(&state), &state.areas, &id) {
+        Some(prices) => HttpResponse::Ok().json(lang::localized(&req, prices)),
+        None => HttpResponse::NotFound().json(serde_json::json!({ "error": "Unknown brand" })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, PetroleumType};
+
+    use crate::brands::{brand_prices, brands, Brand};
+    use crate::fixtures::merged;
+    use crate::nationwide::MergedStation;
+
+    #[test]
+    fn brands_spelled_alike_are_one() {
+        let stations = [("a", "ΕΚΟ"), ("b", "EKO"), ("c", "Eko "), ("d", "EKO"), ("e", "Petrolina"), ("f", "")]
+            .map(|(station_id, brand)| MergedStation {
+                brand: brand.to_string(),
+                ..merged(station_id, "", &[(PetroleumType::Unlead95, 1.35)])
+            })
+            .to_vec();
+
+        let brand = |id: &str, name: &str, stations| Brand {
+            id: id.to_string(),
+            name: name.to_string(),
+            stations,
+        };
+        assert_eq!(brands(&stations), vec![brand("eko", "EKO", 4), brand("petrolina", "Petrolina", 1)]);
+
+        let eko = brand_prices(&stations, &AreasByDistrict::new(), "eko").unwrap();
+        let ids = eko.stations.iter().map(|s| s.station.station_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b", "c", "d"]);
+        assert_eq!(eko.stations[0].prices[&PetroleumType::Unlead95], 1.35);
+        assert!(brand_prices(&stations, &AreasByDistrict::new(), "shell").is_none());
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::{District, PetroleumType, CURRENCY};
+use log::{info, warn};
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::stats::PriceStatistics;
+use crate::SharedState;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// the country code the bulletin's weighted EU average goes by
+pub const EU: &str = "EU";
+
+// the bulletin quotes prices per 1000 litres
+const BULLETIN_LITRES: f32 = 1000.0;
+
+// the bulletin columns, by the petroleum types they compare with
+const FUELS: [(&str, PetroleumType); 3] = [
+    ("euro_super_95", PetroleumType::Unlead95),
+    ("diesel", PetroleumType::DieselAuto),
+    ("heating_oil", PetroleumType::DieselHeat),
+];
+
+/// Prices of the EU Commission's Weekly Oil Bulletin, taxes included, per litre.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OilBulletin {
+    // `YYYY-MM-DD`, of the prices rather than the publication
+    pub date: Option<String>,
+    pub fetched_at: u128,
+    // by country code
+    pub prices: BTreeMap<String, BTreeMap<PetroleumType, f32>>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FuelComparison {
+    // the nationwide average of the latest refresh, closed stations and outliers left out
+    pub cyprus: Option<f32>,
+    pub eu: Option<f32>,
+    pub difference: Option<f32>,
+    // of the EU average
+    pub difference_pct: Option<f32>,
+    pub countries: BTreeMap<String, f32>,
+}
+
+#[derive(Serialize)]
+pub struct EuComparison {
+    pub bulletin_date: Option<String>,
+    pub fetched_at: u128,
+    pub currency: &'static str,
+    pub unit: &'static str,
+    pub petroleum_types: BTreeMap<PetroleumType, FuelComparison>,
+}
+
+fn number(value: &str, decimal_comma: bool) -> Option<f32> {
+    let value = value.trim().replace(' ', "");
+    let value = match decimal_comma {
+        true => value.replace('.', "").replace(',', "."),
+        false => value.replace(',', ""),
+    };
+    value.parse::<f32>().ok().filter(|price| price.is_finite() && *price > 0.0)
+}
+
+/// Reads the bulletin as CSV, a row per country and date with the columns `country`, `date`
+/// if the file holds more than one bulletin, and any of `euro_super_95`, `diesel` and
+/// `heating_oil` in euro per 1000 litres. Separated by `;` numbers take decimal commas. Only the
+/// latest date is kept.
+pub fn parse(body: &str, fetched_at: u128) -> Result<OilBulletin, String> {
+    let mut lines = body.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or("empty bulletin")?;
+    let separator = match header.contains(';') {
+        true => ';',
+        false => ',',
+    };
+    let columns = header
+        .split(separator)
+        .map(|column| column.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |name: &str| columns.iter().position(|column| column == name);
+    let country = column("country").ok_or("no country column")?;
+    let date = column("date");
+    let fuels = FUELS
+        .iter()
+        .filter_map(|(name, petroleum_type)| Some((column(name)?, *petroleum_type)))
+        .collect::<Vec<_>>();
+    if fuels.is_empty() {
+        return Err("no euro_super_95, diesel or heating_oil column".to_string());
+    }
+
+    let rows = lines
+        .map(|line| line.split(separator).map(str::trim).collect::<Vec<_>>())
+        .filter_map(|cells| {
+            let code = cells.get(country).filter(|code| !code.is_empty())?.to_uppercase();
+            let date = date.and_then(|date| cells.get(date)).map(|date| date.to_string());
+            let prices = fuels
+                .iter()
+                .filter_map(|(column, petroleum_type)| {
+                    let price = number(cells.get(*column)?, separator == ';')?;
+                    Some((*petroleum_type, price / BULLETIN_LITRES))
+                })
+                .collect::<BTreeMap<_, _>>();
+            Some((date, code, prices))
+        })
+        .collect::<Vec<_>>();
+    // ISO dates sort by time
+    let latest = rows.iter().map(|(date, _, _)| date.clone()).max().ok_or("no countries")?;
+    Ok(OilBulletin {
+        prices: rows
+            .into_iter()
+            .filter(|(date, _, _)| *date == latest)
+            .map(|(_, code, prices)| (code, prices))
+            .collect(),
+        date: latest,
+        fetched_at,
+    })
+}
+
+/// How the nationwide averages of `stats` compare to the EU average of `bulletin`, and to the
+/// prices of `countries`.
+pub fn compare(bulletin: &OilBulletin, stats: &PriceStatistics, countries: &[String]) -> EuComparison {
+    let petroleum_types = FUELS
+        .iter()
+        .map(|(_, petroleum_type)| {
+            let cyprus = stats
+                .district(*petroleum_type, District::All)
+                .and_then(|(fuel, stats)| Some(stats.avg? / fuel.unit.litres()));
+            let price_of = |code: &str| bulletin.prices.get(code)?.get(petroleum_type).copied();
+            let eu = price_of(EU);
+            let difference = cyprus.zip(eu).map(|(cyprus, eu)| cyprus - eu);
+            let comparison = FuelComparison {
+                cyprus,
+                eu,
+                difference,
+                difference_pct: difference.zip(eu).map(|(difference, eu)| difference / eu * 100.0),
+                countries: countries
+                    .iter()
+                    .filter_map(|code| Some((code.clone(), price_of(code)?)))
+                    .collect(),
+            };
+            (*petroleum_type, comparison)
+        })
+        .collect();
+    EuComparison {
+        bulletin_date: bulletin.date.clone(),
+        fetched_at: bulletin.fetched_at,
+        currency: CURRENCY,
+        unit: "litre",
+        petroleum_types,
+    }
+}
+
+/// Where the bulletin is fetched from, and the latest one fetched.
+pub struct OilBulletinSource {
+    url: Url,
+    // country codes compared besides the EU average
+    countries: Vec<String>,
+    latest: RwLock<Option<OilBulletin>>,
+}
+
+impl OilBulletinSource {
+    /// Takes `EU_BULLETIN_URL` and the comma separated `EU_COMPARISON_COUNTRIES`.
+    pub fn new(url: &str, countries: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|err| format!("{}: {}", url, err))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("{}: expected an http(s) URL", url));
+        }
+        Ok(OilBulletinSource {
+            url,
+            countries: countries
+                .split(',')
+                .map(|code| code.trim().to_uppercase())
+                .filter(|code| !code.is_empty())
+                .collect(),
+            latest: RwLock::new(None),
+        })
+    }
+
+    /// Fetches the bulletin, blocking until done, and keeps it unless it fails.
+    pub fn refresh(&self) -> Result<OilBulletin, String> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        let res = client.get(self.url.clone()).send().map_err(|err| err.to_string())?;
+        if !res.status().is_success() {
+            return Err(format!("{} status {}", self.url, res.status()));
+        }
+        let body = res.text().map_err(|err| err.to_string())?;
+        let fetched_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+        let bulletin = parse(&body, fetched_at).map_err(|err| format!("{}: {}", self.url, err))?;
+        *self.latest.write().unwrap() = Some(bulletin.clone());
+        Ok(bulletin)
+    }
+
+    /// Same as `refresh`, logging how it went.
+    pub fn update(&self) {
+        match self.refresh() {
+            Ok(bulletin) => info!("fetched the EU oil bulletin of {}", bulletin.date.as_deref().unwrap_or("no date")),
+            Err(err) => warn!("failed to fetch the EU oil bulletin {}", err),
+        }
+    }
+
+    pub fn latest(&self) -> Option<OilBulletin> {
+        self.latest.read().unwrap().clone()
+    }
+}
+
+#[get("/stats/eu-comparison")]
+pub async fn eu_comparison(data: web::Data<SharedState>) -> impl Responder {
+    let state = data.read();
+    let Some(source) = &state.eu_bulletin else {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "EU_BULLETIN_URL is not set" }));
+    };
+    match source.latest() {
+        Some(bulletin) => HttpResponse::Ok().json(compare(&bulletin, &state.stats, &source.countries)),
+        None => HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "No bulletin fetched yet" })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, PetroleumStation, PetroleumType};
+
+    use crate::bulletin::{compare, parse};
+    use crate::stats::PriceStatistics;
+    use crate::PriceList;
+
+    #[test]
+    fn compares_nationwide_averages_with_the_bulletin() {
+        let body = "date,country,euro_super_95,diesel\n\
+                    2025-10-06,EU,1700.00,1600.00\n\
+                    2025-10-13,EU,1710.00,1620.00\n\
+                    2025-10-13,CY,1400.00,1500.00\n\
+                    2025-10-13,el,1850.00,\n";
+        let bulletin = parse(body, 10).unwrap();
+        assert_eq!(bulletin.date.as_deref(), Some("2025-10-13"));
+        assert_eq!(bulletin.prices.len(), 3);
+        assert!((bulletin.prices["EU"][&PetroleumType::Unlead95] - 1.71).abs() < 1e-6);
+        assert!(!bulletin.prices["EL"].contains_key(&PetroleumType::DieselAuto));
+
+        let semicolons = parse("Country;Diesel\nEU;1 620,50\n", 10).unwrap();
+        assert!(semicolons.date.is_none());
+        assert!((semicolons.prices["EU"][&PetroleumType::DieselAuto] - 1.6205).abs() < 1e-6);
+        assert!(parse("country,kerosene\nEU,1000\n", 10).is_err());
+
+        let list = PriceList {
+            stations: vec![PetroleumStation::new("", 1.368)],
+            total_rows: 1,
+            ..PriceList::new(PetroleumType::Unlead95, 0, String::new())
+        };
+        let mut stats = PriceStatistics::default();
+        stats.record(&list, &AreasByDistrict::new());
+
+        let comparison = compare(&bulletin, &stats, &["EL".to_string(), "MT".to_string()]);
+        let unlead95 = &comparison.petroleum_types[&PetroleumType::Unlead95];
+        assert_eq!(unlead95.cyprus, Some(1.368));
+        assert!((unlead95.difference.unwrap() + 0.342).abs() < 1e-6);
+        assert!((unlead95.difference_pct.unwrap() + 20.0).abs() < 1e-3);
+        assert_eq!(unlead95.countries.keys().collect::<Vec<_>>(), vec!["EL"]);
+        let diesel = &comparison.petroleum_types[&PetroleumType::DieselAuto];
+        assert!(diesel.cyprus.is_none() && diesel.eu.is_some());
+        assert!(diesel.difference.is_none());
+    }
+}
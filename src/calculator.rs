@@ -0,0 +1,133 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::{District, PetroleumType, CURRENCY};
+use serde::{Deserialize, Serialize};
+
+use crate::stats::DistrictStats;
+use crate::SharedState;
+
+#[derive(Deserialize)]
+pub struct CalculatorQuery {
+    pub distance_km: Option<f32>,
+    pub consumption_l_100km: Option<f32>,
+    pub fuel: Option<String>,
+    // nationwide by default
+    pub district: Option<District>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Costs {
+    pub min: Option<f32>,
+    pub avg: Option<f32>,
+    pub max: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct TripCost {
+    pub petroleum_type: PetroleumType,
+    pub district: District,
+    pub distance_km: f32,
+    pub consumption_l_100km: f32,
+    pub litres: f32,
+    pub currency: &'static str,
+    // when the prices were refreshed
+    pub updated_at: u128,
+    pub cost: Costs,
+}
+
+/// Fuel a trip of `distance_km` burns at `consumption_l_100km`.
+pub fn litres(distance_km: f32, consumption_l_100km: f32) -> f32 {
+    distance_km * consumption_l_100km / 100.0
+}
+
+/// What `litres` cost at the lowest, average and highest price of `stats`, quoted for
+/// `unit_litres` each, in cents.
+pub fn costs(litres: f32, stats: &DistrictStats, unit_litres: f32) -> Costs {
+    let cost = |price: Option<f32>| price.map(|price| (litres * price / unit_litres * 100.0).round() / 100.0);
+    Costs {
+        min: cost(stats.min),
+        avg: cost(stats.avg),
+        max: cost(stats.max),
+    }
+}
+
+// a positive number
+fn positive(name: &str, value: Option<f32>) -> Result<f32, String> {
+    match value {
+        None => Err(format!("{} is required", name)),
+        Some(value) if value.is_finite() && value > 0.0 => Ok(value),
+        Some(_) => Err(format!("{} must be a positive number", name)),
+    }
+}
+
+#[get("/calculator")]
+pub async fn trip_cost(data: web::Data<SharedState>, query: web::Query<CalculatorQuery>) -> impl Responder {
+    let checked = positive("distance_km", query.distance_km).and_then(|distance_km| {
+        let consumption = positive("consumption_l_100km", query.consumption_l_100km)?;
+        let fuel = query.fuel.as_deref().ok_or("fuel is required".to_string())?;
+        let petroleum_type = PetroleumType::from_name(fuel).ok_or(format!("Unknown fuel {}", fuel))?;
+        Ok((distance_km, consumption, petroleum_type))
+    });
+    let (distance_km, consumption_l_100km, petroleum_type) = match checked {
+        Ok(checked) => checked,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let district = query.district.unwrap_or(District::All);
+
+    let state = data.read();
+    let Some((fuel, stats)) = state.stats.district(petroleum_type, district) else {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "No prices yet" }));
+    };
+    let litres = litres(distance_km, consumption_l_100km);
+    HttpResponse::Ok().json(TripCost {
+        petroleum_type,
+        district,
+        distance_km,
+        consumption_l_100km,
+        litres,
+        currency: CURRENCY,
+        updated_at: fuel.updated_at,
+        cost: costs(litres, stats, fuel.unit.litres()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{costs, litres, Costs};
+    use crate::stats::DistrictStats;
+
+    #[test]
+    fn costs_at_the_lowest_average_and_highest_price() {
+        let stats = DistrictStats {
+            count: 3,
+            min: Some(1.30),
+            max: Some(1.50),
+            avg: Some(1.40),
+            median: Some(1.40),
+        };
+        let litres = litres(250.0, 6.0);
+        assert_eq!(litres, 15.0);
+        assert_eq!(
+            costs(litres, &stats, 1.0),
+            Costs {
+                min: Some(19.5),
+                avg: Some(21.0),
+                max: Some(22.5),
+            }
+        );
+        // heating fuel quoted per 1000 litres
+        let stats = DistrictStats {
+            min: Some(1300.0),
+            ..stats
+        };
+        assert_eq!(costs(litres, &stats, 1000.0).min, Some(19.5));
+
+        let none = DistrictStats {
+            count: 0,
+            min: None,
+            max: None,
+            avg: None,
+            median: None,
+        };
+        assert_eq!(costs(litres, &none, 1.0).avg, None);
+    }
+}
@@ -0,0 +1,134 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::{AreasByDistrict, District, PetroleumStation, PetroleumType, PriceUnit, StationStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::age::DataAge;
+use crate::lang;
+use crate::SharedState;
+
+fn default_limit() -> usize {
+    10
+}
+
+#[derive(Deserialize)]
+pub struct CheapestQuery {
+    pub fuel: String,
+    pub district: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize)]
+pub struct CheapestStations {
+    pub petroleum_type: PetroleumType,
+    pub district: District,
+    pub updated_at: u128,
+    pub currency: &'static str,
+    pub unit: PriceUnit,
+    pub stations: Vec<PetroleumStation>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+/// The `limit` cheapest stations of `district` that are online and open. Outliers are left out,
+/// a price far below the rest is more likely a typo than a bargain.
+pub fn cheapest(
+    stations: &[PetroleumStation],
+    areas: &AreasByDistrict,
+    district: District,
+    limit: usize,
+) -> Vec<PetroleumStation> {
+    let mut cheapest = stations
+        .iter()
+        .filter(|station| !station.offline && !station.outlier)
+        .filter(|station| station.status != Some(StationStatus::Closed))
+        .filter(|station| match district {
+            District::All => true,
+            district => areas
+                .get(&district)
+                .is_some_and(|district_areas| district_areas.contains(&station.area)),
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    cheapest.sort_by(|a, b| a.price.total_cmp(&b.price));
+    cheapest.truncate(limit);
+    cheapest
+}
+
+#[get("/prices/cheapest")]
+pub async fn cheapest_prices(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    query: web::Query<CheapestQuery>,
+) -> impl Responder {
+    let Some(petroleum_type) = PetroleumType::from_name(&query.fuel) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": format!("Unknown fuel {}", query.fuel) }));
+    };
+    let district = match &query.district {
+        None => District::All,
+        Some(name) => match District::from_name(name) {
+            Some(district) => district,
+            None => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({ "error": format!("Unknown district {}", name) }))
+            }
+        },
+    };
+    if query.limit == 0 {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "limit must be positive" }));
+    }
+
+    let state = data.read();
+    let Some(list) = state.price_list(petroleum_type) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let age = DataAge::of(&req, list.updated_at);
+    let cheapest = CheapestStations {
+        petroleum_type,
+        district,
+        updated_at: list.updated_at,
+        currency: list.currency,
+        unit: list.unit,
+        stations: state.aggregates.cheapest(petroleum_type, district, query.limit),
+        stale: age.stale,
+    };
+    let mut res = HttpResponse::Ok().json(lang::localized(&req, cheapest));
+    age.insert(res.headers_mut());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, StationStatus};
+
+    use crate::cheapest::cheapest;
+    use crate::fixtures::station;
+
+    #[test]
+    fn cheapest_online_stations_of_district() {
+        let areas = AreasByDistrict::from([(District::Nicosia, vec!["Strovolos".to_string()])]);
+        let mut offline = station("offline", "Strovolos", 1.10);
+        offline.offline = true;
+        let mut closed = station("closed", "Strovolos", 1.11);
+        closed.status = Some(StationStatus::Closed);
+        let mut outlier = station("outlier", "Strovolos", 0.14);
+        outlier.outlier = true;
+        let stations = vec![
+            station("b", "Strovolos", 1.40),
+            station("paphos", "Peyia", 1.20),
+            offline,
+            closed,
+            outlier,
+            station("a", "Strovolos", 1.35),
+            station("c", "Strovolos", 1.45),
+        ];
+
+        let nicosia = cheapest(&stations, &areas, District::Nicosia, 2);
+        assert_eq!(
+            nicosia.iter().map(|s| s.station_id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(cheapest(&stations, &areas, District::All, 1)[0].station_id, "paphos");
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::PetroleumType;
+use serde::{Deserialize, Serialize};
+
+use crate::nationwide::{self, FuelQuery, MergedStation};
+use crate::status::StationFilter;
+use crate::SharedState;
+
+// clusters across a 256 pixel map tile, about 64 pixels apart
+const CELLS_PER_TILE: f64 = 4.0;
+const MAX_ZOOM: u8 = 20;
+// where Web Mercator stops
+const MAX_LATITUDE: f64 = 85.051_128;
+
+#[derive(Deserialize)]
+pub struct ClusterQuery {
+    // `min_lon,min_lat,max_lon,max_lat`, the whole map by default
+    pub bbox: Option<String>,
+    pub zoom: Option<u8>,
+}
+
+/// Longitudes and latitudes stations are looked for within.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BoundingBox {
+    pub min_longitude: f64,
+    pub min_latitude: f64,
+    pub max_longitude: f64,
+    pub max_latitude: f64,
+}
+
+impl BoundingBox {
+    pub fn parse(bbox: &str) -> Result<Self, String> {
+        let corners = bbox
+            .split(',')
+            .map(|corner| corner.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("{}: expected min_lon,min_lat,max_lon,max_lat", bbox))?;
+        let [min_longitude, min_latitude, max_longitude, max_latitude] = corners[..] else {
+            return Err(format!("{}: expected min_lon,min_lat,max_lon,max_lat", bbox));
+        };
+        if min_longitude > max_longitude || min_latitude > max_latitude {
+            return Err(format!("{}: the minimum is past the maximum", bbox));
+        }
+        Ok(BoundingBox {
+            min_longitude,
+            min_latitude,
+            max_longitude,
+            max_latitude,
+        })
+    }
+
+    fn contains(&self, (longitude, latitude): (f64, f64)) -> bool {
+        (self.min_longitude..=self.max_longitude).contains(&longitude)
+            && (self.min_latitude..=self.max_latitude).contains(&latitude)
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Cluster {
+    pub count: usize,
+    // the average position of its stations
+    pub longitude: f64,
+    pub latitude: f64,
+    // only when a single fuel is selected
+    pub min_price: Option<f32>,
+    // the station itself, when alone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub station_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Clusters {
+    pub zoom: u8,
+    pub clusters: Vec<Cluster>,
+}
+
+// the Web Mercator grid cell of a position at `zoom`, cells about as far apart at every latitude
+fn cell((longitude, latitude): (f64, f64), zoom: u8) -> (i64, i64) {
+    let cells = 2f64.powi(zoom.into()) * CELLS_PER_TILE;
+    let latitude = latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (longitude + 180.0) / 360.0;
+    let y = (1.0 - (latitude.tan() + 1.0 / latitude.cos()).ln() / PI) / 2.0;
+    ((x * cells).floor() as i64, (y * cells).floor() as i64)
+}
+
+// a station with its longitude and latitude
+type Located = (MergedStation, (f64, f64));
+
+/// Stations of `stations` within `bbox` grouped by nearness at `zoom`, counted and priced by
+/// the cheapest of `price_of` a petroleum type if given.
+pub fn clusters(
+    stations: Vec<MergedStation>,
+    bbox: &BoundingBox,
+    zoom: u8,
+    price_of: Option<PetroleumType>,
+) -> Vec<Cluster> {
+    let mut cells: BTreeMap<(i64, i64), Vec<Located>> = BTreeMap::new();
+    for station in stations {
+        let latitude = station.latitude.trim().parse::<f64>();
+        let longitude = station.longitude.trim().parse::<f64>();
+        let (Ok(longitude), Ok(latitude)) = (longitude, latitude) else {
+            continue;
+        };
+        if bbox.contains((longitude, latitude)) {
+            cells
+                .entry(cell((longitude, latitude), zoom))
+                .or_default()
+                .push((station, (longitude, latitude)));
+        }
+    }
+    cells
+        .into_values()
+        .map(|stations| {
+            let count = stations.len();
+            let min_price = price_of.and_then(|petroleum_type| {
+                stations
+                    .iter()
+                    .filter_map(|(station, _)| station.prices.get(&petroleum_type).copied())
+                    .min_by(f32::total_cmp)
+            });
+            Cluster {
+                count,
+                longitude: stations.iter().map(|(_, (longitude, _))| longitude).sum::<f64>() / count as f64,
+                latitude: stations.iter().map(|(_, (_, latitude))| latitude).sum::<f64>() / count as f64,
+                min_price,
+                station_id: match &stations[..] {
+                    [(station, _)] => Some(station.station_id.clone()),
+                    _ => None,
+                },
+            }
+        })
+        .collect()
+}
+
+#[get("/stations/clusters")]
+pub async fn station_clusters(
+    data: web::Data<SharedState>,
+    query: web::Query<ClusterQuery>,
+    fuel: web::Query<FuelQuery>,
+    filter: web::Query<StationFilter>,
+) -> impl Responder {
+    let bbox = match query.bbox.as_deref().map(BoundingBox::parse) {
+        None => Ok(BoundingBox {
+            min_longitude: -180.0,
+            min_latitude: -90.0,
+            max_longitude: 180.0,
+            max_latitude: 90.0,
+        }),
+        Some(bbox) => bbox,
+    };
+    let zoom = match query.zoom {
+        None => Err("zoom is required".to_string()),
+        Some(zoom) if zoom > MAX_ZOOM => Err(format!("zoom is {} at most", MAX_ZOOM)),
+        Some(zoom) => Ok(zoom),
+    };
+    let checked = bbox.and_then(|bbox| Ok((bbox, zoom?, fuel.petroleum_types()?)));
+    let (bbox, zoom, selected) = match checked {
+        Ok(checked) => checked,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let price_of = match selected[..] {
+        [petroleum_type] => Some(petroleum_type),
+        _ => None,
+    };
+
+    let state = data.read();
+    let lists = selected
+        .into_iter()
+        .filter_map(|petroleum_type| state.price_list(petroleum_type))
+        .map(|list| filter.apply(list))
+        .collect::<Vec<_>>();
+    let merged = nationwide::merge(&lists.iter().collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(Clusters {
+        zoom,
+        clusters: clusters(merged.stations, &bbox, zoom, price_of),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::PetroleumType;
+
+    use crate::clusters::{clusters, BoundingBox};
+    use crate::fixtures::merged_at;
+
+    #[test]
+    fn near_stations_cluster_at_low_zooms() {
+        let stations = || {
+            vec![
+                // two in Nicosia, one in Limassol, one without coordinates
+                merged_at("a", "35.1264", "33.3614", &[(PetroleumType::Unlead95, 1.40)]),
+                merged_at("b", "35.1300", "33.3700", &[(PetroleumType::Unlead95, 1.35)]),
+                merged_at("c", "34.6786", "33.0413", &[(PetroleumType::Unlead95, 1.30)]),
+                merged_at("d", "", "", &[(PetroleumType::Unlead95, 1.10)]),
+            ]
+        };
+        let cyprus = BoundingBox::parse("32.2,34.5,34.6,35.7").unwrap();
+
+        let country = clusters(stations(), &cyprus, 8, Some(PetroleumType::Unlead95));
+        assert_eq!(country.len(), 2);
+        let nicosia = country.iter().find(|cluster| cluster.count == 2).unwrap();
+        assert_eq!(nicosia.min_price, Some(1.35));
+        assert!((nicosia.longitude - 33.3657).abs() < 1e-9);
+        assert_eq!(nicosia.station_id, None);
+        let limassol = country.iter().find(|cluster| cluster.count == 1).unwrap();
+        assert_eq!(limassol.station_id.as_deref(), Some("c"));
+
+        let street = clusters(stations(), &cyprus, 16, None);
+        assert_eq!(street.len(), 3);
+        assert!(street.iter().all(|cluster| cluster.min_price.is_none()));
+
+        let nicosia_only = BoundingBox::parse("33.3,35.1,33.4,35.2").unwrap();
+        assert_eq!(clusters(stations(), &nicosia_only, 8, None).len(), 1);
+
+        assert!(BoundingBox::parse("33.4,35.1,33.3,35.2").is_err());
+        assert!(BoundingBox::parse("33.3,35.1,33.4").is_err());
+    }
+}
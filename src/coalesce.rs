@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use actix_web::error::BlockingError;
+use actix_web::{web, HttpRequest};
+use tokio::sync::OnceCell;
+
+type Flight<T> = Arc<OnceCell<Arc<T>>>;
+
+/// Runs identical requests arriving together once, every waiter gets the same result. A key
+/// is only shared while its computation runs, a later request computes again.
+pub struct SingleFlight<T> {
+    flights: Mutex<HashMap<String, Flight<T>>>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        SingleFlight {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> SingleFlight<T> {
+    /// Result of `compute` for `key`, computed off the async workers so requests on the same
+    /// worker can join it too.
+    pub async fn run<F>(&self, key: String, compute: F) -> Result<Arc<T>, BlockingError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let flight = self.flights.lock().unwrap().entry(key.clone()).or_default().clone();
+        let result = flight
+            .get_or_try_init(|| async { web::block(compute).await.map(Arc::new) })
+            .await
+            .cloned();
+
+        let mut flights = self.flights.lock().unwrap();
+        if flights.get(&key).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+            flights.remove(&key);
+        }
+        result
+    }
+}
+
+/// Key of a request by its path, its query parameters in any order and the `version` of the
+/// data it is computed from.
+pub fn request_key(req: &HttpRequest, version: u128) -> String {
+    let mut params = req
+        .query_string()
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .collect::<Vec<_>>();
+    params.sort();
+    format!("{}?{}@{}", req.path(), params.join("&"), version)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use actix_web::test::TestRequest;
+
+    use crate::coalesce::{request_key, SingleFlight};
+
+    #[actix_web::test]
+    async fn computes_concurrent_identical_requests_once() {
+        let flight = SingleFlight::<usize>::default();
+        let computed = Arc::new(AtomicUsize::new(0));
+        let compute = || {
+            let computed = computed.clone();
+            move || {
+                thread::sleep(Duration::from_millis(50));
+                computed.fetch_add(1, Ordering::SeqCst) + 1
+            }
+        };
+
+        let results = futures_util::future::join_all(
+            (0..10).map(|_| flight.run("/stations?@1".to_string(), compute())),
+        )
+        .await;
+        assert!(results.iter().all(|result| **result.as_ref().unwrap() == 1));
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+
+        // nothing in flight any more
+        assert_eq!(*flight.run("/stations?@1".to_string(), compute()).await.unwrap(), 2);
+    }
+
+    #[test]
+    fn keys_ignore_parameter_order() {
+        let a = TestRequest::with_uri("/prices/all?fuel=diesel_auto&include_closed=true").to_http_request();
+        let b = TestRequest::with_uri("/prices/all?include_closed=true&fuel=diesel_auto").to_http_request();
+        assert_eq!(request_key(&a, 1), request_key(&b, 1));
+        assert_ne!(request_key(&a, 1), request_key(&a, 2));
+    }
+}
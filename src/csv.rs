@@ -0,0 +1,310 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::normalize::Transliteration;
+use cygaz_lib::PetroleumType;
+use serde::Deserialize;
+
+use crate::age::DataAge;
+use crate::lang::Lang;
+use crate::nationwide::NationwidePriceList;
+use crate::status::StationFilter;
+use crate::{PriceList, SharedState};
+
+#[derive(Deserialize)]
+pub struct CsvQuery {
+    #[serde(default)]
+    pub lang: Lang,
+    // `csv` asks for CSV where JSON is the default
+    pub format: Option<String>,
+}
+
+impl Lang {
+    // Greek spreadsheet locales expect a decimal comma, hence `;` between fields
+    fn separator(&self) -> char {
+        match self {
+            Lang::El => ';',
+            Lang::En => ',',
+        }
+    }
+
+    fn headers(&self) -> [&'static str; 8] {
+        match self {
+            Lang::El => [
+                "Μάρκα",
+                "Εταιρεία",
+                "Διεύθυνση",
+                "Περιοχή",
+                "Γεωγραφικό πλάτος",
+                "Γεωγραφικό μήκος",
+                "Τιμή",
+                "Εκτός λειτουργίας",
+            ],
+            Lang::En => [
+                "Brand",
+                "Company",
+                "Address",
+                "Area",
+                "Latitude",
+                "Longitude",
+                "Price",
+                "Offline",
+            ],
+        }
+    }
+
+    // names as upstream lists them, or in Latin letters
+    fn name(&self, name: &str, transliteration: &Transliteration) -> String {
+        match self {
+            Lang::El => name.to_string(),
+            Lang::En => transliteration.apply(name),
+        }
+    }
+
+    fn petroleum_type(&self, petroleum_type: PetroleumType) -> &'static str {
+        match self {
+            Lang::El => petroleum_type.label_el(),
+            Lang::En => petroleum_type.label_en(),
+        }
+    }
+
+    fn price(&self, price: f32) -> String {
+        match self {
+            Lang::El => price.to_string().replace('.', ","),
+            Lang::En => price.to_string(),
+        }
+    }
+
+    fn yes_no(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (Lang::El, true) => "Ναι",
+            (Lang::El, false) => "Όχι",
+            (Lang::En, true) => "Yes",
+            (Lang::En, false) => "No",
+        }
+    }
+}
+
+fn push_field(line: &mut String, field: &str, separator: char) {
+    if field.contains([separator, '"', '\n', '\r']) {
+        line.push('"');
+        line.push_str(&field.replace('"', "\"\""));
+        line.push('"');
+    } else {
+        line.push_str(field);
+    }
+}
+
+pub fn push_row<S: AsRef<str>>(csv: &mut String, fields: &[S], separator: char) {
+    for (idx, field) in fields.iter().enumerate() {
+        if idx > 0 {
+            csv.push(separator);
+        }
+        push_field(csv, field.as_ref(), separator);
+    }
+    csv.push_str("\r\n");
+}
+
+/// Stations of `list` as CSV, starting with a byte order mark so spreadsheets pick up UTF-8.
+pub fn price_list_csv(list: &PriceList, lang: Lang, transliteration: &Transliteration) -> String {
+    let separator = lang.separator();
+    let mut csv = String::from('\u{feff}');
+
+    push_row(&mut csv, &lang.headers(), separator);
+    for station in &list.stations {
+        push_row(
+            &mut csv,
+            &[
+                lang.name(&station.brand, transliteration),
+                lang.name(&station.company, transliteration),
+                lang.name(&station.address, transliteration),
+                lang.name(&station.area, transliteration),
+                station.latitude.clone(),
+                station.longitude.clone(),
+                lang.price(station.price),
+                lang.yes_no(station.offline).to_string(),
+            ],
+            separator,
+        );
+    }
+
+    csv
+}
+
+/// Stations of `list` as CSV, one price column per petroleum type in the order of `stats`, left
+/// empty where a station does not sell it.
+pub fn nationwide_csv(list: &NationwidePriceList, lang: Lang, transliteration: &Transliteration) -> String {
+    let separator = lang.separator();
+    let mut csv = String::from('\u{feff}');
+
+    let [brand, company, address, area, latitude, longitude, _, offline] = lang.headers();
+    let petroleum_types = list.stats.iter().map(|stats| stats.petroleum_type).collect::<Vec<_>>();
+    let mut headers = vec![brand, company, address, area, latitude, longitude];
+    headers.extend(petroleum_types.iter().map(|petroleum_type| lang.petroleum_type(*petroleum_type)));
+    headers.push(offline);
+    push_row(&mut csv, &headers, separator);
+
+    for station in &list.stations {
+        let mut fields = vec![
+            lang.name(&station.brand, transliteration),
+            lang.name(&station.company, transliteration),
+            lang.name(&station.address, transliteration),
+            lang.name(&station.area, transliteration),
+            station.latitude.clone(),
+            station.longitude.clone(),
+        ];
+        fields.extend(petroleum_types.iter().map(|petroleum_type| {
+            station.prices.get(petroleum_type).map(|price| lang.price(*price)).unwrap_or_default()
+        }));
+        fields.push(lang.yes_no(station.offline).to_string());
+        push_row(&mut csv, &fields, separator);
+    }
+
+    csv
+}
+
+// `Accept: text/csv` or `?format=csv`, JSON stays the default for `*/*`
+fn wants_csv(req: &HttpRequest, query: &CsvQuery) -> bool {
+    if let Some(format) = &query.format {
+        return format.eq_ignore_ascii_case("csv");
+    }
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| {
+            accept
+                .split(',')
+                .filter_map(|range| range.split(';').next())
+                .any(|media_type| media_type.trim().eq_ignore_ascii_case("text/csv"))
+        })
+}
+
+/// The CSV `csv` writes when the request asks for CSV rather than JSON, in the `?lang=` it asks for.
+pub fn negotiate(req: &HttpRequest, csv: impl FnOnce(Lang, &Transliteration) -> String) -> Option<HttpResponse> {
+    let query = web::Query::<CsvQuery>::from_query(req.query_string()).ok()?;
+    if !wants_csv(req, &query) {
+        return None;
+    }
+    let transliteration = req.app_data::<web::Data<Transliteration>>().cloned().unwrap_or_default();
+    Some(
+        HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((header::VARY, "Accept"))
+            .body(csv(query.lang, &transliteration)),
+    )
+}
+
+/// Marks the JSON `negotiate` turned down as varying by `Accept` too, so caches keep it apart from the CSV.
+pub fn vary(mut res: HttpResponse) -> HttpResponse {
+    res.headers_mut().append(header::VARY, HeaderValue::from_static("Accept"));
+    res
+}
+
+#[get("/prices/{id}.csv")]
+pub async fn prices_csv(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    id: web::Path<i32>,
+    query: web::Query<CsvQuery>,
+    filter: web::Query<StationFilter>,
+    transliteration: web::Data<Transliteration>,
+) -> impl Responder {
+    let Some(petroleum_type) = PetroleumType::from_id(id.into_inner()) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let state = data.read();
+    let Some(list) = state.price_list(petroleum_type) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let list = filter.apply(list);
+    let mut res = HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"prices-{}.csv\"", petroleum_type as i32),
+        ))
+        .body(price_list_csv(&list, query.lang, &transliteration));
+    DataAge::of(&req, list.updated_at).insert(res.headers_mut());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::normalize::Transliteration;
+    use cygaz_lib::PetroleumType;
+
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+    use actix_web::HttpResponse;
+
+    use crate::csv::{nationwide_csv, negotiate, price_list_csv, vary, Lang};
+    use crate::fixtures::{list, station};
+    use crate::nationwide::merge;
+    use crate::PriceList;
+
+    fn makariou() -> PriceList {
+        let station = station("", "Στρόβολος", 1.389)
+            .with_company("Petrolina (Holdings), Ltd")
+            .with_address("Λεωφόρος \"Μακαρίου\" 8");
+        list(PetroleumType::DieselAuto, 0, vec![station])
+    }
+
+    #[test]
+    fn english_csv() {
+        let csv = price_list_csv(&makariou(), Lang::En, &Transliteration::Letters);
+        let lines = csv.trim_start_matches('\u{feff}').lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Brand,Company,Address,Area,Latitude,Longitude,Price,Offline");
+        assert_eq!(
+            lines[1],
+            "EKO,\"Petrolina (Holdings), Ltd\",\"Leoforos \"\"Makariou\"\" 8\",Strovolos,35.1,33.3,1.389,No"
+        );
+    }
+
+    #[test]
+    fn greek_csv() {
+        let csv = price_list_csv(&makariou(), Lang::El, &Transliteration::Letters);
+        let lines = csv.trim_start_matches('\u{feff}').lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("Μάρκα;Εταιρεία;"));
+        assert_eq!(
+            lines[1],
+            "EKO;Petrolina (Holdings), Ltd;\"Λεωφόρος \"\"Μακαρίου\"\" 8\";Στρόβολος;35.1;33.3;1,389;Όχι"
+        );
+    }
+
+    #[test]
+    fn nationwide_csv_has_a_column_per_fuel() {
+        let mut unlead95 = makariou();
+        unlead95.petroleum_type = PetroleumType::Unlead95;
+        unlead95.stations[0].address = "Other street 1".to_string();
+        let merged = merge(&[&unlead95, &makariou()]);
+
+        let csv = nationwide_csv(&merged, Lang::En, &Transliteration::Letters);
+        let lines = csv.trim_start_matches('\u{feff}').lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Brand,Company,Address,Area,Latitude,Longitude,Unleaded 95,Diesel,Offline");
+        assert!(lines[1].ends_with(",35.1,33.3,1.389,,No"));
+        assert!(lines[2].ends_with(",35.1,33.3,,1.389,No"));
+    }
+
+    #[test]
+    fn negotiates_csv_by_accept_or_format() {
+        let csv = |_: Lang, _: &Transliteration| String::new();
+        let req = TestRequest::default().insert_header(("Accept", "text/csv;q=0.9")).to_http_request();
+        assert!(negotiate(&req, csv).is_some());
+        let req = TestRequest::default().uri("/prices/4?format=csv").to_http_request();
+        assert!(negotiate(&req, csv).is_some());
+        let req = TestRequest::default()
+            .uri("/prices/4?format=json")
+            .insert_header(("Accept", "text/csv"))
+            .to_http_request();
+        assert!(negotiate(&req, csv).is_none());
+        let req = TestRequest::default().insert_header(("Accept", "*/*")).to_http_request();
+        assert!(negotiate(&req, csv).is_none());
+
+        let req = TestRequest::default().insert_header(("Accept", "text/csv")).to_http_request();
+        let res = negotiate(&req, csv).unwrap();
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "Accept");
+        let res = vary(HttpResponse::Ok().insert_header((header::VARY, "Origin")).finish());
+        let vary = res.headers().get_all(header::VARY).collect::<Vec<_>>();
+        assert_eq!(vary, ["Origin", "Accept"]);
+    }
+}
@@ -0,0 +1,311 @@
+#[cfg(feature = "database")]
+use std::sync::Mutex;
+
+use cygaz_lib::PetroleumType;
+#[cfg(feature = "database")]
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::PriceList;
+
+/// A station price as upstream listed it at one refresh.
+pub struct Observation {
+    pub station_id: String,
+    pub petroleum_type: PetroleumType,
+    pub price: f32,
+    pub observed_at: u128,
+}
+
+impl Observation {
+    /// Every station price of `list`, leaving out the ones carried forward from an earlier refresh.
+    pub fn of(list: &PriceList) -> Vec<Observation> {
+        list.stations
+            .iter()
+            .filter(|station| !station.carried_forward)
+            .map(|station| Observation {
+                station_id: station.station_id.clone(),
+                petroleum_type: list.petroleum_type,
+                price: station.price,
+                observed_at: list.updated_at,
+            })
+            .collect()
+    }
+}
+
+pub const DAY: u128 = 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Copy, Serialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    // every stored observation
+    Refresh,
+    // the average of every UTC day, timed at its start
+    Daily,
+}
+
+/// Station prices of every refresh in SQLite, unlike the in-memory history they survive restarts.
+#[cfg(feature = "database")]
+pub struct PriceDatabase {
+    conn: Mutex<Connection>,
+    // milliseconds observations are kept for, `0` keeps them all
+    retention: u128,
+}
+
+/// Compiled without the `database` feature, none opens and the history is never stored.
+#[cfg(not(feature = "database"))]
+pub enum PriceDatabase {}
+
+#[cfg(feature = "database")]
+impl PriceDatabase {
+    pub fn open(path: &str, retention: u128) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|err| format!("{}: {}", path, err))?;
+        Self::migrate(conn, retention).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    fn migrate(conn: Connection, retention: u128) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS prices (
+                station_id TEXT NOT NULL,
+                fuel INTEGER NOT NULL,
+                price REAL NOT NULL,
+                observed_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS prices_by_station ON prices (station_id, fuel, observed_at);
+            CREATE INDEX IF NOT EXISTS prices_by_time ON prices (observed_at);",
+        )?;
+        Ok(PriceDatabase {
+            conn: Mutex::new(conn),
+            retention,
+        })
+    }
+
+    /// Appends `observations` in one transaction, returns how many were stored. Drops the ones
+    /// stored before, older than the retention by the time of the latest of them.
+    pub fn record(&self, observations: &[Observation]) -> Result<usize, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|err| err.to_string())?;
+        let latest = observations.iter().map(|observation| observation.observed_at).max();
+        if let Some(latest) = latest.filter(|_| self.retention > 0) {
+            let before = latest.saturating_sub(self.retention) as i64;
+            tx.execute("DELETE FROM prices WHERE observed_at < ?1", params![before])
+                .map_err(|err| err.to_string())?;
+        }
+        {
+            let mut insert = tx
+                .prepare_cached("INSERT INTO prices (station_id, fuel, price, observed_at) VALUES (?1, ?2, ?3, ?4)")
+                .map_err(|err| err.to_string())?;
+            for observation in observations {
+                insert
+                    .execute(params![
+                        observation.station_id,
+                        observation.petroleum_type as i32,
+                        observation.price,
+                        observation.observed_at as i64,
+                    ])
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        tx.commit().map_err(|err| err.to_string())?;
+        Ok(observations.len())
+    }
+
+    /// Prices of `station_id` observed from `from` to `to`, both included, of one fuel or all of
+    /// them, oldest first.
+    pub fn station_history(
+        &self,
+        station_id: &str,
+        petroleum_type: Option<PetroleumType>,
+        from: u128,
+        to: u128,
+        resolution: Resolution,
+    ) -> Result<Vec<Observation>, String> {
+        let sql = match resolution {
+            Resolution::Refresh => {
+                "SELECT fuel, price, observed_at FROM prices
+                WHERE station_id = ?1 AND (?2 IS NULL OR fuel = ?2) AND observed_at BETWEEN ?3 AND ?4
+                ORDER BY fuel, observed_at"
+            }
+            Resolution::Daily => {
+                "SELECT fuel, AVG(price), observed_at / ?5 * ?5 AS day FROM prices
+                WHERE station_id = ?1 AND (?2 IS NULL OR fuel = ?2) AND observed_at BETWEEN ?3 AND ?4
+                GROUP BY fuel, day ORDER BY fuel, day"
+            }
+        };
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn.prepare_cached(sql).map_err(|err| err.to_string())?;
+        let fuel = petroleum_type.map(|petroleum_type| petroleum_type as i32);
+        let rows = match resolution {
+            Resolution::Refresh => select.query(params![station_id, fuel, from as i64, to as i64]),
+            Resolution::Daily => select.query(params![station_id, fuel, from as i64, to as i64, DAY as i64]),
+        };
+        let observations = rows
+            .and_then(|rows| {
+                rows.mapped(|row| Ok((row.get::<_, i32>(0)?, row.get::<_, f64>(1)?, row.get::<_, i64>(2)?)))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|err| err.to_string())?;
+
+        Ok(observations
+            .into_iter()
+            .filter_map(|(fuel, price, observed_at)| {
+                Some(Observation {
+                    station_id: station_id.to_string(),
+                    petroleum_type: PetroleumType::from_id(fuel)?,
+                    price: price as f32,
+                    observed_at: observed_at as u128,
+                })
+            })
+            .collect())
+    }
+
+    /// The average price of `petroleum_type` of every station and UTC day from `from` to `to`,
+    /// both included, timed at the start of the day, oldest first.
+    pub fn daily_prices(
+        &self,
+        petroleum_type: PetroleumType,
+        from: u128,
+        to: u128,
+    ) -> Result<Vec<Observation>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn
+            .prepare_cached(
+                "SELECT station_id, AVG(price), observed_at / ?4 * ?4 AS day FROM prices
+                WHERE fuel = ?1 AND observed_at BETWEEN ?2 AND ?3
+                GROUP BY station_id, day ORDER BY day, station_id",
+            )
+            .map_err(|err| err.to_string())?;
+        let rows = select
+            .query_map(
+                params![petroleum_type as i32, from as i64, to as i64, DAY as i64],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, i64>(2)?)),
+            )
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| err.to_string())?;
+        Ok(rows
+            .into_iter()
+            .map(|(station_id, price, day)| Observation {
+                station_id,
+                petroleum_type,
+                price: price as f32,
+                observed_at: day as u128,
+            })
+            .collect())
+    }
+}
+
+#[cfg(not(feature = "database"))]
+impl PriceDatabase {
+    pub fn open(_path: &str, _retention: u128) -> Result<Self, String> {
+        Err("compiled without the database feature".to_string())
+    }
+
+    pub fn record(&self, _observations: &[Observation]) -> Result<usize, String> {
+        match *self {}
+    }
+
+    pub fn station_history(
+        &self,
+        _station_id: &str,
+        _petroleum_type: Option<PetroleumType>,
+        _from: u128,
+        _to: u128,
+        _resolution: Resolution,
+    ) -> Result<Vec<Observation>, String> {
+        match *self {}
+    }
+
+    pub fn daily_prices(
+        &self,
+        _petroleum_type: PetroleumType,
+        _from: u128,
+        _to: u128,
+    ) -> Result<Vec<Observation>, String> {
+        match *self {}
+    }
+}
+
+#[cfg(all(test, feature = "database"))]
+mod tests {
+    use cygaz_lib::PetroleumType;
+    use rusqlite::Connection;
+
+    use crate::database::{Observation, PriceDatabase, Resolution, DAY};
+
+    #[test]
+    fn appends_observations() {
+        let db = PriceDatabase::migrate(Connection::open_in_memory().unwrap(), 0).unwrap();
+        let observation = |station_id: &str, price, observed_at| Observation {
+            station_id: station_id.to_string(),
+            petroleum_type: PetroleumType::DieselAuto,
+            price,
+            observed_at,
+        };
+        assert_eq!(db.record(&[observation("a", 1.4, 10), observation("b", 1.5, 10)]).unwrap(), 2);
+        assert_eq!(db.record(&[observation("a", 1.35, 20)]).unwrap(), 1);
+
+        let conn = db.conn.lock().unwrap();
+        let prices = conn
+            .prepare("SELECT price, observed_at FROM prices WHERE station_id = 'a' AND fuel = 4 ORDER BY observed_at")
+            .unwrap()
+            .query_map([], |row| Ok((row.get::<_, f32>(0)?, row.get::<_, i64>(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(prices, vec![(1.4, 10), (1.35, 20)]);
+    }
+
+    #[test]
+    fn drops_observations_past_the_retention() {
+        let db = PriceDatabase::migrate(Connection::open_in_memory().unwrap(), 10 * DAY).unwrap();
+        let observation = |observed_at| Observation {
+            station_id: "a".to_string(),
+            petroleum_type: PetroleumType::DieselAuto,
+            price: 1.4,
+            observed_at,
+        };
+        db.record(&[observation(DAY), observation(5 * DAY)]).unwrap();
+        db.record(&[]).unwrap();
+        db.record(&[observation(12 * DAY)]).unwrap();
+
+        let kept = db.station_history("a", None, 0, 20 * DAY, Resolution::Refresh).unwrap();
+        assert_eq!(kept.iter().map(|o| o.observed_at / DAY).collect::<Vec<_>>(), vec![5, 12]);
+    }
+
+    #[test]
+    fn downsamples_history_to_days() {
+        let db = PriceDatabase::migrate(Connection::open_in_memory().unwrap(), 0).unwrap();
+        let observation = |petroleum_type, price, observed_at| Observation {
+            station_id: "a".to_string(),
+            petroleum_type,
+            price,
+            observed_at,
+        };
+        db.record(&[
+            observation(PetroleumType::Unlead95, 1.3, DAY + 10),
+            observation(PetroleumType::Unlead95, 1.4, DAY + 20),
+            observation(PetroleumType::Unlead95, 1.5, 2 * DAY + 10),
+            observation(PetroleumType::Kerosene, 1.1, DAY + 10),
+        ])
+        .unwrap();
+
+        let refreshes = db
+            .station_history("a", Some(PetroleumType::Unlead95), DAY + 15, 3 * DAY, Resolution::Refresh)
+            .unwrap();
+        assert_eq!(refreshes.iter().map(|o| o.observed_at).collect::<Vec<_>>(), vec![DAY + 20, 2 * DAY + 10]);
+
+        let days = db.station_history("a", None, 0, 3 * DAY, Resolution::Daily).unwrap();
+        assert_eq!(days.len(), 3);
+        assert_eq!(days[0].petroleum_type, PetroleumType::Unlead95);
+        assert_eq!(days[0].observed_at, DAY);
+        assert!((days[0].price - 1.35).abs() < 1e-6);
+        assert_eq!(days[2].petroleum_type, PetroleumType::Kerosene);
+        assert!(db.station_history("b", None, 0, 3 * DAY, Resolution::Daily).unwrap().is_empty());
+
+        let unlead95 = db.daily_prices(PetroleumType::Unlead95, 0, 3 * DAY).unwrap();
+        let days = unlead95.iter().map(|o| (o.observed_at, o.price)).collect::<Vec<_>>();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].0, DAY);
+        assert!((days[0].1 - 1.35).abs() < 1e-6);
+        assert_eq!(days[1], (2 * DAY, 1.5));
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header::{HeaderValue, CACHE_CONTROL, CONTENT_ENCODING};
+use actix_web::web::Bytes;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::PetroleumType;
+use futures_util::stream;
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::time::{interval_at, Instant, Interval};
+
+use crate::updates::{ChangeCounts, PriceUpdate};
+use crate::SharedState;
+
+// events kept for clients resuming with Last-Event-ID
+const RECENT_EVENTS: usize = 100;
+
+// keeps idle streams open through proxies
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+#[derive(Debug)]
+pub struct Event {
+    pub id: u64,
+    pub event: &'static str,
+    pub data: String,
+}
+
+impl Event {
+    // as the server-sent events wire format has it
+    fn to_bytes(&self) -> Bytes {
+        Bytes::from(format!("id: {}\nevent: {}\ndata: {}\n\n", self.id, self.event, self.data))
+    }
+}
+
+#[derive(Serialize)]
+struct RefreshStarted {
+    started_at: u128,
+}
+
+#[derive(Serialize)]
+struct RefreshCompleted {
+    updated_at: u128,
+}
+
+#[derive(Serialize)]
+struct PricesChanged {
+    updated_at: u128,
+    petroleum_types: BTreeMap<PetroleumType, ChangeCounts>,
+}
+
+struct Recent {
+    next_id: u64,
+    events: VecDeque<Arc<Event>>,
+}
+
+/// Refresh events numbered in order, the latest kept for replaying to clients that reconnect.
+pub struct EventLog {
+    recent: Mutex<Recent>,
+    sender: Sender<Arc<Event>>,
+}
+
+impl Default for EventLog {
+    /// Numbered from the start of the process in milliseconds, shifted to leave room for a million
+    /// events, so those after a restart follow the ones before.
+    fn default() -> Self {
+        let started_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        EventLog::starting_at(started_at << 20)
+    }
+}
+
+impl EventLog {
+    fn starting_at(first_id: u64) -> Self {
+        EventLog {
+            recent: Mutex::new(Recent {
+                next_id: first_id,
+                events: VecDeque::with_capacity(RECENT_EVENTS),
+            }),
+            sender: broadcast::channel(RECENT_EVENTS).0,
+        }
+    }
+
+    fn publish(&self, event: &'static str, data: &impl Serialize) {
+        let Ok(data) = serde_json::to_string(data) else {
+            return;
+        };
+        let mut recent = self.recent.lock().unwrap();
+        let event = Arc::new(Event {
+            id: recent.next_id,
+            event,
+            data,
+        });
+        recent.next_id += 1;
+        if recent.events.len() == RECENT_EVENTS {
+            recent.events.pop_front();
+        }
+        recent.events.push_back(event.clone());
+        let _ = self.sender.send(event);
+    }
+
+    pub fn refresh_started(&self, started_at: u128) {
+        self.publish("refresh_started", &RefreshStarted { started_at });
+    }
+
+    /// `refresh_completed`, then `prices_changed` when anything did.
+    pub fn refresh_completed(&self, update: &PriceUpdate) {
+        self.publish(
+            "refresh_completed",
+            &RefreshCompleted {
+                updated_at: update.updated_at,
+            },
+        );
+        if update.changes.is_empty() {
+            return;
+        }
+        let petroleum_types = update
+            .changes
+            .iter()
+            .map(|delta| {
+                let counts = ChangeCounts {
+                    changed: delta.stations.len(),
+                    removed: delta.removed.len(),
+                };
+                (delta.petroleum_type, counts)
+            })
+            .collect();
+        self.publish(
+            "prices_changed",
+            &PricesChanged {
+                updated_at: update.updated_at,
+                petroleum_types,
+            },
+        );
+    }
+
+    /// Kept events after `last_id`, the id they resume after, and the events to come. An id this
+    /// process never gave out, from before a restart with the clock set back, resumes from the
+    /// first kept event. Subscribes under the same lock as publishing, so none falls in between.
+    pub fn subscribe(&self, last_id: Option<u64>) -> (Vec<Arc<Event>>, u64, Receiver<Arc<Event>>) {
+        let recent = self.recent.lock().unwrap();
+        let resumed = match last_id {
+            Some(last_id) if last_id >= recent.next_id => Some(0),
+            last_id => last_id,
+        };
+        let missed = match resumed {
+            None => vec![],
+            Some(last_id) => recent.events.iter().filter(|event| event.id > last_id).cloned().collect(),
+        };
+        (missed, resumed.unwrap_or_default(), self.sender.subscribe())
+    }
+
+    fn since(&self, last_id: u64) -> Vec<Arc<Event>> {
+        let recent = self.recent.lock().unwrap();
+        recent.events.iter().filter(|event| event.id > last_id).cloned().collect()
+    }
+}
+
+struct Listener {
+    log: Arc<EventLog>,
+    pending: VecDeque<Arc<Event>>,
+    receiver: Receiver<Arc<Event>>,
+    heartbeat: Interval,
+    last_id: u64,
+}
+
+impl Listener {
+    async fn next(mut self) -> Option<(Result<Bytes, Error>, Self)> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                if event.id <= self.last_id {
+                    continue;
+                }
+                self.last_id = event.id;
+                return Some((Ok(event.to_bytes()), self));
+            }
+            tokio::select! {
+                event = self.receiver.recv() => match event {
+                    Ok(event) => self.pending.push_back(event),
+                    // catches up from the kept events, what is gone by now is lost
+                    Err(RecvError::Lagged(_)) => self.pending.extend(self.log.since(self.last_id)),
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = self.heartbeat.tick() => return Some((Ok(Bytes::from_static(b": heartbeat\n\n")), self)),
+            }
+        }
+    }
+}
+
+#[get("/events")]
+pub async fn refresh_events(req: HttpRequest, data: web::Data<SharedState>) -> impl Responder {
+    let last_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    let log = data.read().updates.events();
+    let (missed, last_id, receiver) = log.subscribe(last_id);
+
+    let listener = Listener {
+        log,
+        pending: missed.into(),
+        receiver,
+        heartbeat: interval_at(Instant::now() + HEARTBEAT, HEARTBEAT),
+        last_id,
+    };
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((CACHE_CONTROL, HeaderValue::from_static("no-cache")))
+        // compressing would hold events back until enough of them fill a block
+        .insert_header((CONTENT_ENCODING, HeaderValue::from_static("identity")))
+        .streaming(stream::unfold(listener, Listener::next))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::EventLog;
+    use crate::updates::PriceUpdate;
+
+    #[test]
+    fn replays_events_after_last_id() {
+        let log = EventLog::starting_at(1);
+        log.refresh_started(10);
+        log.refresh_completed(&PriceUpdate {
+            kind: "refresh_completed",
+            updated_at: 20,
+            changes: vec![],
+        });
+        log.refresh_started(30);
+
+        let (missed, last_id, _) = log.subscribe(Some(1));
+        assert_eq!(last_id, 1);
+        assert_eq!(missed.iter().map(|event| event.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(missed[0].event, "refresh_completed");
+        assert_eq!(missed[0].data, r#"{"updated_at":20}"#);
+        assert_eq!(
+            String::from_utf8(missed[1].to_bytes().to_vec()).unwrap(),
+            "id: 3\nevent: refresh_started\ndata: {\"started_at\":30}\n\n"
+        );
+
+        let (missed, _, _) = log.subscribe(None);
+        assert!(missed.is_empty());
+    }
+
+    #[test]
+    fn resumes_across_a_restart() {
+        let before = EventLog::default();
+        before.refresh_started(10);
+        let last_id = before.since(0)[0].id;
+
+        // numbered after the events of the process before
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let after = EventLog::default();
+        after.refresh_started(20);
+        let (missed, resumed, _) = after.subscribe(Some(last_id));
+        assert_eq!((missed.len(), resumed), (1, last_id));
+        assert!(missed[0].id > last_id);
+
+        // or with the clock set back, from the first kept event
+        let set_back = EventLog::starting_at(last_id - 100);
+        set_back.refresh_started(30);
+        set_back.refresh_started(40);
+        let (missed, resumed, _) = set_back.subscribe(Some(last_id));
+        assert_eq!((missed.len(), resumed), (2, 0));
+        assert_eq!(missed[0].data, r#"{"started_at":30}"#);
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::{Cursor, Write};
+
+use actix_web::http::header;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use cygaz_lib::normalize::Transliteration;
+use cygaz_lib::AreasByDistrict;
+use serde::Deserialize;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::age::DataAge;
+use crate::csv::nationwide_csv;
+use crate::geojson::feature_collection;
+use crate::lang::{transliterate, Lang};
+use crate::nationwide::{self, FuelQuery, NationwidePriceList};
+use crate::status::StationFilter;
+use crate::SharedState;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    #[default]
+    Json,
+    Geojson,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Geojson => "geojson",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Geojson => "application/geo+json",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    // a zip archive of the file instead of the file itself
+    #[serde(default)]
+    pub zip: bool,
+    // of the CSV headers and values, English unless given, and of the names in JSON and GeoJSON
+    pub lang: Option<Lang>,
+}
+
+/// What the export of prices refreshed at `updated_at` is downloaded as, without the extension.
+pub fn file_stem(updated_at: u128) -> String {
+    let at = DateTime::<Utc>::from_timestamp_millis(updated_at as i64).unwrap_or_default();
+    format!("cygaz-prices-{}", at.format("%Y%m%dT%H%M%SZ"))
+}
+
+/// `list` written in `format`, the stations of GeoJSON priced by `list`'s only fuel if it has one.
+/// JSON and GeoJSON keep the names as upstream lists them unless `lang` is English.
+pub fn export(
+    mut list: NationwidePriceList,
+    areas: &AreasByDistrict,
+    format: ExportFormat,
+    lang: Option<Lang>,
+    transliteration: &Transliteration,
+) -> Vec<u8> {
+    let latin = lang == Some(Lang::En);
+    match format {
+        ExportFormat::Csv => nationwide_csv(&list, lang.unwrap_or_default(), transliteration).into_bytes(),
+        ExportFormat::Json => {
+            if latin {
+                transliterate(&mut list, transliteration);
+            }
+            serde_json::to_vec(&list).unwrap_or_default()
+        }
+        ExportFormat::Geojson => {
+            let price_of = match &list.stats[..] {
+                [stats] => Some(stats.petroleum_type),
+                _ => None,
+            };
+            // after the districts are looked up by the areas as upstream names them
+            let mut collection = feature_collection(list.stations, areas, price_of);
+            if latin {
+                transliterate(&mut collection, transliteration);
+            }
+            serde_json::to_vec(&collection).unwrap_or_default()
+        }
+    }
+}
+
+/// A zip archive holding `body` as `name`, last modified at `updated_at`.
+pub fn zipped(name: &str, body: &[u8], updated_at: u128) -> Result<Vec<u8>, String> {
+    let at = DateTime::<Utc>::from_timestamp_millis(updated_at as i64).unwrap_or_default();
+    let modified = zip::DateTime::from_date_and_time(
+        at.year() as u16,
+        at.month() as u8,
+        at.day() as u8,
+        at.hour() as u8,
+        at.minute() as u8,
+        at.second() as u8,
+    )
+    // zip dates start in 1980
+    .unwrap_or_default();
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(modified);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file(name, options).map_err(|err| err.to_string())?;
+    writer.write_all(body).map_err(|err| err.to_string())?;
+    let archive = writer.finish().map_err(|err| err.to_string())?;
+    Ok(archive.into_inner())
+}
+
+#[get("/export")]
+pub async fn export_prices(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    query: web::Query<ExportQuery>,
+    fuel: web::Query<FuelQuery>,
+    filter: web::Query<StationFilter>,
+    transliteration: web::Data<Transliteration>,
+) -> impl Responder {
+    let selected = match fuel.petroleum_types() {
+        Ok(selected) => selected,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+
+    let state = data.read();
+    let lists = selected
+        .into_iter()
+        .filter_map(|petroleum_type| state.price_list(petroleum_type))
+        .map(|list| filter.apply(list))
+        .collect::<Vec<_>>();
+    let merged = nationwide::merge(&lists.iter().collect::<Vec<_>>());
+    let areas = state.areas.clone();
+    drop(state);
+
+    let updated_at = merged.updated_at;
+    let name = format!("{}.{}", file_stem(updated_at), query.format.extension());
+    let body = export(merged, &areas, query.format, query.lang, &transliteration);
+    let (name, content_type, body) = match query.zip {
+        false => (name, query.format.content_type(), body),
+        true => match zipped(&name, &body, updated_at) {
+            Ok(archive) => (format!("{}.zip", file_stem(updated_at)), "application/zip", archive),
+            Err(err) => return HttpResponse::InternalServerError().json(serde_json::json!({ "error": err })),
+        },
+    };
+    let mut res = HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)))
+        .body(body);
+    DataAge::of(&req, updated_at).insert(res.headers_mut());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use cygaz_lib::normalize::Transliteration;
+    use cygaz_lib::{AreasByDistrict, PetroleumStation, PetroleumType};
+    use zip::ZipArchive;
+
+    use crate::export::{export, file_stem, zipped, ExportFormat};
+    use crate::lang::Lang;
+    use crate::nationwide::merge;
+    use crate::PriceList;
+
+    #[test]
+    fn exports_every_format_zipped_or_not() {
+        let list = PriceList {
+            stations: vec![PetroleumStation::new("", 1.359)
+                .with_brand("EKO")
+                .with_area("Στρόβολος")
+                .with_coordinates("35.1", "33.3")],
+            total_rows: 1,
+            ..PriceList::new(PetroleumType::Unlead95, 1_760_436_900_000, String::new())
+        };
+        let export_in = |format, lang| {
+            export(merge(&[&list]), &AreasByDistrict::new(), format, lang, &Transliteration::default())
+        };
+        let export = |format| export_in(format, None);
+
+        let csv = String::from_utf8(export(ExportFormat::Csv)).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        let json: serde_json::Value = serde_json::from_slice(&export(ExportFormat::Json)).unwrap();
+        assert_eq!(json["stations"][0]["brand"], "EKO");
+        let geojson: serde_json::Value = serde_json::from_slice(&export(ExportFormat::Geojson)).unwrap();
+        assert_eq!(geojson["features"][0]["geometry"]["coordinates"], serde_json::json!([33.3, 35.1]));
+        assert_eq!(geojson["features"][0]["properties"]["price"], 1.359);
+
+        let latin = |format| serde_json::from_slice::<serde_json::Value>(&export_in(format, Some(Lang::En))).unwrap();
+        assert_eq!(latin(ExportFormat::Json)["stations"][0]["area"], "Strovolos");
+        assert_eq!(latin(ExportFormat::Geojson)["features"][0]["properties"]["area"], "Strovolos");
+        assert_eq!(json["stations"][0]["area"], "Στρόβολος");
+
+        let stem = file_stem(list.updated_at);
+        assert_eq!(stem, "cygaz-prices-20251014T101500Z");
+        let archive = zipped(&format!("{}.csv", stem), csv.as_bytes(), list.updated_at).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut unzipped = String::new();
+        archive
+            .by_name("cygaz-prices-20251014T101500Z.csv")
+            .unwrap()
+            .read_to_string(&mut unzipped)
+            .unwrap();
+        assert_eq!(unzipped, csv);
+    }
+}
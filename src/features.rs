@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Serialize, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureSource {
+    // toggled by the deployment configuration
+    Config,
+    // toggled by the service itself at runtime
+    Automatic,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct Feature {
+    pub enabled: bool,
+    pub source: FeatureSource,
+    pub reason: String,
+}
+
+/// Registry of capabilities that can be switched on or off in a deployment.
+#[derive(Default)]
+pub struct Features {
+    features: RwLock<BTreeMap<&'static str, Feature>>,
+}
+
+impl Features {
+    pub fn set(&self, name: &'static str, enabled: bool, source: FeatureSource, reason: impl Into<String>) {
+        let mut features = self.features.write().unwrap();
+        features.insert(
+            name,
+            Feature {
+                enabled,
+                source,
+                reason: reason.into(),
+            },
+        );
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<&'static str, Feature> {
+        self.features.read().unwrap().clone()
+    }
+}
+
+#[get("/features")]
+pub async fn list_features(features: web::Data<Features>) -> impl Responder {
+    HttpResponse::Ok().json(features.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_and_read_body_json, init_service, TestRequest};
+    use actix_web::{web, App};
+
+    use crate::features::{list_features, FeatureSource, Features};
+
+    #[test]
+    fn the_latest_setting_wins() {
+        let features = Features::default();
+        assert!(features.snapshot().is_empty());
+
+        features.set("webhooks", false, FeatureSource::Config, "WEBHOOK_URLS not set");
+        features.set("upstream_reachable", true, FeatureSource::Automatic, "last refresh succeeded");
+        features.set("upstream_reachable", false, FeatureSource::Automatic, "last refresh failed".to_string());
+
+        let snapshot = features.snapshot();
+        assert_eq!(snapshot.keys().copied().collect::<Vec<_>>(), vec!["upstream_reachable", "webhooks"]);
+        assert!(!snapshot["upstream_reachable"].enabled);
+        assert_eq!(snapshot["upstream_reachable"].reason, "last refresh failed");
+        assert_eq!(snapshot["webhooks"].source, FeatureSource::Config);
+    }
+
+    #[actix_web::test]
+    async fn lists_every_feature() {
+        let features = web::Data::new(Features::default());
+        features.set("webhooks", true, FeatureSource::Config, "WEBHOOK_URLS=http://hooks");
+        let app = init_service(App::new().app_data(features.clone()).service(list_features)).await;
+
+        let req = TestRequest::get().uri("/features").to_request();
+        let json: serde_json::Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "webhooks": { "enabled": true, "source": "config", "reason": "WEBHOOK_URLS=http://hooks" }
+            })
+        );
+    }
+}
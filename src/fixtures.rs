@@ -0,0 +1,63 @@
+use cygaz_lib::{PetroleumStation, PetroleumType};
+
+use crate::nationwide::MergedStation;
+use crate::stations::RegisteredStation;
+use crate::PriceList;
+
+/// An EKO station of `area` listed at `price`, with coordinates and an address of its own, so
+/// merging keeps it apart from the others.
+pub fn station(station_id: &str, area: &str, price: f32) -> PetroleumStation {
+    PetroleumStation::new(station_id, price)
+        .with_brand("EKO")
+        .with_address(format!("Street {}", station_id))
+        .with_area(area)
+        .with_coordinates("35.1", "33.3")
+}
+
+/// A nationwide list of `petroleum_type` refreshed at `updated_at`.
+pub fn list(petroleum_type: PetroleumType, updated_at: u128, stations: Vec<PetroleumStation>) -> PriceList {
+    PriceList {
+        stations,
+        ..PriceList::new(petroleum_type, updated_at, String::new())
+    }
+}
+
+/// A nationwide list of `petroleum_type` with a `station` of every `(station_id, area, price)`.
+pub fn prices(petroleum_type: PetroleumType, prices: &[(&str, &str, f32)]) -> PriceList {
+    let stations = prices
+        .iter()
+        .map(|(station_id, area, price)| station(station_id, area, *price))
+        .collect();
+    list(petroleum_type, 0, stations)
+}
+
+/// An EKO station of `area` merged across the petroleum types it has `prices` for.
+pub fn merged(station_id: &str, area: &str, prices: &[(PetroleumType, f32)]) -> MergedStation {
+    MergedStation {
+        station_id: station_id.to_string(),
+        brand: "EKO".to_string(),
+        area: area.to_string(),
+        prices: prices.iter().copied().collect(),
+        ..Default::default()
+    }
+}
+
+/// `merged` at `latitude` and `longitude` as upstream lists them, blank for none.
+pub fn merged_at(station_id: &str, latitude: &str, longitude: &str, prices: &[(PetroleumType, f32)]) -> MergedStation {
+    MergedStation {
+        latitude: latitude.to_string(),
+        longitude: longitude.to_string(),
+        ..merged(station_id, "Strovolos", prices)
+    }
+}
+
+/// A registered station of `brand` at `address` in `area`.
+pub fn registered(station_id: &str, brand: &str, address: &str, area: &str) -> RegisteredStation {
+    RegisteredStation {
+        station_id: station_id.to_string(),
+        brand: brand.to_string(),
+        address: address.to_string(),
+        area: area.to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::{District, PetroleumType, PriceUnit, CURRENCY};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DAY;
+use crate::trends::{trend, window_days, StoredDays, TrendPoint};
+use crate::SharedState;
+
+const DEFAULT_WINDOW: &str = "30d";
+
+const DEFAULT_DAYS: u32 = 7;
+
+const MAX_DAYS: u32 = 14;
+
+// a line takes two days, its spread a third
+const MIN_HISTORY_DAYS: usize = 3;
+
+// of the bounds, the normal quantile of 95%
+const CONFIDENCE: f64 = 0.95;
+const Z: f64 = 1.96;
+
+#[derive(Deserialize)]
+pub struct ForecastQuery {
+    pub fuel: Option<String>,
+    // nationwide by default
+    pub district: Option<District>,
+    // days ahead, a week by default
+    pub days: Option<u32>,
+    // stored days the trend is fitted on, like `30d`
+    pub window: Option<String>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct ForecastPoint {
+    // the start of the UTC day
+    pub day: u128,
+    pub price: f32,
+    pub lower: f32,
+    pub upper: f32,
+}
+
+#[derive(Serialize)]
+pub struct PriceForecast {
+    pub petroleum_type: PetroleumType,
+    pub district: District,
+    // always true, the prices are extrapolated and not announced by anyone
+    pub estimate: bool,
+    pub method: &'static str,
+    pub window_days: u32,
+    // the stored days the trend was fitted on
+    pub history_days: usize,
+    pub confidence: f64,
+    pub currency: &'static str,
+    pub unit: PriceUnit,
+    // None, as the days, without enough history
+    pub slope_per_day: Option<f32>,
+    pub days: Vec<ForecastPoint>,
+}
+
+/// The least squares line through the daily averages of `history`, extended `days` days past
+/// the last of them with the bounds a price of each falls within at `CONFIDENCE`, along with its
+/// slope per day. None with fewer than `MIN_HISTORY_DAYS` days.
+pub fn linear_trend(history: &[TrendPoint], days: u32) -> Option<(f32, Vec<ForecastPoint>)> {
+    if history.len() < MIN_HISTORY_DAYS {
+        return None;
+    }
+    let last = history.last()?.day;
+    let points = history
+        .iter()
+        .map(|point| ((point.day as f64 - last as f64) / DAY as f64, point.avg as f64))
+        .collect::<Vec<_>>();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+    let sxy = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let residuals = points
+        .iter()
+        .map(|(x, y)| (y - intercept - slope * x).powi(2))
+        .sum::<f64>();
+    let spread = (residuals / (n - 2.0)).sqrt();
+
+    let forecast = (1..=days)
+        .map(|ahead| {
+            let x = ahead as f64;
+            let price = intercept + slope * x;
+            let margin = Z * spread * (1.0 + 1.0 / n + (x - mean_x).powi(2) / sxx).sqrt();
+            ForecastPoint {
+                day: last + ahead as u128 * DAY,
+                price: price as f32,
+                lower: (price - margin).max(0.0) as f32,
+                upper: (price + margin) as f32,
+            }
+        })
+        .collect();
+    Some((slope as f32, forecast))
+}
+
+#[get("/forecast")]
+pub async fn price_forecast(data: web::Data<SharedState>, query: web::Query<ForecastQuery>) -> impl Responder {
+    let checked = query.fuel.as_deref().ok_or("fuel is required".to_string()).and_then(|fuel| {
+        let petroleum_type = PetroleumType::from_name(fuel).ok_or(format!("Unknown fuel {}", fuel))?;
+        let window_days = window_days(query.window.as_deref().unwrap_or(DEFAULT_WINDOW))?;
+        match query.days.unwrap_or(DEFAULT_DAYS) {
+            days @ 1..=MAX_DAYS => Ok((petroleum_type, window_days, days)),
+            _ => Err(format!("days must be 1 to {}", MAX_DAYS)),
+        }
+    });
+    let (petroleum_type, window_days, days) = match checked {
+        Ok(checked) => checked,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let district = query.district.unwrap_or(District::All);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    let since = (now / DAY * DAY).saturating_sub((window_days as u128 - 1) * DAY);
+
+    let stored = match StoredDays::fetch(&data, petroleum_type, district, since, now).await {
+        Ok(stored) => stored,
+        Err(res) => return res,
+    };
+    let history = trend(&stored.daily, stored.stations.as_ref(), since, 1);
+    let forecast = linear_trend(&history, days);
+    HttpResponse::Ok().json(PriceForecast {
+        petroleum_type,
+        district,
+        estimate: true,
+        method: "linear_trend",
+        window_days,
+        history_days: history.len(),
+        confidence: CONFIDENCE,
+        currency: CURRENCY,
+        unit: stored.unit,
+        slope_per_day: forecast.as_ref().map(|(slope, _)| *slope),
+        days: forecast.map(|(_, days)| days).unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::DAY;
+    use crate::forecast::linear_trend;
+    use crate::trends::TrendPoint;
+
+    fn point(day: u128, avg: f32) -> TrendPoint {
+        TrendPoint {
+            day: day * DAY,
+            avg,
+            moving_avg: avg,
+            stations: 1,
+        }
+    }
+
+    #[test]
+    fn extends_the_trend_of_the_stored_days() {
+        assert!(linear_trend(&[point(1, 1.30), point(2, 1.31)], 7).is_none());
+
+        // a cent a day, exactly
+        let rising = [point(1, 1.30), point(2, 1.31), point(3, 1.32), point(5, 1.34)];
+        let (slope, days) = linear_trend(&rising, 2).unwrap();
+        assert!((slope - 0.01).abs() < 1e-6);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].day, 6 * DAY);
+        assert!((days[1].price - 1.36).abs() < 1e-6);
+        assert!((days[1].upper - days[1].lower).abs() < 1e-6);
+
+        // off the line the bounds widen, and further out more so
+        let noisy = [point(1, 1.30), point(2, 1.33), point(3, 1.31), point(4, 1.34)];
+        let (_, days) = linear_trend(&noisy, 3).unwrap();
+        let width = |i: usize| days[i].upper - days[i].lower;
+        assert!(days[0].lower < days[0].price && days[0].price < days[0].upper);
+        assert!(width(0) > 0.0 && width(2) > width(0));
+    }
+}
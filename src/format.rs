@@ -0,0 +1,108 @@
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use serde::{Deserialize, Serialize};
+
+/// How a JSON response is written, compact and unwrapped unless asked otherwise.
+#[derive(Deserialize, Default)]
+pub struct FormatQuery {
+    #[serde(default)]
+    pub pretty: bool,
+    #[serde(default)]
+    pub envelope: bool,
+}
+
+#[derive(Serialize)]
+struct Meta<'a> {
+    status: u16,
+    path: &'a str,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    data: serde_json::Value,
+    meta: Meta<'a>,
+}
+
+/// Rewrites a JSON `body`, None when it is not valid JSON.
+pub fn format_body(body: &[u8], format: &FormatQuery, status: u16, path: &str) -> Option<Vec<u8>> {
+    let data = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    let value = match format.envelope {
+        true => serde_json::to_value(Envelope {
+            data,
+            meta: Meta { status, path },
+        })
+        .ok()?,
+        false => data,
+    };
+    match format.pretty {
+        true => serde_json::to_vec_pretty(&value).ok(),
+        false => serde_json::to_vec(&value).ok(),
+    }
+}
+
+fn is_json(res: &ServiceResponse<impl MessageBody>) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"))
+}
+
+pub async fn format_json(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let format = web::Query::<FormatQuery>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default();
+    if !format.pretty && !format.envelope {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    let path = req.path().to_string();
+    let res = next.call(req).await?;
+    if !is_json(&res) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (http_req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = to_bytes(body)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("failed to read response body"))?;
+    let body = format_body(&body, &format, res.status().as_u16(), &path).unwrap_or_else(|| body.to_vec());
+
+    Ok(ServiceResponse::new(http_req, res.set_body(body).map_into_boxed_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{format_body, FormatQuery};
+
+    #[test]
+    fn pretty_prints_and_wraps_in_envelope() {
+        let body = br#"{"district":"All"}"#;
+
+        let pretty = FormatQuery {
+            pretty: true,
+            envelope: false,
+        };
+        assert_eq!(
+            format_body(body, &pretty, 200, "/prices/1").unwrap(),
+            b"{\n  \"district\": \"All\"\n}"
+        );
+
+        let envelope = FormatQuery {
+            pretty: false,
+            envelope: true,
+        };
+        assert_eq!(
+            String::from_utf8(format_body(body, &envelope, 200, "/prices/1").unwrap()).unwrap(),
+            r#"{"data":{"district":"All"},"meta":{"status":200,"path":"/prices/1"}}"#
+        );
+
+        assert!(format_body(b"0.1.3 not json", &envelope, 200, "/version").is_none());
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::{AreasByDistrict, District, PetroleumType, StationStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::lang;
+use crate::nationwide::{self, FuelQuery, MergedStation};
+use crate::stations::district_of;
+use crate::status::StationFilter;
+use crate::SharedState;
+
+#[derive(Serialize)]
+pub struct Point {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    // longitude first, as GeoJSON has it
+    pub coordinates: [f64; 2],
+}
+
+#[derive(Serialize)]
+pub struct StationProperties {
+    pub station_id: String,
+    pub brand: String,
+    pub company: String,
+    pub address: String,
+    pub area: String,
+    pub district: Option<District>,
+    pub offline: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<StationStatus>,
+    // only when a single fuel is selected, for styling the layer by it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f32>,
+    pub prices: BTreeMap<PetroleumType, f32>,
+}
+
+#[derive(Serialize)]
+pub struct Feature {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub geometry: Point,
+    pub properties: StationProperties,
+}
+
+#[derive(Serialize)]
+pub struct FeatureCollection {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub features: Vec<Feature>,
+}
+
+/// Where to look for stations, a GeoJSON polygon or multi-polygon, bare or as a feature.
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum Area {
+    // the outer ring, then holes, of positions with the longitude first
+    Polygon { coordinates: Vec<Vec<Vec<f64>>> },
+    MultiPolygon { coordinates: Vec<Vec<Vec<Vec<f64>>>> },
+    Feature { geometry: Box<Area> },
+}
+
+type Ring = Vec<(f64, f64)>;
+
+/// Rings of every polygon of the area, as `(longitude, latitude)`.
+fn polygons(area: Area) -> Result<Vec<Vec<Ring>>, String> {
+    let polygons = match area {
+        Area::Polygon { coordinates } => vec![coordinates],
+        Area::MultiPolygon { coordinates } => coordinates,
+        Area::Feature { geometry } => return polygons(*geometry),
+    };
+    if polygons.is_empty() {
+        return Err("No polygon given".to_string());
+    }
+    polygons
+        .into_iter()
+        .map(|rings| {
+            if rings.is_empty() {
+                return Err("Polygon without rings".to_string());
+            }
+            rings
+                .into_iter()
+                .map(|ring| {
+                    let ring = ring
+                        .into_iter()
+                        .map(|position| match position[..] {
+                            [longitude, latitude, ..] => Ok((longitude, latitude)),
+                            _ => Err("Position without longitude and latitude".to_string()),
+                        })
+                        .collect::<Result<Ring, _>>()?;
+                    match ring.len() {
+                        0..=3 => Err("Ring with fewer than 4 positions".to_string()),
+                        _ => Ok(ring),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// even-odd rule, a point on an edge may fall either way
+fn in_ring(ring: &Ring, (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    for (i, &(xi, yi)) in ring.iter().enumerate() {
+        let (xj, yj) = ring[(i + ring.len() - 1) % ring.len()];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Whether `point` is in one of `polygons`, inside its outer ring and outside its holes.
+pub fn contains(polygons: &[Vec<Ring>], point: (f64, f64)) -> bool {
+    polygons.iter().any(|rings| match rings.split_first() {
+        Some((outer, holes)) => in_ring(outer, point) && !holes.iter().any(|hole| in_ring(hole, point)),
+        None => false,
+    })
+}
+
+#[derive(Serialize)]
+pub struct StationsWithin {
+    // cheapest first when a single fuel is selected
+    pub stations: Vec<MergedStation>,
+}
+
+/// Stations of `stations` inside `polygons`, cheapest first by the `price_of` petroleum type if given.
+pub fn within(
+    stations: Vec<MergedStation>,
+    polygons: &[Vec<Ring>],
+    price_of: Option<PetroleumType>,
+) -> Vec<MergedStation> {
+    let mut inside = stations
+        .into_iter()
+        .filter(|station| {
+            let latitude = station.latitude.trim().parse::<f64>();
+            let longitude = station.longitude.trim().parse::<f64>();
+            match (longitude, latitude) {
+                (Ok(longitude), Ok(latitude)) => contains(polygons, (longitude, latitude)),
+                _ => false,
+            }
+        })
+        .collect::<Vec<_>>();
+    if let Some(petroleum_type) = price_of {
+        let price = |station: &MergedStation| station.prices.get(&petroleum_type).copied().unwrap_or(f32::MAX);
+        inside.sort_by(|a, b| price(a).total_cmp(&price(b)));
+    }
+    inside
+}
+
+/// A point feature for every station of `stations` with usable coordinates, with its `price_of` a
+/// petroleum type if given.
+pub fn feature_collection(
+    stations: Vec<MergedStation>,
+    areas: &AreasByDistrict,
+    price_of: Option<PetroleumType>,
+) -> FeatureCollection {
+    let features = stations
+        .into_iter()
+        .filter_map(|station| {
+            let latitude = station.latitude.trim().parse::<f64>().ok()?;
+            let longitude = station.longitude.trim().parse::<f64>().ok()?;
+            let price = price_of.and_then(|petroleum_type| station.prices.get(&petroleum_type).copied());
+            Some(Feature {
+                kind: "Feature",
+                geometry: Point {
+                    kind: "Point",
+                    coordinates: [longitude, latitude],
+                },
+                properties: StationProperties {
+                    district: district_of(areas, &station.area),
+                    station_id: station.station_id,
+                    brand: station.brand,
+                    company: station.company,
+                    address: station.address,
+                    area: station.area,
+                    offline: station.offline,
+                    status: station.status,
+                    price,
+                    prices: station.prices,
+                },
+            })
+        })
+        .collect();
+    FeatureCollection {
+        kind: "FeatureCollection",
+        features,
+    }
+}
+
+// the petroleum type to price stations by, when only one is selected
+fn single(selected: &[PetroleumType]) -> Option<PetroleumType> {
+    match selected {
+        [petroleum_type] => Some(*petroleum_type),
+        _ => None,
+    }
+}
+
+#[get("/stations.geojson")]
+pub async fn stations_geojson(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    fuel: web::Query<FuelQuery>,
+    filter: web::Query<StationFilter>,
+) -> impl Responder {
+    let selected = match fuel.petroleum_types() {
+        Ok(selected) => selected,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let price_of = single(&selected);
+
+    let state = data.read();
+    let lists = selected
+        .into_iter()
+        .filter_map(|petroleum_type| state.price_list(petroleum_type))
+        .map(|list| filter.apply(list))
+        .collect::<Vec<_>>();
+    let merged = nationwide::merge(&lists.iter().collect::<Vec<_>>());
+
+    // after the districts are looked up by the areas as upstream names them
+    let collection = lang::localized(&req, feature_collection(merged.stations, &state.areas, price_of));
+    HttpResponse::Ok().content_type("application/geo+json").json(collection)
+}
+
+#[post("/stations/within")]
+pub async fn stations_within(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    fuel: web::Query<FuelQuery>,
+    filter: web::Query<StationFilter>,
+    area: web::Json<Area>,
+) -> impl Responder {
+    let selected = match fuel.petroleum_types() {
+        Ok(selected) => selected,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let price_of = single(&selected);
+    let polygons = match polygons(area.into_inner()) {
+        Ok(polygons) => polygons,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+
+    let state = data.read();
+    let lists = selected
+        .into_iter()
+        .filter_map(|petroleum_type| state.price_list(petroleum_type))
+        .map(|list| filter.apply(list))
+        .collect::<Vec<_>>();
+    let merged = nationwide::merge(&lists.iter().collect::<Vec<_>>());
+
+    let stations = lang::localized(&req, within(merged.stations, &polygons, price_of));
+    HttpResponse::Ok().json(StationsWithin { stations })
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType};
+
+    use crate::geojson::{feature_collection, polygons, within, Area};
+    use crate::fixtures::merged_at;
+
+    // a square with a square hole, both closed
+    fn area() -> Area {
+        serde_json::from_value(serde_json::json!({
+            "type": "Feature",
+            "properties": {},
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [
+                    [[33.0, 35.0], [34.0, 35.0], [34.0, 36.0], [33.0, 36.0], [33.0, 35.0]],
+                    [[33.2, 35.4], [33.4, 35.4], [33.4, 35.6], [33.2, 35.6], [33.2, 35.4]],
+                ],
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn stations_within_polygon_outside_holes() {
+        let polygons = polygons(area()).unwrap();
+        let inside = within(
+            vec![
+                merged_at("a", "35.1", "33.3", &[(PetroleumType::Unlead95, 1.45)]),
+                merged_at("hole", "35.5", "33.3", &[(PetroleumType::Unlead95, 1.30)]),
+                merged_at("north", "36.5", "33.3", &[(PetroleumType::Unlead95, 1.20)]),
+                merged_at("b", "35.8", "33.3", &[(PetroleumType::Unlead95, 1.40)]),
+            ],
+            &polygons,
+            Some(PetroleumType::Unlead95),
+        );
+        assert_eq!(inside.iter().map(|s| s.station_id.as_str()).collect::<Vec<_>>(), vec!["b", "a"]);
+
+        let open_ring = serde_json::json!({ "type": "Polygon", "coordinates": [[[33.0, 35.0], [34.0, 35.0]]] });
+        assert!(polygons_of(open_ring).is_err());
+        assert!(serde_json::from_value::<Area>(serde_json::json!({ "type": "Point", "coordinates": [33.0, 35.0] })).is_err());
+    }
+
+    fn polygons_of(value: serde_json::Value) -> Result<(), String> {
+        polygons(serde_json::from_value(value).unwrap()).map(|_| ())
+    }
+
+    #[test]
+    fn features_with_longitude_first() {
+        let areas = AreasByDistrict::from([(District::Nicosia, vec!["Strovolos".to_string()])]);
+        let collection = feature_collection(
+            vec![
+                merged_at("a", "35.1", "33.3", &[(PetroleumType::Unlead95, 1.40)]),
+                merged_at("b", "", "33.3", &[(PetroleumType::Unlead95, 1.45)]),
+                merged_at("c", "35.2", "33.3", &[(PetroleumType::Unlead95, 1.41), (PetroleumType::DieselAuto, 1.50)]),
+            ],
+            &areas,
+            None,
+        );
+        assert_eq!(collection.features.len(), 2);
+
+        let json = serde_json::to_value(&collection).unwrap();
+        assert_eq!(json["type"], "FeatureCollection");
+        assert_eq!(json["features"][0]["geometry"]["coordinates"], serde_json::json!([33.3, 35.1]));
+        assert_eq!(json["features"][0]["properties"]["district"], "Nicosia");
+        assert!(json["features"][0]["properties"].get("price").is_none());
+        assert_eq!(json["features"][1]["properties"]["prices"].as_object().unwrap().len(), 2);
+
+        let priced = feature_collection(
+            vec![merged_at("c", "35.2", "33.3", &[(PetroleumType::Unlead95, 1.41), (PetroleumType::DieselAuto, 1.50)])],
+            &areas,
+            Some(PetroleumType::DieselAuto),
+        );
+        assert_eq!(priced.features[0].properties.price, Some(1.50));
+    }
+}
@@ -0,0 +1,221 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+
+use cygaz_lib::{District, PetroleumStation, PetroleumType, StationStatus};
+use futures_util::{stream, Stream};
+use log::{info, warn};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tonic::{Request, Response, Status};
+
+use crate::status::StationFilter;
+use crate::sync::Delta;
+use crate::updates::PriceUpdate;
+use crate::{PriceList, SharedState};
+
+pub mod proto {
+    tonic::include_proto!("cygaz.v1");
+}
+
+use proto::cygaz_server::{Cygaz, CygazServer};
+
+fn district(district: District) -> proto::District {
+    match district {
+        District::Nicosia => proto::District::Nicosia,
+        District::Limassol => proto::District::Limassol,
+        District::Larnaca => proto::District::Larnaca,
+        District::Paphos => proto::District::Paphos,
+        District::Famagusta => proto::District::Famagusta,
+        _ => proto::District::All,
+    }
+}
+
+fn station_status(status: Option<StationStatus>) -> proto::StationStatus {
+    match status {
+        Some(StationStatus::Open) => proto::StationStatus::Open,
+        Some(StationStatus::TemporarilyOffline) => proto::StationStatus::TemporarilyOffline,
+        Some(StationStatus::Closed) => proto::StationStatus::Closed,
+        _ => proto::StationStatus::Unspecified,
+    }
+}
+
+impl From<&PetroleumStation> for proto::Station {
+    fn from(station: &PetroleumStation) -> Self {
+        proto::Station {
+            station_id: station.station_id.clone(),
+            brand: station.brand.clone(),
+            offline: station.offline,
+            company: station.company.clone(),
+            address: station.address.clone(),
+            latitude: station.latitude.clone(),
+            longitude: station.longitude.clone(),
+            area: station.area.clone(),
+            price: station.price,
+            status: station_status(station.status) as i32,
+            status_since: station.status_since.unwrap_or_default() as u64,
+            carried_forward: station.carried_forward,
+            outlier: station.outlier,
+        }
+    }
+}
+
+impl From<&PriceList> for proto::PriceList {
+    fn from(list: &PriceList) -> Self {
+        proto::PriceList {
+            // numbered alike
+            petroleum_type: list.petroleum_type as i32,
+            district: district(list.district) as i32,
+            updated_at: list.updated_at as u64,
+            currency: list.currency.to_string(),
+            unit: serde_json::to_value(list.unit)
+                .ok()
+                .and_then(|unit| unit.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            stations: list.stations.iter().map(proto::Station::from).collect(),
+        }
+    }
+}
+
+impl From<&Delta> for proto::PriceChange {
+    fn from(delta: &Delta) -> Self {
+        proto::PriceChange {
+            petroleum_type: delta.petroleum_type as i32,
+            version: delta.version as u64,
+            stations: delta.stations.iter().map(proto::Station::from).collect(),
+            removed: delta.removed.clone(),
+        }
+    }
+}
+
+impl From<&PriceUpdate> for proto::PriceUpdate {
+    fn from(update: &PriceUpdate) -> Self {
+        proto::PriceUpdate {
+            updated_at: update.updated_at as u64,
+            changes: update.changes.iter().map(proto::PriceChange::from).collect(),
+        }
+    }
+}
+
+struct CygazService {
+    data: SharedState,
+    // ends the update streams, which would otherwise hold shutdown up
+    stopping: watch::Receiver<bool>,
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<proto::PriceUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Cygaz for CygazService {
+    async fn get_prices(
+        &self,
+        request: Request<proto::GetPricesRequest>,
+    ) -> Result<Response<proto::PriceList>, Status> {
+        let request = request.into_inner();
+        let petroleum_type = PetroleumType::from_id(request.petroleum_type)
+            .ok_or_else(|| Status::invalid_argument(format!("Unknown petroleum type {}", request.petroleum_type)))?;
+        let filter = StationFilter {
+            include_closed: request.include_closed,
+            ..Default::default()
+        };
+
+        let state = self.data.read();
+        let list = state
+            .price_list(petroleum_type)
+            .ok_or_else(|| Status::not_found(format!("No prices of {:?}", petroleum_type)))?;
+        Ok(Response::new(proto::PriceList::from(&filter.apply(list))))
+    }
+
+    async fn get_districts(
+        &self,
+        _: Request<proto::GetDistrictsRequest>,
+    ) -> Result<Response<proto::Districts>, Status> {
+        let state = self.data.read();
+        let districts = state
+            .areas
+            .iter()
+            .map(|(name, areas)| proto::DistrictAreas {
+                district: district(*name) as i32,
+                areas: areas.clone(),
+            })
+            .collect();
+        Ok(Response::new(proto::Districts { districts }))
+    }
+
+    type StreamUpdatesStream = UpdateStream;
+
+    async fn stream_updates(
+        &self,
+        _: Request<proto::StreamUpdatesRequest>,
+    ) -> Result<Response<Self::StreamUpdatesStream>, Status> {
+        let updates = self.data.read().updates.subscribe_messages();
+        let listener = (updates, self.stopping.clone());
+        let stream = stream::unfold(listener, |(mut updates, mut stopping)| async move {
+            let update = tokio::select! {
+                update = updates.recv() => update,
+                _ = stopping.wait_for(|stopping| *stopping) => return None,
+            };
+            let update = match update {
+                Ok(update) => Ok(proto::PriceUpdate::clone(&update)),
+                // ends the stream, the client has to fetch the prices again to catch up
+                Err(RecvError::Lagged(missed)) => Err(Status::data_loss(format!("Missed {} updates", missed))),
+                Err(RecvError::Closed) => return None,
+            };
+            Some((update, (updates, stopping)))
+        });
+        Ok(Response::new(Box::pin(stream) as UpdateStream))
+    }
+}
+
+async fn serve(address: SocketAddr, data: SharedState, mut stopping: watch::Receiver<bool>) {
+    let service = CygazService {
+        data,
+        stopping: stopping.clone(),
+    };
+    let served = tonic::transport::Server::builder()
+        .add_service(CygazServer::new(service))
+        .serve_with_shutdown(address, async move {
+            let _ = stopping.wait_for(|stopping| *stopping).await;
+        })
+        .await;
+    if let Err(err) = served {
+        warn!("grpc server failed {}", err);
+    }
+}
+
+/// Serves the gRPC API on `port` of `host` until `stopping` turns true.
+pub fn spawn(host: &str, port: u16, data: SharedState, stopping: watch::Receiver<bool>) -> JoinHandle<()> {
+    let address = (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .unwrap_or_else(|| panic!("invalid GRPC_PORT: cannot listen on {}:{}", host, port));
+    info!("starting grpc server @ {}", address);
+    tokio::spawn(serve(address, data, stopping))
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{PetroleumStation, PetroleumType, PriceUnit, StationStatus};
+
+    use crate::grpc::proto;
+    use crate::PriceList;
+
+    #[test]
+    fn converts_price_lists() {
+        let list = PriceList {
+            stations: vec![PetroleumStation::new("5f1d3c0e8a9b2d47", 1.089)
+                .with_status(StationStatus::TemporarilyOffline, 1647710000000)],
+            unit: PriceUnit::ThousandLitres,
+            ..PriceList::new(PetroleumType::Kerosene, 1647710214169, String::new())
+        };
+
+        let converted = proto::PriceList::from(&list);
+        assert_eq!(converted.petroleum_type(), proto::PetroleumType::Kerosene);
+        assert_eq!(converted.district(), proto::District::All);
+        assert_eq!(converted.updated_at, 1647710214169);
+        assert_eq!(converted.unit, "1000_litres");
+        assert_eq!(converted.stations[0].status(), proto::StationStatus::TemporarilyOffline);
+        assert_eq!(converted.stations[0].status_since, 1647710000000);
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::PetroleumType;
+use serde::Serialize;
+
+use crate::{PriceList, SharedState};
+
+#[derive(Clone, Copy, Serialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Readiness {
+    Ready,
+    // some petroleum types are stale or never loaded, the rest is usable
+    Degraded,
+    Unready,
+}
+
+#[derive(Serialize)]
+pub struct FuelFreshness {
+    pub loaded_at: Option<u128>,
+    pub stale: bool,
+    // why the latest fetch from upstream failed and since when it keeps failing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failing_since: Option<u128>,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessReport {
+    pub status: Readiness,
+    pub max_age: u64,
+    pub petroleum_types: BTreeMap<PetroleumType, FuelFreshness>,
+}
+
+/// When upstream last confirmed every price list, a refresh that failed and left a list empty
+/// does not count.
+#[derive(Clone)]
+pub struct Freshness {
+    // milliseconds, `0` never considers a loaded list stale
+    max_age: u128,
+    loaded_at: BTreeMap<PetroleumType, u128>,
+}
+
+impl Freshness {
+    pub fn new(max_age: u128) -> Self {
+        Freshness {
+            max_age,
+            loaded_at: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, list: &PriceList) {
+        if !list.stations.is_empty() {
+            self.loaded_at.insert(list.petroleum_type, list.updated_at);
+        }
+    }
+
+    pub fn report(&self, petroleum_types: &[PetroleumType], now: u128) -> ReadinessReport {
+        let petroleum_types = petroleum_types
+            .iter()
+            .map(|petroleum_type| {
+                let loaded_at = self.loaded_at.get(petroleum_type).copied();
+                let stale = loaded_at
+                    .is_none_or(|loaded_at| self.max_age > 0 && now.saturating_sub(loaded_at) > self.max_age);
+                let freshness = FuelFreshness {
+                    loaded_at,
+                    stale,
+                    error: None,
+                    failing_since: None,
+                };
+                (*petroleum_type, freshness)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let stale = petroleum_types.values().filter(|fuel| fuel.stale).count();
+        let status = match stale {
+            0 => Readiness::Ready,
+            stale if stale < petroleum_types.len() => Readiness::Degraded,
+            _ => Readiness::Unready,
+        };
+        ReadinessReport {
+            status,
+            max_age: (self.max_age / 1000) as u64,
+            petroleum_types,
+        }
+    }
+}
+
+/// Liveness, answers as long as the process serves requests.
+#[get("/healthz")]
+pub async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+#[get("/ready")]
+pub async fn ready(data: web::Data<SharedState>) -> impl Responder {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let state = data.read();
+    let tracked = PetroleumType::ALL
+        .into_iter()
+        .filter(|petroleum_type| state.price_list(*petroleum_type).is_some())
+        .collect::<Vec<_>>();
+    let mut report = state.freshness.report(&tracked, now);
+    for (petroleum_type, fuel) in report.petroleum_types.iter_mut() {
+        if let Some(summary) = state.summaries.latest().get(petroleum_type) {
+            fuel.error = summary.scrape.error.clone();
+            fuel.failing_since = summary.failing_since;
+        }
+    }
+    match report.status {
+        Readiness::Unready => HttpResponse::ServiceUnavailable().json(report),
+        _ => HttpResponse::Ok().json(report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{PetroleumStation, PetroleumType};
+
+    use crate::fixtures::list;
+    use crate::health::{Freshness, Readiness};
+
+    #[test]
+    fn unready_until_loaded_and_degraded_when_partly_stale() {
+        let fuels = [PetroleumType::Unlead95, PetroleumType::DieselAuto];
+        let mut freshness = Freshness::new(1000);
+        assert_eq!(freshness.report(&fuels, 0).status, Readiness::Unready);
+
+        // a failed refresh leaves the list empty
+        freshness.record(&list(PetroleumType::Unlead95, 100, vec![]));
+        assert_eq!(freshness.report(&fuels, 100).status, Readiness::Unready);
+
+        freshness.record(&list(PetroleumType::Unlead95, 100, vec![PetroleumStation::default(); 3]));
+        freshness.record(&list(PetroleumType::DieselAuto, 500, vec![PetroleumStation::default(); 3]));
+        assert_eq!(freshness.report(&fuels, 600).status, Readiness::Ready);
+        let report = freshness.report(&fuels, 1200);
+        assert_eq!(report.status, Readiness::Degraded);
+        assert!(report.petroleum_types[&PetroleumType::Unlead95].stale);
+        assert_eq!(freshness.report(&fuels, 1600).status, Readiness::Unready);
+
+        assert_eq!(Freshness { max_age: 0, ..freshness }.report(&fuels, 1600).status, Readiness::Ready);
+    }
+}
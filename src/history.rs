@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::nationwide::PriceStats;
+use crate::pagination::{seek, CursorError, Page, PageQuery};
+
+#[derive(Clone, Serialize)]
+pub struct RefreshRecord {
+    pub updated_at: u128,
+    pub updated_at_str: String,
+    pub stats: Vec<PriceStats>,
+}
+
+/// The most recent refreshes, oldest first, capped at `capacity` entries.
+#[derive(Clone)]
+pub struct RefreshHistory {
+    capacity: usize,
+    records: VecDeque<RefreshRecord>,
+}
+
+impl RefreshHistory {
+    pub fn new(capacity: usize) -> Self {
+        RefreshHistory {
+            capacity,
+            records: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, record: RefreshRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.records.len() >= self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &RefreshRecord> {
+        self.records.iter()
+    }
+
+    pub fn page(&self, query: &PageQuery) -> Result<Page<RefreshRecord>, CursorError> {
+        seek(&self.records, |record| record.updated_at, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{RefreshHistory, RefreshRecord};
+    use crate::pagination::{encode_cursor, PageQuery};
+
+    fn record(updated_at: u128) -> RefreshRecord {
+        RefreshRecord {
+            updated_at,
+            updated_at_str: String::new(),
+            stats: vec![],
+        }
+    }
+
+    fn updated_at(history: &RefreshHistory) -> Vec<u128> {
+        history.records().map(|record| record.updated_at).collect()
+    }
+
+    #[test]
+    fn keeps_the_most_recent_refreshes() {
+        let mut history = RefreshHistory::new(2);
+        for at in [1, 2, 3] {
+            history.push(record(at));
+        }
+        assert_eq!(updated_at(&history), vec![2, 3]);
+
+        let mut disabled = RefreshHistory::new(0);
+        disabled.push(record(1));
+        assert!(updated_at(&disabled).is_empty());
+    }
+
+    #[test]
+    fn pages_newest_first() {
+        let mut history = RefreshHistory::new(10);
+        for at in [10, 20, 30] {
+            history.push(record(at));
+        }
+        let query = |cursor: Option<String>| PageQuery { cursor, limit: Some(2) };
+
+        let first = history.page(&query(None)).unwrap();
+        assert_eq!(first.items.iter().map(|record| record.updated_at).collect::<Vec<_>>(), vec![30, 20]);
+        let second = history.page(&query(first.next_cursor.clone())).unwrap();
+        assert_eq!(second.items.iter().map(|record| record.updated_at).collect::<Vec<_>>(), vec![10]);
+        assert!(second.next_cursor.is_none());
+
+        assert_eq!(first.next_cursor, Some(encode_cursor(20)));
+        assert!(history.page(&query(Some("nonsense".to_string()))).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::web::{self, Bytes};
+use actix_web::{Error, HttpResponse};
+use futures_util::{stream, Stream};
+
+static IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+static REPLAYED_HEADER: &str = "idempotent-replayed";
+
+// requests are told apart per client, the same key from two API keys or admin tokens does
+// not collide, nor with one sent without credentials
+static SCOPE_HEADERS: [&str; 2] = ["x-api-key", "authorization"];
+
+#[derive(Clone)]
+struct StoredResponse {
+    request_hash: u64,
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+enum Entry {
+    // the first request with the key is still running
+    Pending {
+        request_hash: u64,
+        started_at: Instant,
+    },
+    Done(StoredResponse),
+}
+
+impl Entry {
+    fn request_hash(&self) -> u64 {
+        match self {
+            Entry::Pending { request_hash, .. } => *request_hash,
+            Entry::Done(stored) => stored.request_hash,
+        }
+    }
+
+    fn since(&self) -> Instant {
+        match self {
+            Entry::Pending { started_at, .. } => *started_at,
+            Entry::Done(stored) => stored.stored_at,
+        }
+    }
+}
+
+pub enum Lookup {
+    // claimed for this request until it stores its response or releases the key
+    New,
+    Replay(HttpResponse),
+    // the key was used before for a different request
+    Mismatch,
+    // the first request with the key has not answered yet
+    InFlight,
+}
+
+/// Responses of POST requests sent with an `Idempotency-Key`, kept for `ttl` so a retried
+/// request gets the original response instead of being executed twice.
+pub struct IdempotencyStore {
+    ttl: Duration,
+    responses: Mutex<HashMap<String, Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        IdempotencyStore {
+            ttl,
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How to answer a request with `key`, claiming the key when it is new so a concurrent
+    /// request with it waits for the first to answer.
+    fn lookup(&self, key: &str, request_hash: u64, now: Instant) -> Lookup {
+        let mut responses = self.responses.lock().unwrap();
+        let live = responses.get(key).filter(|entry| now.duration_since(entry.since()) < self.ttl);
+        match live {
+            Some(entry) if entry.request_hash() != request_hash => Lookup::Mismatch,
+            Some(Entry::Pending { .. }) => Lookup::InFlight,
+            Some(Entry::Done(stored)) => {
+                let mut response = HttpResponse::build(stored.status);
+                if let Some(content_type) = &stored.content_type {
+                    response.insert_header((CONTENT_TYPE, content_type.clone()));
+                }
+                response.insert_header((
+                    HeaderName::from_static(REPLAYED_HEADER),
+                    HeaderValue::from_static("true"),
+                ));
+                Lookup::Replay(response.body(stored.body.clone()))
+            }
+            None => {
+                let pending = Entry::Pending {
+                    request_hash,
+                    started_at: now,
+                };
+                responses.insert(key.to_string(), pending);
+                Lookup::New
+            }
+        }
+    }
+
+    fn store(&self, key: String, stored: StoredResponse) {
+        let mut responses = self.responses.lock().unwrap();
+        responses.retain(|_, old| stored.stored_at.duration_since(old.since()) < self.ttl);
+        responses.insert(key, Entry::Done(stored));
+    }
+
+    // frees a key claimed by a request that stored no response
+    fn release(&self, key: &str) {
+        let mut responses = self.responses.lock().unwrap();
+        if let Some(Entry::Pending { .. }) = responses.get(key) {
+            responses.remove(key);
+        }
+    }
+}
+
+/// Releases the claimed key unless a response was stored for it, also when the request is
+/// dropped before answering.
+struct Claim {
+    store: web::Data<IdempotencyStore>,
+    key: Option<String>,
+}
+
+impl Claim {
+    fn store(mut self, stored: StoredResponse) {
+        if let Some(key) = self.key.take() {
+            self.store.store(key, stored);
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            self.store.release(key);
+        }
+    }
+}
+
+fn scoped_key(headers: &HeaderMap, path: &str) -> Option<String> {
+    let key = headers.get(IDEMPOTENCY_KEY_HEADER)?.to_str().ok()?;
+    let scope = SCOPE_HEADERS
+        .iter()
+        .map(|name| headers.get(*name).and_then(|value| value.to_str().ok()).unwrap_or_default())
+        .collect::<Vec<_>>();
+    Some(format!("{}\n{}\n{}", scope.join("\n"), path, key))
+}
+
+fn request_hash(body: &Bytes) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub async fn idempotency(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let store = req.app_data::<web::Data<IdempotencyStore>>().cloned();
+    let key = match (&store, req.method()) {
+        (Some(_), &Method::POST) => scoped_key(req.headers(), req.path()),
+        _ => None,
+    };
+    let (Some(store), Some(key)) = (store, key) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let body = req.extract::<Bytes>().await?;
+    let hash = request_hash(&body);
+
+    match store.lookup(&key, hash, Instant::now()) {
+        Lookup::Replay(response) => return Ok(req.into_response(response)),
+        Lookup::Mismatch => {
+            return Ok(req.into_response(HttpResponse::UnprocessableEntity().json(
+                serde_json::json!({ "error": "Idempotency-Key was used for a different request" }),
+            )))
+        }
+        Lookup::InFlight => {
+            return Ok(req.into_response(HttpResponse::Conflict().json(
+                serde_json::json!({ "error": "A request with this Idempotency-Key is still running" }),
+            )))
+        }
+        Lookup::New => {}
+    }
+    let claim = Claim {
+        store,
+        key: Some(key),
+    };
+
+    // the handler reads the body again
+    let replay: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+        Box::pin(stream::once(async move { Ok(body) }));
+    req.set_payload(Payload::from(replay));
+    let res = next.call(req).await?;
+
+    // server errors are worth retrying for real, and a request turned away for its credentials
+    // has not run, it runs once retried with the right ones
+    let status = res.status();
+    if status.is_server_error() || status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (http_req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = to_bytes(body)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("failed to read response body"))?;
+
+    claim.store(StoredResponse {
+        request_hash: hash,
+        status: res.status(),
+        content_type: res.headers().get(CONTENT_TYPE).cloned(),
+        body: body.clone(),
+        stored_at: Instant::now(),
+    });
+
+    Ok(ServiceResponse::new(http_req, res.set_body(body).map_into_boxed_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web::{self, Bytes};
+    use actix_web::{App, HttpRequest, HttpResponse};
+
+    use crate::idempotency::{idempotency, Claim, IdempotencyStore, Lookup, StoredResponse};
+
+    #[test]
+    fn replays_within_ttl_and_rejects_other_requests() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(matches!(store.lookup("key", 1, now), Lookup::New));
+        // claimed until the first request answers
+        assert!(matches!(store.lookup("key", 1, now), Lookup::InFlight));
+        assert!(matches!(store.lookup("key", 2, now), Lookup::Mismatch));
+        store.release("key");
+
+        // a request dropped without answering frees its key
+        let store = web::Data::new(store);
+        assert!(matches!(store.lookup("key", 1, now), Lookup::New));
+        drop(Claim {
+            store: store.clone(),
+            key: Some("key".to_string()),
+        });
+        assert!(matches!(store.lookup("key", 1, now), Lookup::New));
+
+        store.store(
+            "key".to_string(),
+            StoredResponse {
+                request_hash: 1,
+                status: StatusCode::CREATED,
+                content_type: None,
+                body: Bytes::from_static(b"{}"),
+                stored_at: now,
+            },
+        );
+
+        match store.lookup("key", 1, now) {
+            Lookup::Replay(response) => assert_eq!(response.status(), StatusCode::CREATED),
+            _ => panic!("expected a replay"),
+        }
+        assert!(matches!(store.lookup("key", 2, now), Lookup::Mismatch));
+        assert!(matches!(
+            store.lookup("key", 1, now + Duration::from_secs(61)),
+            Lookup::New
+        ));
+    }
+
+    #[actix_web::test]
+    async fn replays_only_to_the_same_credentials() {
+        let store = web::Data::new(IdempotencyStore::new(Duration::from_secs(60)));
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(idempotency))
+                .app_data(store)
+                .route(
+                    "/admin/webhooks",
+                    web::post().to(|req: HttpRequest| async move {
+                        match req.headers().get("authorization").and_then(|value| value.to_str().ok()) {
+                            Some("Bearer secret") => HttpResponse::Created().body("created"),
+                            _ => HttpResponse::Unauthorized().finish(),
+                        }
+                    }),
+                ),
+        )
+        .await;
+        let post = |authorization: Option<&str>| {
+            let req = TestRequest::post().uri("/admin/webhooks").insert_header(("idempotency-key", "k"));
+            match authorization {
+                Some(authorization) => req.insert_header(("authorization", authorization)),
+                None => req,
+            }
+            .to_request()
+        };
+
+        // turned away without storing, the admin's own request with the key still runs
+        let res = call_service(&app, post(None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = call_service(&app, post(Some("Bearer secret"))).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert!(res.headers().get("idempotent-replayed").is_none());
+
+        let res = call_service(&app, post(Some("Bearer secret"))).await;
+        assert_eq!(res.headers().get("idempotent-replayed").unwrap(), "true");
+        assert_eq!(read_body(res).await, "created");
+
+        // nobody else gets the admin's response replayed
+        let res = call_service(&app, post(None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = call_service(&app, post(Some("Bearer guess"))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, HttpResponse, Responder};
+use cygaz_lib::{District, PetroleumType};
+use serde::Serialize;
+
+use crate::request_id;
+
+/// The price refreshes running, one at a time per fuel, whoever started them.
+pub static JOBS: RefreshJobs = RefreshJobs::new();
+
+#[derive(Clone, Copy, Serialize, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FuelProgress {
+    Fetching,
+    Fetched,
+    NotModified,
+    // of a district the previous stations are kept, nationwide the list is left empty
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RefreshJob {
+    #[serde(skip)]
+    seq: u64,
+    // the request or scheduled job that started it
+    pub id: Option<String>,
+    pub district: District,
+    pub started_at: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u128>,
+    pub petroleum_types: BTreeMap<PetroleumType, FuelProgress>,
+}
+
+#[derive(Serialize)]
+pub struct JobsReport {
+    pub running: Vec<RefreshJob>,
+    pub last: Option<RefreshJob>,
+}
+
+pub struct RefreshJobs {
+    running: Mutex<Vec<RefreshJob>>,
+    // notified whenever a refresh finishes
+    finished: Condvar,
+    seq: AtomicU64,
+    last: Mutex<Option<RefreshJob>>,
+}
+
+fn now() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
+}
+
+impl RefreshJobs {
+    pub const fn new() -> Self {
+        RefreshJobs {
+            running: Mutex::new(vec![]),
+            finished: Condvar::new(),
+            seq: AtomicU64::new(0),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Starts refreshing `fuels` of `district`, once no other refresh of any of them is running,
+    /// queued behind those that are. The refresh counts as running until the returned guard is
+    /// dropped.
+    pub fn start(&'static self, fuels: &[PetroleumType], district: District) -> RefreshLock {
+        let mut running = self.running.lock().unwrap();
+        while overlaps(&running, fuels) {
+            running = self.finished.wait(running).unwrap();
+        }
+        self.register(running, fuels, district)
+    }
+
+    fn register(
+        &'static self,
+        mut running: MutexGuard<Vec<RefreshJob>>,
+        fuels: &[PetroleumType],
+        district: District,
+    ) -> RefreshLock {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        running.push(RefreshJob {
+            seq,
+            id: request_id::current(),
+            district,
+            started_at: now(),
+            finished_at: None,
+            petroleum_types: fuels.iter().map(|fuel| (*fuel, FuelProgress::Fetching)).collect(),
+        });
+        RefreshLock(self, seq)
+    }
+
+    /// Reports on the one refresh running of `petroleum_type`.
+    pub fn progress(&self, petroleum_type: PetroleumType, progress: FuelProgress) {
+        let mut running = self.running.lock().unwrap();
+        if let Some(job) = running.iter_mut().find(|job| job.petroleum_types.contains_key(&petroleum_type)) {
+            job.petroleum_types.insert(petroleum_type, progress);
+        }
+    }
+
+    pub fn report(&self) -> JobsReport {
+        JobsReport {
+            running: self.running.lock().unwrap().clone(),
+            last: self.last.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn overlaps(running: &[RefreshJob], fuels: &[PetroleumType]) -> bool {
+    running
+        .iter()
+        .any(|job| fuels.iter().any(|fuel| job.petroleum_types.contains_key(fuel)))
+}
+
+/// Held for as long as a refresh runs.
+pub struct RefreshLock(&'static RefreshJobs, u64);
+
+impl Drop for RefreshLock {
+    fn drop(&mut self) {
+        let mut running = self.0.running.lock().unwrap();
+        let finished = running
+            .iter()
+            .position(|job| job.seq == self.1)
+            .map(|index| running.remove(index));
+        drop(running);
+        self.0.finished.notify_all();
+        if let Some(job) = finished {
+            *self.0.last.lock().unwrap() = Some(RefreshJob {
+                finished_at: Some(now()),
+                ..job
+            });
+        }
+    }
+}
+
+/// Which price refreshes are running, how far they got, and how the last one went.
+#[get("/jobs")]
+pub async fn refresh_jobs() -> impl Responder {
+    HttpResponse::Ok().json(JOBS.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use cygaz_lib::{District, PetroleumType};
+
+    use crate::jobs::{FuelProgress, RefreshJobs};
+
+    #[test]
+    fn one_refresh_at_a_time_per_fuel() {
+        static JOBS: RefreshJobs = RefreshJobs::new();
+
+        let lock = JOBS.start(&[PetroleumType::Unlead95, PetroleumType::Kerosene], District::All);
+        // the other fuels refresh alongside
+        let diesel = JOBS.start(&[PetroleumType::DieselAuto], District::Paphos);
+
+        JOBS.progress(PetroleumType::Kerosene, FuelProgress::Failed);
+        JOBS.progress(PetroleumType::DieselAuto, FuelProgress::Fetched);
+        let running = JOBS.report().running;
+        assert_eq!(running.len(), 2);
+        assert_eq!(running[0].petroleum_types[&PetroleumType::Unlead95], FuelProgress::Fetching);
+        assert_eq!(running[0].petroleum_types[&PetroleumType::Kerosene], FuelProgress::Failed);
+        assert_eq!(running[1].petroleum_types[&PetroleumType::DieselAuto], FuelProgress::Fetched);
+
+        // an overlapping refresh waits its turn rather than being dropped
+        let (started, queued) = mpsc::channel();
+        let waiting = thread::spawn(move || {
+            let _lock = JOBS.start(&PetroleumType::ALL, District::All);
+            started.send(()).unwrap();
+        });
+        assert!(queued.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(lock);
+        assert!(queued.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(diesel);
+        queued.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiting.join().unwrap();
+
+        let report = JOBS.report();
+        assert!(report.running.is_empty());
+        assert!(report.last.unwrap().finished_at.is_some());
+    }
+}
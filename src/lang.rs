@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpRequest};
+use cygaz_lib::normalize::Transliteration;
+use cygaz_lib::PetroleumStation;
+use serde::Deserialize;
+
+use crate::areas::{Area, AreaPrices};
+use crate::brands::BrandPrices;
+use crate::cheapest::CheapestStations;
+use crate::geojson::{Feature, FeatureCollection};
+use crate::nationwide::{MergedStation, NationwidePriceList};
+use crate::nearest::{NearStation, NearestStations};
+use crate::search::SearchResult;
+use crate::stations::{PricedStation, RegisteredStation};
+use crate::sync::{Delta, PriceChanges};
+
+/// The language station names are given in, `?lang=en` for Latin letters by the configured
+/// transliteration.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    El,
+    #[default]
+    En,
+}
+
+#[derive(Deserialize)]
+struct LangQuery {
+    lang: Option<Lang>,
+}
+
+/// The transliteration to apply when the request asks for `?lang=en`. JSON keeps the names as
+/// upstream lists them otherwise.
+pub fn latin(req: &HttpRequest) -> Option<web::Data<Transliteration>> {
+    let query = web::Query::<LangQuery>::from_query(req.query_string()).ok()?;
+    match query.lang? {
+        Lang::El => None,
+        Lang::En => Some(req.app_data::<web::Data<Transliteration>>().cloned().unwrap_or_default()),
+    }
+}
+
+/// Anything with Greek names, of stations or areas.
+pub trait Names {
+    fn names(&mut self) -> Vec<&mut String>;
+}
+
+// an area
+impl Names for String {
+    fn names(&mut self) -> Vec<&mut String> {
+        vec![self]
+    }
+}
+
+impl<T: Names> Names for Vec<T> {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.iter_mut().flat_map(Names::names).collect()
+    }
+}
+
+impl<K, T: Names> Names for BTreeMap<K, T> {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.values_mut().flat_map(Names::names).collect()
+    }
+}
+
+impl Names for NationwidePriceList {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.stations.names()
+    }
+}
+
+impl Names for PetroleumStation {
+    fn names(&mut self) -> Vec<&mut String> {
+        vec![&mut self.brand, &mut self.company, &mut self.address, &mut self.area]
+    }
+}
+
+impl Names for MergedStation {
+    fn names(&mut self) -> Vec<&mut String> {
+        vec![&mut self.brand, &mut self.company, &mut self.address, &mut self.area]
+    }
+}
+
+impl Names for RegisteredStation {
+    fn names(&mut self) -> Vec<&mut String> {
+        vec![&mut self.brand, &mut self.company, &mut self.address, &mut self.area]
+    }
+}
+
+impl Names for PricedStation {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.station.names()
+    }
+}
+
+impl Names for NearStation {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.station.names()
+    }
+}
+
+impl Names for Feature {
+    fn names(&mut self) -> Vec<&mut String> {
+        let properties = &mut self.properties;
+        vec![&mut properties.brand, &mut properties.company, &mut properties.address, &mut properties.area]
+    }
+}
+
+impl Names for FeatureCollection {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.features.names()
+    }
+}
+
+impl Names for Area {
+    fn names(&mut self) -> Vec<&mut String> {
+        vec![&mut self.name]
+    }
+}
+
+impl Names for AreaPrices {
+    fn names(&mut self) -> Vec<&mut String> {
+        let mut names = self.stations.names();
+        names.push(&mut self.name);
+        names
+    }
+}
+
+impl Names for BrandPrices {
+    fn names(&mut self) -> Vec<&mut String> {
+        let mut names = self.stations.names();
+        names.push(&mut self.name);
+        names
+    }
+}
+
+impl Names for SearchResult {
+    fn names(&mut self) -> Vec<&mut String> {
+        match self {
+            SearchResult::Station { station, .. } => station.names(),
+            SearchResult::Area { area, .. } => vec![area],
+        }
+    }
+}
+
+impl Names for NearestStations {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.stations.names()
+    }
+}
+
+impl Names for CheapestStations {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.stations.names()
+    }
+}
+
+impl Names for PriceChanges {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.stations.names()
+    }
+}
+
+impl Names for Delta {
+    fn names(&mut self) -> Vec<&mut String> {
+        self.stations.names()
+    }
+}
+
+/// The names of `value` in Latin letters, as the CSV has them.
+pub fn transliterate<T: Names>(value: &mut T, transliteration: &Transliteration) {
+    for name in value.names() {
+        *name = transliteration.apply(name);
+    }
+}
+
+/// `value` with its names in Latin letters when the request asks for `?lang=en`.
+pub fn localized<T: Names>(req: &HttpRequest, mut value: T) -> T {
+    if let Some(transliteration) = latin(req) {
+        transliterate(&mut value, &transliteration);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use cygaz_lib::normalize::Transliteration;
+
+    use super::{latin, transliterate};
+    use crate::nationwide::MergedStation;
+
+    #[test]
+    fn latin_only_when_asked_for() {
+        let request = |uri: &str| TestRequest::get().uri(uri).to_http_request();
+        assert!(latin(&request("/prices/1")).is_none());
+        assert!(latin(&request("/prices/1?lang=el")).is_none());
+        assert!(latin(&request("/prices/1?lang=fr")).is_none());
+        assert!(latin(&request("/prices/1?lang=en")).is_some());
+
+        let configured = TestRequest::get()
+            .uri("/prices/1?lang=en")
+            .app_data(web::Data::new(Transliteration::AnyAscii))
+            .to_http_request();
+        assert!(matches!(*latin(&configured).unwrap().into_inner(), Transliteration::AnyAscii));
+    }
+
+    #[test]
+    fn transliterates_every_name() {
+        let mut stations = vec![MergedStation {
+            station_id: "1".to_string(),
+            brand: "ΠΕΤΡΟΛΙΝΑ".to_string(),
+            company: "Πετρολίνα Λτδ".to_string(),
+            address: "Λεωφόρος Αθαλάσσας".to_string(),
+            latitude: "35.1".to_string(),
+            area: "Στρόβολος".to_string(),
+            ..Default::default()
+        }];
+        transliterate(&mut stations, &Transliteration::Letters);
+        assert_eq!(stations[0].brand, "PETROLINA");
+        assert_eq!(stations[0].company, "Petrolina Ltd");
+        assert_eq!(stations[0].address, "Leoforos Athalassas");
+        assert_eq!(stations[0].area, "Strovolos");
+        assert_eq!(stations[0].latitude, "35.1");
+    }
+}
@@ -0,0 +1,177 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::PetroleumStation;
+use serde::{Deserialize, Serialize};
+
+use crate::database::DAY;
+use crate::{PriceList, SharedState};
+
+// how long stations gone from upstream are remembered
+const RETENTION: u128 = 30 * DAY;
+
+// what /stations/changes covers without `since`
+const DEFAULT_WINDOW: u128 = 7 * DAY;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Sighting {
+    pub station_id: String,
+    // as last listed
+    pub brand: String,
+    pub company: String,
+    pub address: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub area: String,
+    pub first_seen: u128,
+    pub last_seen: u128,
+    // the first refresh that no longer listed it, None while listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed_at: Option<u128>,
+}
+
+impl Sighting {
+    fn of(station: &PetroleumStation, now: u128) -> Self {
+        Sighting {
+            station_id: station.station_id.clone(),
+            brand: station.brand.clone(),
+            company: station.company.clone(),
+            address: station.address.clone(),
+            latitude: station.latitude.clone(),
+            longitude: station.longitude.clone(),
+            area: station.area.clone(),
+            first_seen: now,
+            last_seen: now,
+            removed_at: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct StationChanges {
+    pub since: u128,
+    // stations listed by the first refresh tracked are not counted as new
+    pub tracking_since: Option<u128>,
+    pub added: Vec<Sighting>,
+    pub removed: Vec<Sighting>,
+}
+
+/// When every station was first and last listed upstream, to tell which appeared or disappeared.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct StationLifecycle {
+    tracking_since: Option<u128>,
+    stations: BTreeMap<String, Sighting>,
+}
+
+impl StationLifecycle {
+    /// Records the stations `lists` hold after the refresh at `now`, listed for any fuel.
+    pub fn observe(&mut self, lists: &[&PriceList], now: u128) {
+        self.tracking_since.get_or_insert(now);
+        let mut listed = HashSet::new();
+        for station in lists.iter().flat_map(|list| &list.stations) {
+            if station.station_id.is_empty() || !listed.insert(station.station_id.as_str()) {
+                continue;
+            }
+            let first_seen = self.stations.get(&station.station_id).map_or(now, |seen| seen.first_seen);
+            self.stations.insert(
+                station.station_id.clone(),
+                Sighting {
+                    first_seen,
+                    ..Sighting::of(station, now)
+                },
+            );
+        }
+
+        for sighting in self.stations.values_mut() {
+            if !listed.contains(sighting.station_id.as_str()) {
+                sighting.removed_at.get_or_insert(now);
+            }
+        }
+        self.stations
+            .retain(|_, sighting| sighting.removed_at.is_none_or(|at| now.saturating_sub(at) < RETENTION));
+    }
+
+    /// The stations that appeared or disappeared at `since` or later, the latest first.
+    pub fn changes(&self, since: u128) -> StationChanges {
+        let mut added = self
+            .stations
+            .values()
+            .filter(|sighting| sighting.first_seen >= since && Some(sighting.first_seen) != self.tracking_since)
+            .cloned()
+            .collect::<Vec<_>>();
+        added.sort_by_key(|sighting| Reverse(sighting.first_seen));
+        let mut removed = self
+            .stations
+            .values()
+            .filter(|sighting| sighting.removed_at.is_some_and(|at| at >= since))
+            .cloned()
+            .collect::<Vec<_>>();
+        removed.sort_by_key(|sighting| Reverse(sighting.removed_at));
+        StationChanges {
+            since,
+            tracking_since: self.tracking_since,
+            added,
+            removed,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    // milliseconds since the epoch, a week ago by default
+    pub since: Option<u64>,
+}
+
+#[get("/stations/changes")]
+pub async fn station_changes(data: web::Data<SharedState>, query: web::Query<ChangesQuery>) -> impl Responder {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    let since = query.since.map_or(now.saturating_sub(DEFAULT_WINDOW), u128::from);
+    HttpResponse::Ok().json(data.read().lifecycle.changes(since))
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::PetroleumType;
+
+    use crate::database::DAY;
+    use crate::fixtures::prices;
+    use crate::lifecycle::{Sighting, StationLifecycle};
+
+    #[test]
+    fn tells_stations_that_appeared_or_disappeared() {
+        let mut lifecycle = StationLifecycle::default();
+        let ids = |sightings: &[Sighting]| {
+            sightings.iter().map(|s| s.station_id.clone()).collect::<Vec<_>>()
+        };
+
+        lifecycle.observe(&[&prices(PetroleumType::Unlead95, &[("a", "", 1.0), ("b", "", 1.0)])], 10);
+        let changes = lifecycle.changes(0);
+        assert_eq!(changes.tracking_since, Some(10));
+        assert!(changes.added.is_empty());
+
+        // listed for another fuel only is still listed
+        lifecycle.observe(
+            &[
+                &prices(PetroleumType::Unlead95, &[("a", "", 1.0), ("c", "", 1.0)]),
+                &prices(PetroleumType::DieselAuto, &[("b", "", 1.0)]),
+            ],
+            20,
+        );
+        lifecycle.observe(&[&prices(PetroleumType::Unlead95, &[("a", "", 1.0), ("d", "", 1.0)])], 30);
+        let changes = lifecycle.changes(0);
+        assert_eq!(ids(&changes.added), vec!["d", "c"]);
+        assert_eq!(ids(&changes.removed), vec!["b", "c"]);
+        assert_eq!(changes.removed[0].last_seen, 20);
+        assert_eq!(changes.removed[0].removed_at, Some(30));
+        assert_eq!(ids(&lifecycle.changes(25).added), vec!["d"]);
+
+        let a = &lifecycle.stations["a"];
+        assert_eq!((a.first_seen, a.last_seen), (10, 30));
+
+        lifecycle.observe(&[&prices(PetroleumType::Unlead95, &[("a", "", 1.0), ("d", "", 1.0)])], 30 + 30 * DAY);
+        assert!(!lifecycle.stations.contains_key("b"));
+        assert!(lifecycle.changes(0).removed.is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where the HTTP server accepts connections.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Listen {
+    Tcp(String),
+    // for a reverse proxy on the same machine
+    Unix(PathBuf),
+}
+
+impl Listen {
+    /// `LISTEN` when set, `unix:` and the path of a socket or a `host:port`, otherwise `host`
+    /// and `port`.
+    pub fn parse(listen: Option<&str>, host: &str, port: u16) -> Result<Self, String> {
+        match listen {
+            None => Ok(Listen::Tcp(format!("{}:{}", host, port))),
+            Some(listen) => match listen.strip_prefix("unix:") {
+                Some("") => Err("unix: needs the path of a socket".to_string()),
+                Some(path) => Ok(Listen::Unix(PathBuf::from(path))),
+                None if listen.contains(':') => Ok(Listen::Tcp(listen.to_string())),
+                None => Err(format!("{}: expected unix:<path> or <host>:<port>", listen)),
+            },
+        }
+    }
+
+    /// Makes way for binding here, replacing a socket left behind by a previous run.
+    pub fn prepare(&self) -> std::io::Result<()> {
+        match self {
+            Listen::Tcp(_) => Ok(()),
+            #[cfg(unix)]
+            Listen::Unix(path) => remove_socket(path),
+            #[cfg(not(unix))]
+            Listen::Unix(_) => Err(std::io::Error::other("unix sockets are not supported here")),
+        }
+    }
+
+    /// Removes the socket once the server stopped.
+    pub fn close(&self) {
+        #[cfg(unix)]
+        if let Listen::Unix(path) = self {
+            let _ = remove_socket(path);
+        }
+    }
+}
+
+impl fmt::Display for Listen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listen::Tcp(address) => write!(f, "{}", address),
+            Listen::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+// only sockets, anything else at the path is left alone for the bind to fail on
+#[cfg(unix)]
+fn remove_socket(path: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::listen::Listen;
+
+    #[test]
+    fn unix_sockets_or_tcp_addresses() {
+        assert_eq!(Listen::parse(None, "0.0.0.0", 8080), Ok(Listen::Tcp("0.0.0.0:8080".to_string())));
+        let listen = Listen::parse(Some("unix:/run/cygaz.sock"), "0.0.0.0", 8080).unwrap();
+        assert_eq!(listen, Listen::Unix(PathBuf::from("/run/cygaz.sock")));
+        assert_eq!(listen.to_string(), "unix:/run/cygaz.sock");
+        assert_eq!(Listen::parse(Some("127.0.0.1:9090"), "0.0.0.0", 8080), Ok(Listen::Tcp("127.0.0.1:9090".to_string())));
+        assert!(Listen::parse(Some("unix:"), "0.0.0.0", 8080).is_err());
+        assert!(Listen::parse(Some("/run/cygaz.sock"), "0.0.0.0", 8080).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use actix_web::{get, rt, web, Error, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use log::debug;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::SharedState;
+
+// keeps idle connections open through proxies
+const HEARTBEAT: Duration = Duration::from_secs(30);
+
+#[get("/ws")]
+pub async fn price_updates(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<SharedState>,
+) -> Result<HttpResponse, Error> {
+    let (response, mut session, mut stream) = actix_ws::handle(&req, body)?;
+    let mut updates = data.read().updates.subscribe();
+
+    rt::spawn(async move {
+        let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + HEARTBEAT, HEARTBEAT);
+        loop {
+            tokio::select! {
+                update = updates.recv() => {
+                    let sent = match update {
+                        Ok(json) => session.text(json.as_str().to_owned()).await,
+                        // the client has to fetch the prices again to catch up
+                        Err(RecvError::Lagged(missed)) => {
+                            session
+                                .text(serde_json::json!({ "type": "lagged", "missed": missed }).to_string())
+                                .await
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                message = stream.recv() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // listen only, whatever else the client sends is ignored
+                    Some(Ok(_)) => {}
+                },
+                _ = heartbeat.tick() => {
+                    if session.ping(b"").await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        debug!("price updates listener left");
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
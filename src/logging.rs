@@ -0,0 +1,122 @@
+use std::io::Write;
+
+use chrono::{SecondsFormat, Utc};
+use env_logger::fmt::Formatter;
+use log::kv::{self, Key, VisitSource};
+use log::Record;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::request_id;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    // one object a line for log aggregators
+    Json,
+}
+
+/// Logs to stderr at `level`, or the level of `RUST_LOG` without one, in `format`.
+pub fn init(format: LogFormat, level: Option<&str>) {
+    let mut builder = env_logger::Builder::from_default_env();
+    if let Some(level) = level {
+        builder.parse_filters(level);
+    }
+    match format {
+        LogFormat::Text => builder.format(text),
+        LogFormat::Json => builder.format(json),
+    };
+    builder.init();
+}
+
+/// The env_logger line, tagged with the id of the request or job that logged it.
+fn text(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    let level = buf.default_level_style(record.level());
+    let id = request_id::current().map(|id| format!(" {}", id)).unwrap_or_default();
+    writeln!(
+        buf,
+        "[{} {level}{:<5}{level:#} {}{}] {}",
+        buf.timestamp(),
+        record.level(),
+        record.target(),
+        id,
+        record.args()
+    )
+}
+
+// numbers and flags stay numbers and flags, anything else is its text
+struct Fields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = value
+            .to_u64()
+            .map(Value::from)
+            .or_else(|| value.to_i64().map(Value::from))
+            .or_else(|| value.to_f64().map(Value::from))
+            .or_else(|| value.to_bool().map(Value::from))
+            .unwrap_or_else(|| Value::from(value.to_string()));
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// The fields of a record as a JSON object, those it was logged with included.
+pub fn json_fields(record: &Record, timestamp: String, request_id: Option<String>) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("timestamp".to_string(), Value::from(timestamp));
+    fields.insert("level".to_string(), Value::from(record.level().as_str()));
+    fields.insert("target".to_string(), Value::from(record.target()));
+    fields.insert("message".to_string(), Value::from(record.args().to_string()));
+    if let Some(request_id) = request_id {
+        fields.insert("request_id".to_string(), Value::from(request_id));
+    }
+    let _ = record.key_values().visit(&mut Fields(&mut fields));
+    fields
+}
+
+fn json(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let fields = json_fields(record, timestamp, request_id::current());
+    writeln!(buf, "{}", Value::Object(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::PetroleumType;
+    use log::kv::{Source, ToValue};
+    use log::{Level, Record};
+
+    use crate::logging::json_fields;
+
+    #[test]
+    fn json_lines_keep_logged_fields() {
+        let fuel = format!("{:?}", PetroleumType::DieselAuto);
+        let pairs: [(&str, log::kv::Value); 3] =
+            [("duration_ms", 12u64.to_value()), ("fuel", fuel.to_value()), ("not_modified", true.to_value())];
+        let source: &dyn Source = &pairs;
+        let record = Record::builder()
+            .level(Level::Info)
+            .target("cygaz")
+            .args(format_args!("refreshed"))
+            .key_values(source)
+            .build();
+
+        let fields = json_fields(&record, "2026-10-14T12:24:13.000Z".to_string(), Some("trace-me".to_string()));
+        assert_eq!(
+            serde_json::Value::Object(fields),
+            serde_json::json!({
+                "timestamp": "2026-10-14T12:24:13.000Z",
+                "level": "INFO",
+                "target": "cygaz",
+                "message": "refreshed",
+                "request_id": "trace-me",
+                "duration_ms": 12,
+                "fuel": "DieselAuto",
+                "not_modified": true,
+            })
+        );
+    }
+}
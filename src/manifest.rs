@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::PetroleumType;
+use serde::Serialize;
+
+use crate::features::{Feature, Features};
+
+#[derive(Clone, Serialize)]
+pub struct Schedule {
+    pub name: &'static str,
+    pub cron: String,
+    // what a refresh schedule refreshes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub petroleum_types: Vec<PetroleumType>,
+}
+
+/// What this deployment runs with, for tooling to compare against what was meant to be deployed.
+#[derive(Clone, Serialize)]
+pub struct Manifest {
+    pub version: &'static str,
+    pub listen_address: String,
+    // `memory` starts over on restart, `snapshot` keeps the last prices but not history or alerts,
+    // `redis` shares the last prices with every replica
+    pub storage: &'static str,
+    pub cargo_features: Vec<&'static str>,
+    pub schedules: Vec<Schedule>,
+}
+
+#[derive(Serialize)]
+struct ManifestWithFeatures<'a> {
+    #[serde(flatten)]
+    manifest: &'a Manifest,
+    features: BTreeMap<&'static str, Feature>,
+}
+
+// every feature of Cargo.toml, and whether this build has it
+const CARGO_FEATURES: [(&str, bool); 8] = [
+    ("exports", cfg!(feature = "exports")),
+    ("alerts", cfg!(feature = "alerts")),
+    ("grpc", cfg!(feature = "grpc")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("database", cfg!(feature = "database")),
+    ("redis", cfg!(feature = "redis")),
+    ("realtime", cfg!(feature = "realtime")),
+    ("icu", cfg!(feature = "icu")),
+];
+
+impl Manifest {
+    pub fn new(listen_address: String, storage: &'static str, schedules: Vec<Schedule>) -> Self {
+        Manifest {
+            version: env!("CARGO_PKG_VERSION"),
+            listen_address,
+            storage,
+            cargo_features: CARGO_FEATURES
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name)
+                .collect(),
+            schedules,
+        }
+    }
+
+    /// The manifest as JSON, with the features as they stand now.
+    pub fn to_json(&self, features: &Features) -> serde_json::Value {
+        serde_json::to_value(ManifestWithFeatures {
+            manifest: self,
+            features: features.snapshot(),
+        })
+        .unwrap_or_default()
+    }
+}
+
+#[get("/manifest")]
+pub async fn manifest(manifest: web::Data<Manifest>, features: web::Data<Features>) -> impl Responder {
+    HttpResponse::Ok().json(manifest.to_json(&features))
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::PetroleumType;
+
+    use crate::features::{FeatureSource, Features};
+    use crate::manifest::{Manifest, Schedule, CARGO_FEATURES};
+
+    #[test]
+    fn lists_configuration_and_current_features() {
+        let manifest = Manifest::new(
+            "0.0.0.0:8080".to_string(),
+            "memory",
+            vec![Schedule {
+                name: "refresh",
+                cron: "0 1,16,31,46 * * * *".to_string(),
+                petroleum_types: vec![PetroleumType::Unlead95],
+            }],
+        );
+        let features = Features::default();
+        features.set("raw_capture", false, FeatureSource::Config, "CAPTURE_DIR not set");
+
+        let json = manifest.to_json(&features);
+        assert_eq!(json["listen_address"], "0.0.0.0:8080");
+        assert_eq!(json["storage"], "memory");
+        assert_eq!(json["schedules"][0]["name"], "refresh");
+        assert_eq!(json["schedules"][0]["petroleum_types"][0], "Unlead95");
+        assert_eq!(json["features"]["raw_capture"]["enabled"], false);
+    }
+
+    #[test]
+    fn lists_every_cargo_feature() {
+        let manifest = include_str!("../Cargo.toml").parse::<toml::Table>().unwrap();
+        let mut declared = manifest["features"]
+            .as_table()
+            .unwrap()
+            .keys()
+            .filter(|name| *name != "default")
+            .cloned()
+            .collect::<Vec<_>>();
+        declared.sort();
+        let mut listed = CARGO_FEATURES.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+        listed.sort();
+        assert_eq!(listed, declared);
+
+        let built = Manifest::new(String::new(), "memory", vec![]).cargo_features;
+        assert_eq!(built.contains(&"mqtt"), cfg!(feature = "mqtt"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use cygaz_lib::PetroleumType;
+use serde::{Deserialize, Serialize};
+
+use crate::history::RefreshHistory;
+
+/// Wholesale prices published at `published_at`, valid until the next bulletin.
+#[derive(Clone, Deserialize)]
+pub struct WholesaleBulletin {
+    pub published_at: u128,
+    pub prices: BTreeMap<PetroleumType, f32>,
+}
+
+#[derive(Default)]
+pub struct Wholesale {
+    // oldest first
+    bulletins: Vec<WholesaleBulletin>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MarginPoint {
+    pub updated_at: u128,
+    pub retail_avg: f32,
+    pub wholesale: f32,
+    pub margin: f32,
+}
+
+impl Wholesale {
+    pub fn new(mut bulletins: Vec<WholesaleBulletin>) -> Self {
+        bulletins.sort_by_key(|bulletin| bulletin.published_at);
+        Wholesale { bulletins }
+    }
+
+    /// Reads a JSON array of bulletins.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let body = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        let bulletins = serde_json::from_str(&body).map_err(|err| format!("{}: {}", path, err))?;
+        Ok(Wholesale::new(bulletins))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bulletins.is_empty()
+    }
+
+    /// Wholesale price of the latest bulletin listing `petroleum_type` at `at`.
+    fn price_at(&self, petroleum_type: PetroleumType, at: u128) -> Option<f32> {
+        let published = self.bulletins.partition_point(|bulletin| bulletin.published_at <= at);
+        self.bulletins[..published]
+            .iter()
+            .rev()
+            .find_map(|bulletin| bulletin.prices.get(&petroleum_type).copied())
+    }
+
+    /// Estimated gross margin per fuel for every refresh, oldest first. Refreshes without
+    /// a retail average or an earlier bulletin are left out.
+    pub fn margins(&self, history: &RefreshHistory) -> BTreeMap<PetroleumType, Vec<MarginPoint>> {
+        let mut margins: BTreeMap<PetroleumType, Vec<MarginPoint>> = BTreeMap::new();
+        for record in history.records() {
+            for stats in &record.stats {
+                let (Some(retail_avg), Some(wholesale)) =
+                    (stats.avg, self.price_at(stats.petroleum_type, record.updated_at))
+                else {
+                    continue;
+                };
+                margins.entry(stats.petroleum_type).or_default().push(MarginPoint {
+                    updated_at: record.updated_at,
+                    retail_avg,
+                    wholesale,
+                    margin: retail_avg - wholesale,
+                });
+            }
+        }
+        margins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use cygaz_lib::{PetroleumType, PriceUnit};
+
+    use crate::history::{RefreshHistory, RefreshRecord};
+    use crate::margins::{Wholesale, WholesaleBulletin};
+    use crate::nationwide::PriceStats;
+
+    fn record(updated_at: u128, avg: Option<f32>) -> RefreshRecord {
+        RefreshRecord {
+            updated_at,
+            updated_at_str: "".to_string(),
+            stats: vec![PriceStats {
+                petroleum_type: PetroleumType::Unlead95,
+                unit: PriceUnit::Litre,
+                count: 1,
+                min: avg,
+                max: avg,
+                avg,
+                updated_at: None,
+                updated_at_by_district: BTreeMap::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn margins_use_the_bulletin_in_effect() {
+        let wholesale = Wholesale::new(vec![
+            WholesaleBulletin {
+                published_at: 20,
+                prices: BTreeMap::from([(PetroleumType::Unlead95, 1.25)]),
+            },
+            WholesaleBulletin {
+                published_at: 10,
+                prices: BTreeMap::from([(PetroleumType::Unlead95, 1.0)]),
+            },
+        ]);
+
+        let mut history = RefreshHistory::new(10);
+        history.push(record(5, Some(1.5)));
+        history.push(record(15, Some(1.5)));
+        history.push(record(25, Some(1.5)));
+        history.push(record(30, None));
+
+        let margins = wholesale.margins(&history);
+        let points = &margins[&PetroleumType::Unlead95];
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[0].updated_at, points[0].margin), (15, 0.5));
+        assert_eq!((points[1].updated_at, points[1].margin), (25, 0.25));
+    }
+}
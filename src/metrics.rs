@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{get, web, Error, HttpResponse, Responder};
+use arc_swap::{ArcSwap, Guard};
+
+use crate::{AppStateWithPrices, SharedState};
+
+// percentiles are over this many of the latest samples
+const RECENT_SAMPLES: usize = 1024;
+
+static QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// Latest durations of something measured over and over.
+#[derive(Default)]
+pub struct Samples {
+    recent: VecDeque<Duration>,
+    count: u64,
+    sum: Duration,
+}
+
+impl Samples {
+    pub fn record(&mut self, duration: Duration) {
+        if self.recent.len() == RECENT_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(duration);
+        self.count += 1;
+        self.sum += duration;
+    }
+
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        let mut sorted = self.recent.iter().copied().collect::<Vec<_>>();
+        sorted.sort();
+        let idx = ((sorted.len() as f64 * quantile).ceil() as usize).saturating_sub(1);
+        sorted.get(idx).copied().unwrap_or_default()
+    }
+
+    // as a prometheus summary
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        for quantile in QUANTILES {
+            let _ = writeln!(
+                out,
+                "{}{{{},quantile=\"{}\"}} {}",
+                name,
+                labels,
+                quantile,
+                self.quantile(quantile).as_secs_f64()
+            );
+        }
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum.as_secs_f64());
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+/// The shared state as an immutable snapshot that every `write` replaces with a new one, so
+/// readers never wait and never see a write halfway. Records how long every `write` waited for
+/// the one before it.
+pub struct TimedSwap<T> {
+    current: ArcSwap<T>,
+    // one write at a time, so that none is lost
+    writer: Mutex<()>,
+    write_waits: Mutex<Samples>,
+}
+
+impl<T: Clone> TimedSwap<T> {
+    pub fn new(value: T) -> Self {
+        TimedSwap {
+            current: ArcSwap::from_pointee(value),
+            writer: Mutex::new(()),
+            write_waits: Mutex::new(Samples::default()),
+        }
+    }
+
+    /// The current snapshot.
+    pub fn read(&self) -> Guard<Arc<T>> {
+        self.current.load()
+    }
+
+    /// A copy of the current snapshot to change, swapped in once the returned guard is dropped.
+    pub fn write(&self) -> SwapGuard<'_, T> {
+        let started = Instant::now();
+        let writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.write_waits.lock().unwrap().record(started.elapsed());
+        SwapGuard {
+            next: Some(T::clone(&self.current.load())),
+            swap: self,
+            _writer: writer,
+        }
+    }
+}
+
+/// Changes to the shared state, served from when it is dropped.
+pub struct SwapGuard<'a, T> {
+    swap: &'a TimedSwap<T>,
+    _writer: MutexGuard<'a, ()>,
+    next: Option<T>,
+}
+
+impl<T> Deref for SwapGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.next.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for SwapGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.next.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for SwapGuard<'_, T> {
+    fn drop(&mut self) {
+        // a write that panicked halfway is left out
+        if std::thread::panicking() {
+            return;
+        }
+        if let Some(next) = self.next.take() {
+            self.swap.current.store(Arc::new(next));
+        }
+    }
+}
+
+/// Latency of every route, by the pattern it matched.
+#[derive(Default)]
+pub struct HandlerLatencies {
+    handlers: Mutex<BTreeMap<String, Samples>>,
+}
+
+pub async fn handler_latency(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let latencies = req.app_data::<web::Data<HandlerLatencies>>().cloned();
+    let pattern = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+    let res = next.call(req).await;
+
+    if let Some(latencies) = latencies {
+        let mut handlers = latencies.handlers.lock().unwrap();
+        handlers.entry(pattern).or_default().record(started.elapsed());
+    }
+    res
+}
+
+fn render(state: &TimedSwap<AppStateWithPrices>, latencies: &HandlerLatencies) -> String {
+    let mut out = String::new();
+
+    out.push_str("# TYPE cygaz_lock_wait_seconds summary\n");
+    state
+        .write_waits
+        .lock()
+        .unwrap()
+        .write(&mut out, "cygaz_lock_wait_seconds", "lock=\"state\",mode=\"write\"");
+
+    out.push_str("# TYPE cygaz_handler_latency_seconds summary\n");
+    for (pattern, samples) in latencies.handlers.lock().unwrap().iter() {
+        let labels = format!("handler=\"{}\"", pattern.replace('\\', "\\\\").replace('"', "\\\""));
+        samples.write(&mut out, "cygaz_handler_latency_seconds", &labels);
+    }
+    out
+}
+
+#[get("/metrics")]
+pub async fn metrics(data: web::Data<SharedState>, latencies: web::Data<HandlerLatencies>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(render(&data, &latencies))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::metrics::{Samples, TimedSwap, RECENT_SAMPLES};
+
+    #[test]
+    fn quantiles_of_recent_samples() {
+        let mut samples = Samples::default();
+        assert_eq!(samples.quantile(0.5), Duration::ZERO);
+
+        for millis in 1..=100 {
+            samples.record(Duration::from_millis(millis));
+        }
+        assert_eq!(samples.quantile(0.5), Duration::from_millis(50));
+        assert_eq!(samples.quantile(0.99), Duration::from_millis(99));
+
+        for _ in 0..RECENT_SAMPLES {
+            samples.record(Duration::from_millis(1));
+        }
+        assert_eq!(samples.quantile(0.99), Duration::from_millis(1));
+        assert_eq!(samples.count, 100 + RECENT_SAMPLES as u64);
+    }
+
+    #[test]
+    fn writes_are_served_once_done() {
+        let swap = TimedSwap::new(vec![1]);
+        let before = swap.read();
+        let mut write = swap.write();
+        write.push(2);
+        assert_eq!(**swap.read(), vec![1]);
+        drop(write);
+        assert_eq!(**swap.read(), vec![1, 2]);
+        assert_eq!(**before, vec![1]);
+
+        let panicked = std::panic::catch_unwind(|| {
+            let mut write = swap.write();
+            write.clear();
+            panic!("halfway");
+        });
+        assert!(panicked.is_err());
+        assert_eq!(**swap.read(), vec![1, 2]);
+    }
+}
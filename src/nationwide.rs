@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashMap};
+
+use cygaz_lib::{District, PetroleumType, PriceUnit, StationStatus, CURRENCY};
+use serde::{Deserialize, Serialize};
+
+use crate::stats::district_stats;
+use crate::status::{station_key, StationKey};
+use crate::PriceList;
+
+#[derive(Clone, Serialize)]
+pub struct PriceStats {
+    pub petroleum_type: PetroleumType,
+    // what the prices of this fuel are quoted for
+    pub unit: PriceUnit,
+    pub count: usize,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub avg: Option<f32>,
+    // when upstream last answered for every district, and for each of them, of this fuel
+    pub updated_at: Option<u128>,
+    pub updated_at_by_district: BTreeMap<District, u128>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct MergedStation {
+    pub station_id: String,
+    pub brand: String,
+    pub offline: bool,
+    pub company: String,
+    pub address: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub area: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<StationStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_since: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline_since: Option<u128>,
+    pub prices: BTreeMap<PetroleumType, f32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NationwidePriceList {
+    pub district: District,
+    pub updated_at: u128,
+    pub updated_at_str: String,
+    pub currency: &'static str,
+    pub stats: Vec<PriceStats>,
+    pub stations: Vec<MergedStation>,
+}
+
+/// Narrows a price list down to some petroleum types, by name with `fuel` or by id with
+/// `kind`, both comma separated.
+#[derive(Deserialize, Default)]
+pub struct FuelQuery {
+    pub fuel: Option<String>,
+    pub kind: Option<String>,
+}
+
+impl FuelQuery {
+    /// The selected petroleum types, all of them when neither is given.
+    pub fn petroleum_types(&self) -> Result<Vec<PetroleumType>, String> {
+        let mut selected = vec![];
+        for name in self.fuel.iter().flat_map(|fuel| fuel.split(',')) {
+            selected.push(PetroleumType::from_name(name.trim()).ok_or(format!("Unknown fuel {}", name))?);
+        }
+        for id in self.kind.iter().flat_map(|kind| kind.split(',')) {
+            let petroleum_type = id.trim().parse().ok().and_then(PetroleumType::from_id);
+            selected.push(petroleum_type.ok_or(format!("Unknown kind {}", id))?);
+        }
+        Ok(match selected.is_empty() {
+            true => PetroleumType::ALL.to_vec(),
+            false => PetroleumType::ALL
+                .into_iter()
+                .filter(|petroleum_type| selected.contains(petroleum_type))
+                .collect(),
+        })
+    }
+}
+
+/// Statistics of `list`, over the same stations as `stats::district_stats`.
+pub fn price_stats(list: &PriceList) -> PriceStats {
+    let stats = district_stats(list.stations.iter());
+    PriceStats {
+        petroleum_type: list.petroleum_type,
+        unit: list.unit,
+        count: stats.count,
+        min: stats.min,
+        max: stats.max,
+        avg: stats.avg,
+        updated_at: list.listed_at(),
+        updated_at_by_district: list.updated_at_by_district.clone(),
+    }
+}
+
+/// Merges the per fuel price lists into one station set.
+pub fn merge(lists: &[&PriceList]) -> NationwidePriceList {
+    let mut stations: Vec<MergedStation> = Vec::new();
+    let mut index: HashMap<StationKey, usize> = HashMap::new();
+
+    for list in lists {
+        for station in &list.stations {
+            let position = *index.entry(station_key(station)).or_insert_with(|| {
+                stations.push(MergedStation {
+                    station_id: station.station_id.clone(),
+                    brand: station.brand.clone(),
+                    offline: false,
+                    company: station.company.clone(),
+                    address: station.address.clone(),
+                    latitude: station.latitude.clone(),
+                    longitude: station.longitude.clone(),
+                    area: station.area.clone(),
+                    status: station.status,
+                    status_since: station.status_since,
+                    offline_since: station.offline_since,
+                    prices: BTreeMap::new(),
+                });
+                stations.len() - 1
+            });
+
+            let merged = &mut stations[position];
+            merged.offline |= station.offline;
+            merged.prices.insert(list.petroleum_type, station.price);
+        }
+    }
+
+    let latest = lists.iter().max_by_key(|list| list.updated_at);
+
+    NationwidePriceList {
+        district: District::All,
+        updated_at: latest.map(|list| list.updated_at).unwrap_or_default(),
+        updated_at_str: latest
+            .map(|list| list.updated_at_str.clone())
+            .unwrap_or_default(),
+        currency: CURRENCY,
+        stats: lists.iter().map(|list| price_stats(list)).collect(),
+        stations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{District, PetroleumType};
+
+    use crate::fixtures::{list, prices};
+    use crate::nationwide::{merge, price_stats, FuelQuery};
+
+    #[test]
+    fn merges_stations_across_fuels() {
+        let unlead95 = prices(PetroleumType::Unlead95, &[("1", "Strovolos", 1.40), ("2", "Strovolos", 1.50)]);
+        let diesel = prices(PetroleumType::DieselAuto, &[("1", "Strovolos", 1.60)]);
+
+        let merged = merge(&[&unlead95, &diesel]);
+
+        assert_eq!(merged.stations.len(), 2);
+        assert_eq!(merged.stations[0].prices.len(), 2);
+        assert_eq!(merged.stations[0].prices[&PetroleumType::DieselAuto], 1.60);
+        assert_eq!(merged.stats[0].count, 2);
+        assert_eq!(merged.stats[0].min, Some(1.40));
+        assert_eq!(merged.stats[0].max, Some(1.50));
+    }
+
+    #[test]
+    fn empty_list_has_no_stats() {
+        let kerosene = list(PetroleumType::Kerosene, 1, vec![]);
+        let merged = merge(&[&kerosene]);
+        assert_eq!(merged.stats[0].count, 0);
+        assert!(merged.stats[0].avg.is_none());
+    }
+
+    #[test]
+    fn stats_tell_when_each_district_was_listed() {
+        let mut diesel = list(PetroleumType::DieselAuto, 1, vec![]);
+        diesel.listed(District::Paphos, 10);
+        assert!(price_stats(&diesel).updated_at.is_none());
+
+        diesel.listed(District::All, 20);
+        diesel.listed(District::Limassol, 30);
+        let stats = price_stats(&diesel);
+        assert_eq!(stats.updated_at, Some(20));
+        assert_eq!(stats.updated_at_by_district[&District::Paphos], 20);
+        assert_eq!(stats.updated_at_by_district[&District::Limassol], 30);
+        assert_eq!(stats.updated_at_by_district.len(), 5);
+    }
+
+    #[test]
+    fn fuel_query_selects_by_name_or_id() {
+        let query = FuelQuery {
+            fuel: Some("diesel_auto,Unlead95".to_string()),
+            kind: Some("5".to_string()),
+        };
+        assert_eq!(
+            query.petroleum_types().unwrap(),
+            vec![PetroleumType::Unlead95, PetroleumType::DieselAuto, PetroleumType::Kerosene]
+        );
+        assert_eq!(FuelQuery::default().petroleum_types().unwrap().len(), 5);
+
+        let query = FuelQuery {
+            fuel: None,
+            kind: Some("6".to_string()),
+        };
+        assert!(query.petroleum_types().is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::age::DataAge;
+use crate::lang;
+use crate::nationwide::{self, FuelQuery, MergedStation};
+use crate::status::StationFilter;
+use crate::SharedState;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+pub fn default_radius_km() -> f64 {
+    10.0
+}
+
+#[derive(Deserialize)]
+pub struct NearestQuery {
+    pub lat: f64,
+    pub lon: f64,
+    #[serde(default = "default_radius_km")]
+    pub radius_km: f64,
+}
+
+#[derive(Serialize)]
+pub struct NearStation {
+    #[serde(flatten)]
+    pub station: MergedStation,
+    pub distance_km: f64,
+}
+
+#[derive(Serialize)]
+pub struct NearestStations {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_km: f64,
+    pub stations: Vec<NearStation>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+/// Great circle distance between two coordinates in degrees.
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// The `stations` within `radius_km` of `from`, closest first. Stations without usable
+/// coordinates are left out.
+pub fn nearest(stations: Vec<MergedStation>, from: (f64, f64), radius_km: f64) -> Vec<NearStation> {
+    let mut near = stations
+        .into_iter()
+        .filter_map(|station| {
+            let latitude = station.latitude.trim().parse::<f64>().ok()?;
+            let longitude = station.longitude.trim().parse::<f64>().ok()?;
+            let distance_km = distance_km(from, (latitude, longitude));
+            (distance_km <= radius_km).then_some(NearStation {
+                station,
+                distance_km,
+            })
+        })
+        .collect::<Vec<_>>();
+    near.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+    near
+}
+
+#[get("/prices/nearest")]
+pub async fn nearest_prices(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    query: web::Query<NearestQuery>,
+    fuel: web::Query<FuelQuery>,
+    filter: web::Query<StationFilter>,
+) -> impl Responder {
+    let valid = (-90.0..=90.0).contains(&query.lat)
+        && (-180.0..=180.0).contains(&query.lon)
+        && query.radius_km > 0.0;
+    if !valid {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "lat, lon or radius_km out of range" }));
+    }
+    let selected = match fuel.petroleum_types() {
+        Ok(selected) => selected,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+
+    let state = data.read();
+    let lists = selected
+        .into_iter()
+        .filter_map(|petroleum_type| state.price_list(petroleum_type))
+        .map(|list| filter.apply(list))
+        .collect::<Vec<_>>();
+    let merged = nationwide::merge(&lists.iter().collect::<Vec<_>>());
+
+    let age = DataAge::of(&req, merged.updated_at);
+    let nearest = NearestStations {
+        latitude: query.lat,
+        longitude: query.lon,
+        radius_km: query.radius_km,
+        stations: nearest(merged.stations, (query.lat, query.lon), query.radius_km),
+        stale: age.stale,
+    };
+    let mut res = HttpResponse::Ok().json(lang::localized(&req, nearest));
+    age.insert(res.headers_mut());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::merged_at;
+    use crate::nearest::{distance_km, nearest};
+
+    #[test]
+    fn sorts_stations_within_radius_by_distance() {
+        // Nicosia to Limassol is roughly 63km as the crow flies
+        let nicosia = (35.1856, 33.3823);
+        let limassol = (34.7071, 33.0226);
+        assert!((distance_km(nicosia, limassol) - 63.0).abs() < 2.0);
+
+        let stations = vec![
+            merged_at("limassol", "34.7071", "33.0226", &[]),
+            merged_at("strovolos", "35.1500", "33.3500", &[]),
+            merged_at("centre", "35.1856", "33.3823", &[]),
+            merged_at("no coordinates", "", "", &[]),
+        ];
+        let near = nearest(stations, nicosia, 10.0);
+        assert_eq!(
+            near.iter().map(|s| s.station.station_id.as_str()).collect::<Vec<_>>(),
+            vec!["centre", "strovolos"]
+        );
+        assert_eq!(near[0].distance_km, 0.0);
+    }
+}
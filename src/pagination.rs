@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+const CURSOR_VERSION: &str = "v1";
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+pub const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, PartialEq)]
+pub struct CursorError(String);
+
+impl Display for CursorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Opaque cursor pointing right after the item with timestamp `millis`.
+pub fn encode_cursor(millis: u128) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", CURSOR_VERSION, millis))
+}
+
+pub fn decode_cursor(cursor: &str) -> Result<u128, CursorError> {
+    let invalid = || CursorError(format!("Invalid cursor {:?}", cursor));
+    decode(cursor)?.parse::<u128>().map_err(|_| invalid())
+}
+
+/// Opaque cursor pointing at `position` of a list as of its `version`.
+pub fn encode_versioned_cursor(position: u128, version: u128) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}@{}", CURSOR_VERSION, position, version))
+}
+
+/// The position and the version of the list `cursor` points into.
+pub fn decode_versioned_cursor(cursor: &str) -> Result<(u128, u128), CursorError> {
+    let invalid = || CursorError(format!("Invalid cursor {:?}", cursor));
+    let decoded = decode(cursor)?;
+    let (position, version) = decoded.split_once('@').ok_or_else(invalid)?;
+    Ok((
+        position.parse::<u128>().map_err(|_| invalid())?,
+        version.parse::<u128>().map_err(|_| invalid())?,
+    ))
+}
+
+// what follows the cursor version
+fn decode(cursor: &str) -> Result<String, CursorError> {
+    let invalid = || CursorError(format!("Invalid cursor {:?}", cursor));
+
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    match decoded.split_once(':') {
+        Some((CURSOR_VERSION, rest)) => Ok(rest.to_string()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Newest first page of `items`, which have to be sorted by ascending `key`.
+/// Seeks straight to the cursor position instead of skipping an offset.
+pub fn seek<T: Clone>(
+    items: &VecDeque<T>,
+    key: impl Fn(&T) -> u128,
+    query: &PageQuery,
+) -> Result<Page<T>, CursorError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let end = match &query.cursor {
+        Some(cursor) => {
+            let before = decode_cursor(cursor)?;
+            items.partition_point(|item| key(item) < before)
+        }
+        None => items.len(),
+    };
+    let start = end.saturating_sub(limit);
+
+    let page = items.range(start..end).rev().cloned().collect::<Vec<_>>();
+    let next_cursor = match (start, page.last()) {
+        (0, _) | (_, None) => None,
+        (_, Some(last)) => Some(encode_cursor(key(last))),
+    };
+
+    Ok(Page {
+        items: page,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    use crate::pagination::{
+        decode_cursor, decode_versioned_cursor, encode_cursor, encode_versioned_cursor, seek, PageQuery,
+    };
+
+    #[test]
+    fn cursor_round_trip() {
+        let cursor = encode_cursor(1723729592807);
+        assert_eq!(decode_cursor(&cursor), Ok(1723729592807));
+        assert!(decode_cursor("not-a-cursor").is_err());
+        assert!(decode_cursor(&URL_SAFE_NO_PAD.encode("v0:1")).is_err());
+        assert!(decode_cursor(&URL_SAFE_NO_PAD.encode("v1:abc")).is_err());
+    }
+
+    #[test]
+    fn versioned_cursor_round_trip() {
+        let cursor = encode_versioned_cursor(50, 1723729592807);
+        assert_eq!(decode_versioned_cursor(&cursor), Ok((50, 1723729592807)));
+        assert!(decode_versioned_cursor(&encode_cursor(50)).is_err());
+        assert!(decode_versioned_cursor(&URL_SAFE_NO_PAD.encode("v1:50@abc")).is_err());
+    }
+
+    #[test]
+    fn seeks_pages_newest_first() {
+        let items = (1..=5u128).collect::<VecDeque<_>>();
+
+        let first = seek(&items, |i| *i, &PageQuery { cursor: None, limit: Some(2) }).unwrap();
+        assert_eq!(first.items, vec![5, 4]);
+
+        let second = seek(
+            &items,
+            |i| *i,
+            &PageQuery {
+                cursor: first.next_cursor,
+                limit: Some(2),
+            },
+        )
+        .unwrap();
+        assert_eq!(second.items, vec![3, 2]);
+
+        let last = seek(
+            &items,
+            |i| *i,
+            &PageQuery {
+                cursor: second.next_cursor,
+                limit: Some(2),
+            },
+        )
+        .unwrap();
+        assert_eq!(last.items, vec![1]);
+        assert!(last.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,91 @@
+use cygaz_lib::PriceResult;
+
+use crate::summary::Scrape;
+use crate::PriceList;
+
+/// The nationwide listing of `list` with the stations of one district, those in `district_areas`,
+/// replaced by `fetched`, what upstream lists for that district alone. None when the `scrape`
+/// failed or listed no stations, which keeps those of the district as they are.
+pub fn splice(
+    list: &PriceList,
+    mut fetched: PriceResult,
+    scrape: &Scrape,
+    district_areas: &[String],
+) -> Option<PriceResult> {
+    if !scrape.ok || fetched.stations.is_empty() {
+        return None;
+    }
+    let (replaced, mut stations): (Vec<_>, Vec<_>) = list
+        .stations
+        .iter()
+        .cloned()
+        .partition(|station| district_areas.contains(&station.area));
+    stations.append(&mut fetched.stations);
+    fetched.stations = stations;
+    // the warnings stay those of this refresh only, the rest were reported when last refreshed
+    fetched.total_rows += list.total_rows.saturating_sub(replaced.len());
+    Some(fetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{PetroleumType, PriceResult};
+
+    use crate::fixtures::station;
+    use crate::partial::splice;
+    use crate::summary::Scrape;
+    use crate::PriceList;
+
+    #[test]
+    fn replaces_only_the_refreshed_district() {
+        let list = PriceList {
+            stations: vec![
+                station("a", "Strovolos", 1.40),
+                station("b", "Limassol", 1.45),
+                station("c", "Germasogeia", 1.50),
+            ],
+            total_rows: 3,
+            ..PriceList::new(PetroleumType::DieselAuto, 0, String::new())
+        };
+        let mut fetched = PriceResult::default();
+        fetched.stations = vec![station("b", "Limassol", 1.39)];
+        fetched.total_rows = 1;
+        let areas = ["Limassol".to_string(), "Germasogeia".to_string()];
+        let ok = Scrape {
+            ok: true,
+            ..Scrape::default()
+        };
+
+        let spliced = splice(&list, fetched, &ok, &areas).unwrap();
+        let prices = spliced
+            .stations
+            .iter()
+            .map(|station| (station.station_id.as_str(), station.price))
+            .collect::<Vec<_>>();
+        assert_eq!(prices, vec![("a", 1.40), ("b", 1.39)]);
+        assert_eq!(spliced.total_rows, 2);
+    }
+
+    #[test]
+    fn keeps_the_district_upstream_did_not_list() {
+        let list = PriceList {
+            stations: vec![station("a", "Strovolos", 1.40), station("b", "Limassol", 1.45)],
+            total_rows: 2,
+            ..PriceList::new(PetroleumType::DieselAuto, 0, String::new())
+        };
+        let areas = ["Limassol".to_string()];
+        let failed = Scrape {
+            ok: false,
+            error: Some("timed out".to_string()),
+            duration_ms: 5000,
+        };
+        assert!(splice(&list, PriceResult::default(), &failed, &areas).is_none());
+
+        let empty = Scrape {
+            ok: false,
+            error: Some("no stations listed".to_string()),
+            duration_ms: 300,
+        };
+        assert!(splice(&list, PriceResult::default(), &empty, &areas).is_none());
+    }
+}
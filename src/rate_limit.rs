@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ConnectionInfo, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::middleware::Next;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+// purge expired windows once the map grows past this many clients
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+// probes and the metrics scraper are never limited, nor counted
+static EXEMPT_PATHS: [&str; 3] = ["/healthz", "/ready", "/metrics"];
+
+#[derive(Clone, Copy, Serialize)]
+pub struct RateLimitPolicy {
+    pub limit: u32,
+    pub window_seconds: u64,
+    // requests past the limit a client may still make within a window when enforced
+    pub burst: u32,
+    pub enforced: bool,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: u64,
+    // past the limit and the burst
+    #[serde(skip)]
+    pub exceeded: bool,
+}
+
+struct Window {
+    started_at: Instant,
+    count: u32,
+}
+
+/// Fixed window request counter per client address.
+pub struct RateLimiter {
+    policy: RateLimitPolicy,
+    // counts clients by the address the proxy in front forwards rather than the proxy's own
+    trust_proxy: bool,
+    windows: Mutex<HashMap<IpAddr, Window>>,
+}
+
+// an address as `Forwarded` or `X-Forwarded-For` gives it, with or without a port
+fn parse_ip(addr: &str) -> Option<IpAddr> {
+    let addr = addr.trim();
+    addr.parse::<IpAddr>()
+        .or_else(|_| addr.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| addr.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>())
+        .ok()
+}
+
+impl RateLimiter {
+    pub fn new(policy: RateLimitPolicy, trust_proxy: bool) -> Self {
+        RateLimiter {
+            policy,
+            trust_proxy,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> RateLimitPolicy {
+        self.policy
+    }
+
+    /// The address of the client a request came from, the one the proxy forwards when trusted.
+    pub fn client_ip(&self, info: &ConnectionInfo) -> Option<IpAddr> {
+        match self.trust_proxy {
+            true => info.realip_remote_addr().and_then(parse_ip),
+            false => info.peer_addr().and_then(parse_ip),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.policy.window_seconds)
+    }
+
+    fn status(&self, window: &Window, now: Instant) -> RateLimitStatus {
+        let elapsed = now.duration_since(window.started_at);
+        RateLimitStatus {
+            limit: self.policy.limit,
+            remaining: self.policy.limit.saturating_sub(window.count),
+            reset: self.window().saturating_sub(elapsed).as_secs(),
+            exceeded: window.count > self.policy.limit.saturating_add(self.policy.burst),
+        }
+    }
+
+    /// Counts a request for `ip` and returns the remaining allowance.
+    pub fn hit(&self, ip: IpAddr) -> RateLimitStatus {
+        self.hit_at(ip, Instant::now())
+    }
+
+    fn hit_at(&self, ip: IpAddr, now: Instant) -> RateLimitStatus {
+        let window_length = self.window();
+        let mut windows = self.windows.lock().unwrap();
+
+        if windows.len() > MAX_TRACKED_CLIENTS {
+            windows.retain(|_, w| now.duration_since(w.started_at) < window_length);
+        }
+
+        let window = windows.entry(ip).or_insert(Window {
+            started_at: now,
+            count: 0,
+        });
+        if now.duration_since(window.started_at) >= window_length {
+            window.started_at = now;
+            window.count = 0;
+        }
+        window.count = window.count.saturating_add(1);
+
+        self.status(window, now)
+    }
+
+    /// Current allowance for `ip` without counting a request.
+    pub fn peek(&self, ip: IpAddr) -> RateLimitStatus {
+        let now = Instant::now();
+        let windows = self.windows.lock().unwrap();
+        match windows.get(&ip) {
+            Some(window) if now.duration_since(window.started_at) < self.window() => {
+                self.status(window, now)
+            }
+            _ => RateLimitStatus {
+                limit: self.policy.limit,
+                remaining: self.policy.limit,
+                reset: self.policy.window_seconds,
+                exceeded: false,
+            },
+        }
+    }
+}
+
+fn insert_headers(headers: &mut actix_web::http::header::HeaderMap, status: RateLimitStatus) {
+    for (name, value) in [
+        ("x-ratelimit-limit", status.limit),
+        ("x-ratelimit-remaining", status.remaining),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+    headers.insert(
+        HeaderName::from_static("x-ratelimit-reset"),
+        HeaderValue::from(status.reset),
+    );
+}
+
+/// Adds the rate limit headers, and answers `429 Too Many Requests` instead of calling the
+/// handler once an enforced limit is exceeded.
+pub async fn rate_limit_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    let exempt = EXEMPT_PATHS.contains(&req.path());
+    let ip = limiter.as_ref().and_then(|limiter| limiter.client_ip(&req.connection_info()));
+    let (status, enforced) = match (limiter, ip) {
+        (Some(limiter), Some(ip)) if limiter.policy().limit > 0 && !exempt => {
+            (Some(limiter.hit(ip)), limiter.policy().enforced)
+        }
+        _ => (None, false),
+    };
+
+    if let Some(status) = status.filter(|status| enforced && status.exceeded) {
+        let mut res = HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, status.reset.max(1)))
+            .json(serde_json::json!({ "error": "Rate limit exceeded" }));
+        insert_headers(res.headers_mut(), status);
+        return Ok(req.into_response(res));
+    }
+
+    let mut res = next.call(req).await?.map_into_boxed_body();
+    if let Some(status) = status {
+        insert_headers(res.headers_mut(), status);
+    }
+
+    Ok(res)
+}
+
+#[derive(Serialize)]
+struct RateLimitDescription {
+    policy: RateLimitPolicy,
+    status: Option<RateLimitStatus>,
+}
+
+#[get("/rate-limit")]
+pub async fn rate_limit(req: HttpRequest, limiter: web::Data<RateLimiter>) -> impl Responder {
+    let status = limiter.client_ip(&req.connection_info()).map(|ip| limiter.peek(ip));
+    HttpResponse::Ok().json(RateLimitDescription {
+        policy: limiter.policy(),
+        status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+
+    use actix_web::test::TestRequest;
+
+    use crate::rate_limit::{RateLimitPolicy, RateLimiter};
+
+    const POLICY: RateLimitPolicy = RateLimitPolicy {
+        limit: 2,
+        window_seconds: 60,
+        burst: 0,
+        enforced: false,
+    };
+
+    #[test]
+    fn counts_down_and_resets_after_window() {
+        let limiter = RateLimiter::new(POLICY, false);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let now = Instant::now();
+
+        assert_eq!(limiter.hit_at(ip, now).remaining, 1);
+        assert_eq!(limiter.hit_at(ip, now).remaining, 0);
+        assert_eq!(limiter.hit_at(ip, now).remaining, 0);
+
+        let later = now + Duration::from_secs(61);
+        let status = limiter.hit_at(ip, later);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(status.reset, 60);
+    }
+
+    #[test]
+    fn exceeds_after_the_burst() {
+        let policy = RateLimitPolicy {
+            limit: 1,
+            window_seconds: 60,
+            burst: 1,
+            enforced: true,
+        };
+        let limiter = RateLimiter::new(policy, false);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let now = Instant::now();
+
+        assert!(!limiter.hit_at(ip, now).exceeded);
+        let status = limiter.hit_at(ip, now);
+        assert_eq!(status.remaining, 0);
+        assert!(!status.exceeded);
+        assert!(limiter.hit_at(ip, now).exceeded);
+        assert!(!limiter.hit_at(ip, now + Duration::from_secs(60)).exceeded);
+    }
+
+    #[test]
+    fn trusts_forwarded_addresses_only_behind_a_proxy() {
+        let peer = "10.0.0.1:41000".parse().unwrap();
+        let req = |header: (&str, &str)| TestRequest::default().peer_addr(peer).insert_header(header).to_srv_request();
+        let forwarded = req(("x-forwarded-for", "203.0.113.7, 10.0.0.1"));
+
+        let direct = RateLimiter::new(POLICY, false);
+        assert_eq!(direct.client_ip(&forwarded.connection_info()), "10.0.0.1".parse().ok());
+
+        let proxied = RateLimiter::new(POLICY, true);
+        assert_eq!(proxied.client_ip(&forwarded.connection_info()), "203.0.113.7".parse().ok());
+        let forwarded = req(("forwarded", "for=\"[2001:db8::1]:4711\""));
+        assert_eq!(proxied.client_ip(&forwarded.connection_info()), "2001:db8::1".parse().ok());
+        let direct = TestRequest::default().peer_addr(peer).to_srv_request();
+        assert_eq!(proxied.client_ip(&direct.connection_info()), "10.0.0.1".parse().ok());
+    }
+}
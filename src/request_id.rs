@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::error::BlockingError;
+use actix_web::{web, Error};
+use log::info;
+use uuid::Uuid;
+
+pub static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+// longer ones from clients are replaced rather than logged
+const MAX_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static TASK_ID: String;
+}
+
+thread_local! {
+    static THREAD_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Id of the request or job running, for tagging what it logs.
+pub fn current() -> Option<String> {
+    TASK_ID
+        .try_with(Clone::clone)
+        .ok()
+        .or_else(|| THREAD_ID.with(|id| id.borrow().clone()))
+}
+
+/// A new id for a job of `kind`, like `refresh-1f0c4a2e`.
+pub fn job_id(kind: &str) -> String {
+    let uuid = Uuid::new_v4().simple().to_string();
+    format!("{}-{}", kind, &uuid[..8])
+}
+
+/// Runs `future` under `id`, every await of it included.
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    TASK_ID.scope(id, future).await
+}
+
+/// Runs `f` under `id` on this thread, for blocking work that logs.
+pub fn within<T>(id: Option<String>, f: impl FnOnce() -> T) -> T {
+    let previous = THREAD_ID.with(|current| current.replace(id));
+    let result = f();
+    THREAD_ID.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// Spawns a thread that logs under the id of the request or job spawning it.
+pub fn spawn<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> JoinHandle<T> {
+    let id = current();
+    thread::spawn(move || within(id, f))
+}
+
+/// Runs blocking `f` on the blocking thread pool under the id of the request or job awaiting it,
+/// keeping the async runtime free to serve meanwhile.
+pub fn block<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> impl Future<Output = Result<T, BlockingError>> {
+    let id = current();
+    web::block(move || within(id, f))
+}
+
+fn usable(id: &HeaderValue) -> Option<String> {
+    let id = id.to_str().ok()?;
+    let valid = !id.is_empty() && id.len() <= MAX_ID_LENGTH && id.bytes().all(|b| b.is_ascii_graphic());
+    valid.then(|| id.to_string())
+}
+
+/// Runs every request under the `X-Request-Id` it came with, or a new one, echoes it back and
+/// logs how the request went.
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID)
+        .and_then(usable)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let method = req.method().clone();
+    let path = req.path().to_string();
+    let started = Instant::now();
+
+    let mut res = scope(id.clone(), async move {
+        let res = next.call(req).await;
+        let status = match &res {
+            Ok(res) => res.status().as_u16(),
+            Err(err) => err.as_response_error().status_code().as_u16(),
+        };
+        let duration_ms = started.elapsed().as_millis() as u64;
+        info!(
+            method:% = method, path:% = path, status, duration_ms;
+            "{} {} {} in {}ms", method, path, status, duration_ms
+        );
+        res
+    })
+    .await?;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID.clone(), value);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::HeaderValue;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    use crate::request_id::{current, request_id, spawn, within, REQUEST_ID};
+
+    #[actix_web::test]
+    async fn honors_and_echoes_request_ids() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(request_id))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().body(current().unwrap()) })),
+        )
+        .await;
+
+        let req = TestRequest::get().insert_header((REQUEST_ID.clone(), "abc-123")).to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.headers().get(&REQUEST_ID).unwrap(), "abc-123");
+        assert_eq!(read_body(res).await, "abc-123");
+
+        let req = TestRequest::get()
+            .insert_header((REQUEST_ID.clone(), HeaderValue::from_static("not one")))
+            .to_request();
+        let res = call_service(&app, req).await;
+        let id = res.headers().get(&REQUEST_ID).unwrap().to_str().unwrap().to_string();
+        assert_eq!(id.len(), 36);
+        assert_eq!(read_body(res).await, id);
+    }
+
+    #[test]
+    fn threads_log_under_the_id_spawning_them() {
+        assert_eq!(current(), None);
+        let spawned = within(Some("refresh-1".to_string()), || spawn(current).join().unwrap());
+        assert_eq!(spawned.as_deref(), Some("refresh-1"));
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,253 @@
+use actix_web::middleware::from_fn;
+use actix_web::web::{self, ServiceConfig};
+
+use crate::features::{FeatureSource, Features};
+
+// mounts the endpoints of a route group
+type Configure = fn(&mut ServiceConfig);
+
+/// Endpoints that can be left out of a deployment, either at compile time through the cargo
+/// feature of the same name or at runtime through `DISABLED_ROUTES`.
+pub struct RouteGroup {
+    pub name: &'static str,
+    feature: &'static str,
+    compiled: bool,
+    configure: Configure,
+}
+
+pub static ROUTE_GROUPS: [RouteGroup; 8] = [
+    RouteGroup {
+        name: "prices",
+        feature: "prices_routes",
+        compiled: true,
+        configure: prices,
+    },
+    RouteGroup {
+        name: "stations",
+        feature: "stations_routes",
+        compiled: true,
+        configure: stations,
+    },
+    RouteGroup {
+        name: "districts",
+        feature: "districts_routes",
+        compiled: true,
+        configure: districts,
+    },
+    RouteGroup {
+        name: "stats",
+        feature: "stats_routes",
+        compiled: true,
+        configure: stats,
+    },
+    RouteGroup {
+        name: "exports",
+        feature: "exports_routes",
+        compiled: cfg!(feature = "exports"),
+        configure: exports,
+    },
+    RouteGroup {
+        name: "alerts",
+        feature: "alerts_routes",
+        compiled: cfg!(feature = "alerts"),
+        configure: alerts,
+    },
+    RouteGroup {
+        name: "realtime",
+        feature: "realtime_routes",
+        compiled: cfg!(feature = "realtime"),
+        configure: realtime,
+    },
+    RouteGroup {
+        name: "admin",
+        feature: "admin_routes",
+        compiled: true,
+        configure: admin,
+    },
+];
+
+/// A version of the API, served under its prefix. A version changing response shapes replaces
+/// the route groups it changes rather than the handlers of the versions before it.
+pub struct ApiVersion {
+    pub prefix: &'static str,
+    // by the name of the group of `ROUTE_GROUPS` they replace
+    replaced: &'static [(&'static str, Configure)],
+}
+
+/// Every version of the API. The unversioned paths are aliases of the first.
+pub static API_VERSIONS: [ApiVersion; 1] = [ApiVersion {
+    prefix: "/v1",
+    replaced: &[],
+}];
+
+impl ApiVersion {
+    fn configure(&self, group: &RouteGroup) -> Configure {
+        self.replaced
+            .iter()
+            .find(|(name, _)| *name == group.name)
+            .map(|(_, configure)| *configure)
+            .unwrap_or(group.configure)
+    }
+}
+
+// what every version serves whichever groups are mounted
+fn common(cfg: &mut ServiceConfig) {
+    cfg.service(crate::petroleum_types).service(crate::fuel_types);
+}
+
+/// Mounts the `enabled` route groups unversioned, as the first version serves them, and under
+/// the prefix of every version.
+pub fn mount(cfg: &mut ServiceConfig, enabled: &[&'static RouteGroup]) {
+    common(cfg);
+    for group in enabled {
+        (API_VERSIONS[0].configure(group))(cfg);
+    }
+    for version in &API_VERSIONS {
+        let mut scope = web::scope(version.prefix).configure(common);
+        for group in enabled {
+            scope = scope.configure(version.configure(group));
+        }
+        cfg.service(scope);
+    }
+}
+
+fn prices(cfg: &mut ServiceConfig) {
+    cfg.service(crate::unlead95)
+        .service(crate::unlead98)
+        .service(crate::diesel_heat)
+        .service(crate::diesel_auto)
+        .service(crate::kerosene)
+        .service(crate::all_prices)
+        .service(crate::nearest::nearest_prices)
+        .service(crate::cheapest::cheapest_prices)
+        .service(crate::sync::price_delta)
+        .service(crate::sync::price_changes);
+}
+
+fn stations(cfg: &mut ServiceConfig) {
+    cfg.service(crate::stations::list_stations)
+        .service(crate::geojson::stations_within)
+        // before /stations/{id} takes the path
+        .service(crate::clusters::station_clusters)
+        .service(crate::lifecycle::station_changes)
+        .service(crate::stations::get_station)
+        .service(crate::stations::station_history)
+        .service(crate::geojson::stations_geojson)
+        .service(crate::search::search_stations)
+        .service(crate::brands::list_brands)
+        .service(crate::brands::get_brand_prices);
+}
+
+fn districts(cfg: &mut ServiceConfig) {
+    cfg.service(crate::districts)
+        .service(crate::areas::list_areas)
+        .service(crate::areas::get_area_prices);
+}
+
+fn stats(cfg: &mut ServiceConfig) {
+    cfg.service(crate::stats::price_statistics)
+        .service(crate::refresh_history)
+        .service(crate::price_margins)
+        .service(crate::bulletin::eu_comparison)
+        .service(crate::summary::refresh_status)
+        .service(crate::calculator::trip_cost)
+        .service(crate::trends::price_trends)
+        .service(crate::forecast::price_forecast);
+}
+
+fn exports(_cfg: &mut ServiceConfig) {
+    #[cfg(feature = "exports")]
+    _cfg.service(crate::csv::prices_csv)
+        .service(crate::export::export_prices);
+}
+
+fn alerts(_cfg: &mut ServiceConfig) {
+    #[cfg(feature = "alerts")]
+    _cfg.service(crate::alerts::create_alert)
+        .service(crate::alerts::list_alerts)
+        .service(crate::alerts::delete_alert);
+}
+
+fn realtime(_cfg: &mut ServiceConfig) {
+    #[cfg(feature = "realtime")]
+    _cfg.service(crate::live::price_updates)
+        .service(crate::events::refresh_events);
+}
+
+fn admin(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .wrap(from_fn(crate::admin::require_admin))
+            .service(crate::admin::refresh)
+            .service(crate::jobs::refresh_jobs)
+            .service(crate::admin::list_webhooks)
+            .service(crate::admin::add_webhook)
+            .service(crate::admin::remove_webhook),
+    );
+}
+
+/// Parses the comma separated `DISABLED_ROUTES` into route group names.
+pub fn parse_disabled(disabled: &str) -> Result<Vec<&str>, String> {
+    let names = disabled
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    match names
+        .iter()
+        .find(|name| !ROUTE_GROUPS.iter().any(|group| group.name == **name))
+    {
+        Some(unknown) => Err(format!("unknown route group {}", unknown)),
+        None => Ok(names),
+    }
+}
+
+/// Records every route group in `features` and returns the ones to mount.
+pub fn enabled(disabled: &[&str], features: &Features) -> Vec<&'static RouteGroup> {
+    let mut enabled = vec![];
+    for group in &ROUTE_GROUPS {
+        let (mounted, reason) = if !group.compiled {
+            (false, format!("compiled without the {} feature", group.name))
+        } else if disabled.contains(&group.name) {
+            (false, format!("DISABLED_ROUTES lists {}", group.name))
+        } else {
+            enabled.push(group);
+            (true, "mounted".to_string())
+        };
+        features.set(group.feature, mounted, FeatureSource::Config, reason);
+    }
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::App;
+
+    use crate::features::Features;
+    use crate::routes::{enabled, mount, parse_disabled, ROUTE_GROUPS};
+
+    #[actix_web::test]
+    async fn versioned_and_unversioned_paths() {
+        let app = init_service(App::new().configure(|cfg| mount(cfg, &[]))).await;
+        for (path, status) in [("/petroleum-types", 200), ("/v1/petroleum-types", 200), ("/v2/petroleum-types", 404)] {
+            let res = call_service(&app, TestRequest::get().uri(path).to_request()).await;
+            assert_eq!(res.status().as_u16(), status, "{}", path);
+        }
+    }
+
+    #[test]
+    fn disabled_groups_are_not_mounted() {
+        assert!(parse_disabled("prices, stats").is_ok());
+        assert!(parse_disabled("realtime").is_ok());
+        assert!(parse_disabled("websockets").is_err());
+
+        let features = Features::default();
+        let mounted = enabled(&parse_disabled("stats").unwrap(), &features);
+        assert_eq!(mounted.len(), ROUTE_GROUPS.iter().filter(|g| g.compiled).count() - 1);
+
+        let snapshot = features.snapshot();
+        assert!(!snapshot["stats_routes"].enabled);
+        assert!(snapshot["prices_routes"].enabled);
+    }
+}
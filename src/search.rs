@@ -0,0 +1,192 @@
+use std::cmp::Reverse;
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::normalize::greeklish_key;
+use cygaz_lib::{AreasByDistrict, District};
+use serde::{Deserialize, Serialize};
+
+use crate::lang;
+use crate::pagination::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::stations::{merged_stations, registry, RegisteredStation, StationsQuery};
+use crate::SharedState;
+
+// what a query word scores by how it matches a word of a name
+const EXACT: u32 = 3;
+const PREFIX: u32 = 2;
+const CLOSE: u32 = 1;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SearchResult {
+    Station {
+        score: u32,
+        #[serde(flatten)]
+        station: Box<RegisteredStation>,
+    },
+    Area {
+        score: u32,
+        area: String,
+        district: District,
+    },
+}
+
+impl SearchResult {
+    fn score(&self) -> u32 {
+        match self {
+            SearchResult::Station { score, .. } | SearchResult::Area { score, .. } => *score,
+        }
+    }
+}
+
+// typos allowed in a word, none in short ones
+fn typos_allowed(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn word_score(query: &str, word: &str) -> Option<u32> {
+    if word == query {
+        return Some(EXACT);
+    }
+    if query.chars().count() > 1 && word.starts_with(query) {
+        return Some(PREFIX);
+    }
+    (edit_distance(query, word) <= typos_allowed(query)).then_some(CLOSE)
+}
+
+/// How well `names` match every word of `query`, already a `greeklish_key`. None unless each
+/// query word matches a word of some name.
+pub fn score(query: &str, names: &[&str]) -> Option<u32> {
+    let keys = names.iter().map(|name| greeklish_key(name)).collect::<Vec<_>>();
+    query
+        .split_whitespace()
+        .map(|query_word| {
+            keys.iter()
+                .flat_map(|key| key.split_whitespace())
+                .filter_map(|word| word_score(query_word, word))
+                .max()
+        })
+        .sum()
+}
+
+/// Stations and areas matching `q`, best first, stations before areas that score the same.
+pub fn search(q: &str, stations: Vec<RegisteredStation>, areas: &AreasByDistrict) -> Vec<SearchResult> {
+    let query = greeklish_key(q);
+    let stations = stations.into_iter().filter_map(|station| {
+        let names = [
+            station.brand.as_str(),
+            station.company.as_str(),
+            station.address.as_str(),
+            station.area.as_str(),
+        ];
+        let score = score(&query, &names)?;
+        Some(SearchResult::Station {
+            score,
+            station: Box::new(station),
+        })
+    });
+    let areas = areas.iter().flat_map(|(district, areas)| {
+        let query = &query;
+        areas.iter().filter_map(move |area| {
+            let score = score(query, &[area])?;
+            Some(SearchResult::Area {
+                score,
+                area: area.clone(),
+                district: *district,
+            })
+        })
+    });
+
+    let mut results = stations.chain(areas).collect::<Vec<_>>();
+    // stable, so stations and areas keep their order among equals
+    results.sort_by_key(|result| Reverse(result.score()));
+    results
+}
+
+#[get("/search")]
+pub async fn search_stations(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    if greeklish_key(&query.q).is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "q is required" }));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let state = data.read();
+    let all = StationsQuery {
+        district: None,
+        brand: None,
+        include_offline: true,
+    };
+    let stations = registry(merged_stations(&state), &state.areas, &all);
+    let mut results = search(&query.q, stations, &state.areas);
+    results.truncate(limit);
+    HttpResponse::Ok().json(lang::localized(&req, results))
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District};
+
+    use crate::fixtures::registered;
+    use crate::search::{search, SearchResult};
+
+    fn ids(results: &[SearchResult]) -> Vec<String> {
+        results
+            .iter()
+            .map(|result| match result {
+                SearchResult::Station { station, .. } => station.station_id.clone(),
+                SearchResult::Area { area, .. } => area.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranks_greeklish_and_typos() {
+        let stations = vec![
+            registered("a", "ΕΚΟ", "Λεωφόρος Μακαρίου 8", "Στρόβολος"),
+            registered("b", "Petrolina", "Αρχιεπισκόπου Κυπριανού 12", "Στροβολος"),
+            registered("c", "Shell", "Γρίβα Διγενή 40", "Λεμεσός"),
+        ];
+        let areas = AreasByDistrict::from([(District::Nicosia, vec!["Στρόβολος".to_string()])]);
+
+        // the area name matches exactly, the addresses of b add nothing
+        let results = search("strovolos", stations.clone(), &areas);
+        assert_eq!(ids(&results), vec!["a", "b", "Στρόβολος"]);
+
+        // a typo and a prefix, the second word has to match too
+        let results = search("makarioy strov", stations.clone(), &areas);
+        assert_eq!(ids(&results), vec!["a"]);
+
+        assert_eq!(ids(&search("Strobolos", stations.clone(), &areas)), vec!["a", "b", "Στρόβολος"]);
+        let results = search("Lemesso", stations.clone(), &areas);
+        assert_eq!(ids(&results), vec!["c"]);
+        assert!(search("xyz", stations, &areas).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use serde::de::{self, value, DeserializeOwned, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use toml::{Table, Value};
+
+/// Settings come from the `--config` file, then from environment variables and last from flags,
+/// each overriding the one before.
+#[derive(Parser, Debug, Default)]
+#[command(version, about = "Cyprus petroleum prices, directly from the source")]
+pub struct Cli {
+    /// TOML file of settings, named as their environment variables in lowercase
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Address host
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Address port
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Cron expression, with seconds, of the scheduled refresh
+    #[arg(long, value_name = "CRON")]
+    pub refresh_schedule: Option<String>,
+    /// SQLite file every refreshed station price is appended to
+    #[arg(long, value_name = "FILE")]
+    pub database_path: Option<String>,
+    /// Last known prices, saved after every refresh and served at startup
+    #[arg(long, value_name = "FILE")]
+    pub snapshot_file: Option<String>,
+    /// What gets logged, a filter as RUST_LOG takes
+    #[arg(long, value_name = "FILTER")]
+    pub log_level: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scrapes the live upstream once instead of serving
+    Smoke {
+        /// unlead95 by default
+        fuel: Option<String>,
+    },
+}
+
+impl Cli {
+    // with the variables they override
+    fn flags(&self) -> [(&'static str, Option<String>); 6] {
+        [
+            ("HOST", self.host.clone()),
+            ("PORT", self.port.map(|port| port.to_string())),
+            ("REFRESH_SCHEDULE", self.refresh_schedule.clone()),
+            ("DATABASE_PATH", self.database_path.clone()),
+            ("SNAPSHOT_FILE", self.snapshot_file.clone()),
+            ("LOG_LEVEL", self.log_level.clone()),
+        ]
+    }
+}
+
+// a setting as its environment variable would have it, lists comma separated
+fn env_value(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        Value::Boolean(value) => Ok(value.to_string()),
+        Value::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| match value {
+                    Value::Array(_) | Value::Table(_) => Err(format!("{}: lists cannot nest", key)),
+                    value => env_value(key, value),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(values.join(","))
+        }
+        _ => Err(format!("{}: expected a string, number, boolean or list of them", key)),
+    }
+}
+
+// captures the fields a derived Deserialize asks for and deserializes nothing
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields only"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+// the settings `T` knows of
+fn setting_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut names: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut names));
+    names
+}
+
+/// The settings of a config file under the environment variables they stand for, rejecting
+/// those `T` does not know of.
+pub fn file_vars<T: DeserializeOwned>(body: &str) -> Result<Vec<(String, String)>, String> {
+    let table = body.parse::<Table>().map_err(|err| err.to_string())?;
+    let names = setting_names::<T>();
+    let unknown = table
+        .keys()
+        .filter(|key| !names.contains(&key.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(format!("unknown settings {}", unknown.join(", ")));
+    }
+
+    table
+        .iter()
+        .map(|(key, value)| Ok((key.to_uppercase(), env_value(key, value)?)))
+        .collect()
+}
+
+/// The settings of `cli`, its config file overridden by `env` overridden by its flags.
+pub fn load<T: DeserializeOwned>(cli: &Cli, env: impl IntoIterator<Item = (String, String)>) -> Result<T, String> {
+    let mut vars = BTreeMap::new();
+    if let Some(path) = &cli.config {
+        let body = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        vars.extend(file_vars::<T>(&body).map_err(|err| format!("{}: {}", path.display(), err))?);
+    }
+    vars.extend(env.into_iter().map(|(name, value)| (name.to_uppercase(), value)));
+    vars.extend(
+        cli.flags()
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value?))),
+    );
+    envy::from_iter(vars).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::settings::{file_vars, load, Cli};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Settings {
+        port: u16,
+        host: Option<String>,
+        #[serde(default)]
+        webhook_urls: String,
+    }
+
+    #[test]
+    fn flags_override_env_overriding_the_file() {
+        let body = "port = 8081\nhost = \"127.0.0.1\"\nwebhook_urls = [\"http://a\", \"http://b\"]\n";
+        let vars = file_vars::<Settings>(body).unwrap();
+        assert!(vars.contains(&("WEBHOOK_URLS".to_string(), "http://a,http://b".to_string())));
+
+        let path = std::env::temp_dir().join(format!("cygaz-settings-{}.toml", std::process::id()));
+        std::fs::write(&path, body).unwrap();
+        let cli = Cli {
+            config: Some(path.clone()),
+            ..Cli::default()
+        };
+        let env = || vec![("PORT".to_string(), "8082".to_string())];
+        let settings = load::<Settings>(&cli, env()).unwrap();
+        assert_eq!(settings.port, 8082);
+        assert_eq!(settings.host.as_deref(), Some("127.0.0.1"));
+        assert_eq!(settings.webhook_urls, "http://a,http://b");
+
+        let cli = Cli { port: Some(8083), ..cli };
+        assert_eq!(load::<Settings>(&cli, env()).unwrap().port, 8083);
+        std::fs::remove_file(path).unwrap();
+
+        assert!(file_vars::<Settings>("port = 1\nprot = 2\n").unwrap_err().contains("unknown settings prot"));
+        assert!(file_vars::<Settings>("port = [[1]]\n").is_err());
+    }
+}
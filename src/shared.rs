@@ -0,0 +1,196 @@
+#[cfg(feature = "redis")]
+use std::sync::Mutex;
+#[cfg(feature = "redis")]
+use std::time::Duration;
+
+#[cfg(feature = "redis")]
+use redis::{Client, Commands, Connection};
+#[cfg(feature = "redis")]
+use uuid::Uuid;
+
+use crate::snapshot::Snapshot;
+
+#[cfg(feature = "redis")]
+const LEAD_KEY: &str = "cygaz:refresh";
+#[cfg(feature = "redis")]
+const VERSION_KEY: &str = "cygaz:version";
+#[cfg(feature = "redis")]
+const SNAPSHOT_KEY: &str = "cygaz:snapshot";
+
+// long enough for replicas whose schedule fires a little later to find a leader, short enough for
+// the next scheduled refresh to elect one again
+#[cfg(feature = "redis")]
+const LEAD_FOR: Duration = Duration::from_secs(60);
+
+// a slow Redis fails the call instead of holding up the refresh thread
+#[cfg(feature = "redis")]
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prices shared with every replica through Redis, only the replica leading a scheduled refresh
+/// scrapes upstream and the others take over what it publishes.
+#[cfg(feature = "redis")]
+pub struct SharedCache {
+    client: Client,
+    // tells apart who holds the lead
+    replica: String,
+    // version of the latest snapshot this replica published or took over, its own price lists
+    // get newer timestamps from refreshes that failed too
+    taken: Mutex<u128>,
+}
+
+#[cfg(not(feature = "redis"))]
+pub enum SharedCache {}
+
+#[cfg(feature = "redis")]
+impl SharedCache {
+    pub fn open(url: &str) -> Result<Self, String> {
+        let client = Client::open(url).map_err(|err| format!("{}: {}", url, err))?;
+        Ok(SharedCache {
+            client,
+            replica: Uuid::new_v4().to_string(),
+            taken: Mutex::new(0),
+        })
+    }
+
+    fn connect(&self) -> Result<Connection, String> {
+        let conn = self
+            .client
+            .get_connection_with_timeout(TIMEOUT)
+            .map_err(|err| err.to_string())?;
+        conn.set_read_timeout(Some(TIMEOUT)).map_err(|err| err.to_string())?;
+        conn.set_write_timeout(Some(TIMEOUT)).map_err(|err| err.to_string())?;
+        Ok(conn)
+    }
+
+    /// Whether this replica leads the refresh of the next `LEAD_FOR`.
+    pub fn lead(&self) -> Result<bool, String> {
+        let mut conn = self.connect()?;
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(LEAD_KEY)
+            .arg(&self.replica)
+            .arg("NX")
+            .arg("PX")
+            .arg(LEAD_FOR.as_millis() as u64)
+            .query(&mut conn)
+            .map_err(|err| err.to_string())?;
+        Ok(acquired.is_some())
+    }
+
+    /// Makes `snapshot` of prices last refreshed at `version` the one every replica serves.
+    pub fn publish(&self, version: u128, snapshot: &Snapshot) -> Result<(), String> {
+        let body = serde_json::to_string(snapshot).map_err(|err| err.to_string())?;
+        let mut conn = self.connect()?;
+        redis::pipe()
+            .atomic()
+            .set(SNAPSHOT_KEY, body)
+            .set(VERSION_KEY, version.to_string())
+            .query::<()>(&mut conn)
+            .map_err(|err| err.to_string())?;
+        *self.taken.lock().unwrap() = version;
+        Ok(())
+    }
+
+    /// The published snapshot, unless this replica already has it.
+    pub fn take(&self) -> Result<Option<Snapshot>, String> {
+        let mut conn = self.connect()?;
+        let published: Option<String> = conn.get(VERSION_KEY).map_err(|err| err.to_string())?;
+        let taken = *self.taken.lock().unwrap();
+        let Some(published) = newer(published.as_deref(), taken) else {
+            return Ok(None);
+        };
+        let body: Option<String> = conn.get(SNAPSHOT_KEY).map_err(|err| err.to_string())?;
+        let Some(snapshot) = body.as_deref().map(decode).transpose()? else {
+            return Ok(None);
+        };
+        let mut taken = self.taken.lock().unwrap();
+        // published or taken over by another thread meanwhile
+        if published <= *taken {
+            return Ok(None);
+        }
+        *taken = published;
+        Ok(Some(snapshot))
+    }
+}
+
+#[cfg(not(feature = "redis"))]
+impl SharedCache {
+    pub fn open(_url: &str) -> Result<Self, String> {
+        Err("compiled without the redis feature".to_string())
+    }
+
+    pub fn lead(&self) -> Result<bool, String> {
+        match *self {}
+    }
+
+    pub fn publish(&self, _version: u128, _snapshot: &Snapshot) -> Result<(), String> {
+        match *self {}
+    }
+
+    pub fn take(&self) -> Result<Option<Snapshot>, String> {
+        match *self {}
+    }
+}
+
+/// The published version, if one was and it is newer than the `taken` one.
+#[cfg(feature = "redis")]
+fn newer(published: Option<&str>, taken: u128) -> Option<u128> {
+    published
+        .and_then(|published| published.parse::<u128>().ok())
+        .filter(|published| *published > taken)
+}
+
+#[cfg(feature = "redis")]
+fn decode(body: &str) -> Result<Snapshot, String> {
+    serde_json::from_str(body).map_err(|err| err.to_string())
+}
+
+#[cfg(all(test, feature = "redis"))]
+mod tests {
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    use cygaz_lib::{AreasByDistrict, District};
+
+    use crate::lifecycle::StationLifecycle;
+    use crate::shared::{decode, newer, SharedCache};
+    use crate::snapshot::Snapshot;
+
+    #[test]
+    fn takes_only_newer_versions() {
+        assert_eq!(newer(None, 0), None);
+        assert_eq!(newer(Some("not a version"), 0), None);
+        assert_eq!(newer(Some("10"), 10), None);
+        assert_eq!(newer(Some("10"), 11), None);
+        assert_eq!(newer(Some("11"), 10), Some(11));
+    }
+
+    #[test]
+    fn decodes_published_snapshots() {
+        let snapshot = Snapshot {
+            areas: AreasByDistrict::from([(District::Paphos, vec!["Πέγεια".to_string()])]),
+            lists: vec![],
+            lifecycle: StationLifecycle::default(),
+        };
+        let decoded = decode(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        assert_eq!(decoded.areas[&District::Paphos], vec!["Πέγεια"]);
+        assert!(decode("{").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_urls() {
+        assert!(SharedCache::open("not a url").is_err());
+        assert!(SharedCache::open("redis://127.0.0.1:6379").is_ok());
+    }
+
+    #[test]
+    fn gives_up_on_an_unresponsive_redis() {
+        // accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let shared = SharedCache::open(&url).unwrap();
+
+        let started = Instant::now();
+        assert!(shared.lead().is_err());
+        assert!(started.elapsed() < Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+// refreshes running right now, whoever started them
+static REFRESHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a refresh as running until dropped.
+pub struct RefreshRunning(());
+
+impl RefreshRunning {
+    pub fn start() -> Self {
+        REFRESHES.fetch_add(1, Ordering::SeqCst);
+        RefreshRunning(())
+    }
+}
+
+impl Drop for RefreshRunning {
+    fn drop(&mut self) {
+        REFRESHES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Waits for the running refreshes to finish, returns false when `deadline` passed first.
+pub async fn refreshes_finished(deadline: Duration) -> bool {
+    let started = Instant::now();
+    while REFRESHES.load(Ordering::SeqCst) > 0 {
+        if started.elapsed() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    true
+}
+
+/// Resolves on the first SIGINT or SIGTERM.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(err) => warn!("cannot listen for SIGTERM: {}", err),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::shutdown::{refreshes_finished, RefreshRunning};
+
+    #[actix_web::test]
+    async fn waits_for_running_refreshes() {
+        assert!(refreshes_finished(Duration::ZERO).await);
+
+        let running = RefreshRunning::start();
+        assert!(!refreshes_finished(Duration::from_millis(150)).await);
+
+        let finishing = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(running);
+        });
+        assert!(refreshes_finished(Duration::from_secs(5)).await);
+        finishing.await.unwrap();
+    }
+}
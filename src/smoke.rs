@@ -0,0 +1,154 @@
+use std::thread;
+
+use cygaz_lib::{CyGazClient, District, PetroleumType, PriceResult};
+
+// more skipped rows than this share most likely means the markup changed
+const MAX_WARNING_SHARE: f32 = 0.1;
+
+// a listed price outside of this range was parsed from the wrong column
+const PLAUSIBLE_PRICES: std::ops::Range<f32> = 0.3..5.0;
+
+/// What `check` found in a live listing.
+#[derive(Default, PartialEq, Debug)]
+pub struct Findings {
+    // the markup most likely changed, the listing fails
+    pub problems: Vec<String>,
+    // upstream lists some stations like this, worth a look but no failure
+    pub warnings: Vec<String>,
+}
+
+/// What is wrong with a live listing, no problems when it looks like upstream still serves
+/// the markup the parser expects.
+pub fn check(result: &PriceResult, min_stations: usize) -> Findings {
+    let mut problems = vec![];
+    let mut without_coordinates = 0;
+    if result.stations.len() < min_stations {
+        problems.push(format!(
+            "{} stations, expected at least {}",
+            result.stations.len(),
+            min_stations
+        ));
+    }
+    if result.total_rows > 0 && result.warnings.len() as f32 / result.total_rows as f32 > MAX_WARNING_SHARE {
+        problems.push(format!(
+            "{} of {} rows skipped, first: {}",
+            result.warnings.len(),
+            result.total_rows,
+            result.warnings[0].reason
+        ));
+    }
+    for station in &result.stations {
+        let unmapped = station.latitude.trim().is_empty() && station.longitude.trim().is_empty();
+        if unmapped {
+            without_coordinates += 1;
+        }
+        let coordinates = station.latitude.trim().parse::<f64>().is_ok_and(|lat| (-90.0..=90.0).contains(&lat))
+            && station.longitude.trim().parse::<f64>().is_ok_and(|lon| (-180.0..=180.0).contains(&lon));
+        let missing = [
+            ("station_id", &station.station_id),
+            ("brand", &station.brand),
+            ("address", &station.address),
+            ("area", &station.area),
+        ]
+        .into_iter()
+        .filter(|(_, value)| value.trim().is_empty())
+        .map(|(field, _)| field)
+        .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            problems.push(format!("station {} has no {}", station.station_id, missing.join(", ")));
+        }
+        if !PLAUSIBLE_PRICES.contains(&station.price) {
+            problems.push(format!("station {} priced {}", station.station_id, station.price));
+        }
+        if !coordinates && !unmapped {
+            problems.push(format!(
+                "station {} at invalid coordinates {},{}",
+                station.station_id, station.latitude, station.longitude
+            ));
+        }
+    }
+    let warnings = match without_coordinates {
+        0 => vec![],
+        count => vec![format!("{} stations without coordinates", count)],
+    };
+    Findings { problems, warnings }
+}
+
+/// Scrapes `petroleum_type` once, nationwide, and prints what is wrong with it. Returns
+/// whether the listing passed.
+pub fn run(petroleum_type: PetroleumType, min_stations: usize) -> bool {
+    // the blocking client cannot be built or used on the async runtime
+    let fetched = thread::spawn(move || CyGazClient::new()?.fetch_prices(petroleum_type, District::All)).join();
+    let result = match fetched {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => {
+            eprintln!("smoke {:?}: {}", petroleum_type, err);
+            return false;
+        }
+        Err(_) => {
+            eprintln!("smoke {:?}: scrape panicked", petroleum_type);
+            return false;
+        }
+    };
+
+    let findings = check(&result, min_stations);
+    for warning in &findings.warnings {
+        eprintln!("smoke {:?}: warning: {}", petroleum_type, warning);
+    }
+    for problem in &findings.problems {
+        eprintln!("smoke {:?}: {}", petroleum_type, problem);
+    }
+    println!(
+        "smoke {:?}: {} stations, {} skipped rows, {} warnings, {} problems",
+        petroleum_type,
+        result.stations.len(),
+        result.warnings.len(),
+        findings.warnings.len(),
+        findings.problems.len()
+    );
+    findings.problems.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::PriceResult;
+
+    use crate::fixtures::station;
+    use crate::smoke::{check, Findings};
+
+    #[test]
+    fn reports_what_looks_like_changed_markup() {
+        let mut result = PriceResult::default();
+        result.stations = vec![station("a", "Strovolos", 1.389), station("b", "Strovolos", 1.401)];
+        result.total_rows = 2;
+        assert_eq!(check(&result, 2), Findings::default());
+        assert_eq!(check(&result, 3).problems, vec!["2 stations, expected at least 3"]);
+
+        let mut swapped = station("c", "Strovolos", 2024.0);
+        swapped.area = "".to_string();
+        swapped.latitude = "Strovolos".to_string();
+        result.stations.push(swapped);
+        assert_eq!(
+            check(&result, 2).problems,
+            vec![
+                "station c has no area",
+                "station c priced 2024",
+                "station c at invalid coordinates Strovolos,33.3",
+            ]
+        );
+    }
+
+    #[test]
+    fn stations_without_coordinates_only_warn() {
+        let mut result = PriceResult::default();
+        let mut unmapped = station("b", "Strovolos", 1.401);
+        unmapped.latitude = "".to_string();
+        unmapped.longitude = " ".to_string();
+        result.stations = vec![station("a", "Strovolos", 1.389), unmapped];
+
+        let findings = check(&result, 2);
+        assert!(findings.problems.is_empty());
+        assert_eq!(findings.warnings, vec!["1 stations without coordinates"]);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use cygaz_lib::{AreasByDistrict, District, ParseWarning, PetroleumStation, PetroleumType};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregates::Aggregates;
+use crate::lifecycle::StationLifecycle;
+use crate::stats::PriceStatistics;
+use crate::{AppStateWithPrices, PriceList};
+
+/// A price list as stored, what the list takes from the configuration is not.
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotList {
+    pub petroleum_type: PetroleumType,
+    pub updated_at: u128,
+    pub updated_at_str: String,
+    pub stations: Vec<PetroleumStation>,
+    pub warnings: Vec<ParseWarning>,
+    pub total_rows: usize,
+    // missing from snapshots saved before it was tracked
+    #[serde(default)]
+    pub updated_at_by_district: BTreeMap<District, u128>,
+}
+
+/// The last known prices and districts, so a restart serves them until the warm-up
+/// refresh is done.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub areas: AreasByDistrict,
+    pub lists: Vec<SnapshotList>,
+    // missing from snapshots saved before stations were tracked
+    #[serde(default)]
+    pub lifecycle: StationLifecycle,
+}
+
+fn lists(state: &mut AppStateWithPrices) -> [&mut PriceList; 5] {
+    [
+        &mut state.unlead95,
+        &mut state.unlead98,
+        &mut state.diesel_heat,
+        &mut state.diesel_auto,
+        &mut state.kerosene,
+    ]
+}
+
+impl Snapshot {
+    pub fn of(state: &AppStateWithPrices) -> Self {
+        let lists = [
+            &state.unlead95,
+            &state.unlead98,
+            &state.diesel_heat,
+            &state.diesel_auto,
+            &state.kerosene,
+        ];
+        Snapshot {
+            areas: state.areas.clone(),
+            lists: lists
+                .into_iter()
+                .map(|list| SnapshotList {
+                    petroleum_type: list.petroleum_type,
+                    updated_at: list.updated_at,
+                    updated_at_str: list.updated_at_str.clone(),
+                    stations: list.stations.clone(),
+                    warnings: list.warnings.clone(),
+                    total_rows: list.total_rows,
+                    updated_at_by_district: list.updated_at_by_district.clone(),
+                })
+                .collect(),
+            lifecycle: state.lifecycle.clone(),
+        }
+    }
+
+    /// The saved prices as lists of `state`, with what is served from them worked out already.
+    pub fn prepare(self, state: &AppStateWithPrices) -> Restored {
+        let areas = match self.areas.is_empty() {
+            true => state.areas.clone(),
+            false => self.areas,
+        };
+        let mut lists = [
+            state.unlead95.clone(),
+            state.unlead98.clone(),
+            state.diesel_heat.clone(),
+            state.diesel_auto.clone(),
+            state.kerosene.clone(),
+        ];
+        for saved in self.lists {
+            let Some(list) = lists
+                .iter_mut()
+                .find(|list| list.petroleum_type == saved.petroleum_type)
+            else {
+                continue;
+            };
+            list.updated_at = saved.updated_at;
+            list.updated_at_str = saved.updated_at_str;
+            list.stations = saved.stations;
+            list.warnings = saved.warnings;
+            list.total_rows = saved.total_rows;
+            list.updated_at_by_district = saved.updated_at_by_district;
+        }
+
+        let mut stats = PriceStatistics::default();
+        for list in &lists {
+            stats.record(list, &areas);
+        }
+        let mut aggregates = Aggregates::default();
+        aggregates.update(&lists.iter().collect::<Vec<_>>(), &areas);
+        Restored {
+            areas,
+            lifecycle: self.lifecycle,
+            lists,
+            stats,
+            aggregates,
+        }
+    }
+
+    /// Replaces the prices and districts of `state`, stations keep the status they were saved with.
+    pub fn restore(self, state: &mut AppStateWithPrices) {
+        self.prepare(state).restore(state);
+    }
+}
+
+/// A snapshot turned back into price lists, so that restoring it only swaps them in.
+pub struct Restored {
+    areas: AreasByDistrict,
+    lifecycle: StationLifecycle,
+    lists: [PriceList; 5],
+    stats: PriceStatistics,
+    aggregates: Aggregates,
+}
+
+impl Restored {
+    pub fn restore(self, state: &mut AppStateWithPrices) {
+        state.areas = self.areas;
+        state.lifecycle = self.lifecycle;
+        for restored in self.lists {
+            let Some(list) = lists(state)
+                .into_iter()
+                .find(|list| list.petroleum_type == restored.petroleum_type)
+            else {
+                continue;
+            };
+            list.updated_at = restored.updated_at;
+            list.updated_at_str = restored.updated_at_str;
+            list.stations = restored.stations;
+            list.warnings = restored.warnings;
+            list.total_rows = restored.total_rows;
+            list.updated_at_by_district = restored.updated_at_by_district;
+        }
+
+        let lists = [
+            &state.unlead95,
+            &state.unlead98,
+            &state.diesel_heat,
+            &state.diesel_auto,
+            &state.kerosene,
+        ];
+        for list in lists {
+            state.freshness.record(list);
+            state.sync.update(list, &state.areas, list.updated_at);
+        }
+        state.stats = self.stats;
+        state.aggregates = self.aggregates;
+    }
+}
+
+/// Writes next to `path` first, so a crash mid write leaves the previous snapshot intact.
+pub fn save(path: &Path, snapshot: &Snapshot) -> Result<(), String> {
+    let body = serde_json::to_vec(snapshot).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let partial = path.with_extension("partial");
+    fs::write(&partial, body).map_err(|err| format!("{}: {}", partial.display(), err))?;
+    fs::rename(&partial, path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// `None` when nothing was saved yet.
+pub fn load(path: &Path) -> Result<Option<Snapshot>, String> {
+    let body = match fs::read(path) {
+        Ok(body) => body,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("{}: {}", path.display(), err)),
+    };
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use cygaz_lib::{AreasByDistrict, District, PetroleumStation, PetroleumType};
+
+    use crate::lifecycle::StationLifecycle;
+    use crate::snapshot::{load, save, Snapshot, SnapshotList};
+
+    #[test]
+    fn saves_and_loads_snapshot() {
+        let path = std::env::temp_dir().join(format!("cygaz-snapshot-{}.json", std::process::id()));
+        assert!(load(&path).unwrap().is_none());
+
+        let snapshot = Snapshot {
+            areas: AreasByDistrict::from([(District::Paphos, vec!["Πέγεια".to_string()])]),
+            lists: vec![SnapshotList {
+                petroleum_type: PetroleumType::Kerosene,
+                updated_at: 10,
+                updated_at_str: "".to_string(),
+                stations: vec![PetroleumStation::new("a", 1.2)],
+                warnings: vec![],
+                total_rows: 1,
+                updated_at_by_district: BTreeMap::from([(District::Paphos, 10)]),
+            }],
+            lifecycle: StationLifecycle::default(),
+        };
+        save(&path, &snapshot).unwrap();
+        let loaded = load(&path).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.areas[&District::Paphos], vec!["Πέγεια"]);
+        assert_eq!(loaded.lists[0].petroleum_type, PetroleumType::Kerosene);
+        assert_eq!(loaded.lists[0].stations[0].price, 1.2);
+        assert_eq!(loaded.lists[0].updated_at_by_district[&District::Paphos], 10);
+    }
+}
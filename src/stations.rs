@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::normalize::fold;
+use cygaz_lib::{AreasByDistrict, District, PetroleumType, StationStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::coalesce::{request_key, SingleFlight};
+use crate::database::{Observation, Resolution, DAY};
+use crate::nationwide::MergedStation;
+use crate::lang;
+use crate::status::default_include_offline;
+use crate::{AppStateWithPrices, SharedState};
+
+#[derive(Clone, Default, Serialize)]
+pub struct RegisteredStation {
+    pub station_id: String,
+    pub brand: String,
+    pub company: String,
+    pub address: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub area: String,
+    // unknown until the districts are fetched, or for areas upstream lists under none
+    pub district: Option<District>,
+    pub offline: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<StationStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_since: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline_since: Option<u128>,
+    // petroleum types the station lists a price for
+    pub petroleum_types: Vec<PetroleumType>,
+}
+
+/// A station with its current price of every petroleum type it lists.
+#[derive(Clone, Serialize)]
+pub struct PricedStation {
+    #[serde(flatten)]
+    pub station: RegisteredStation,
+    pub prices: BTreeMap<PetroleumType, f32>,
+}
+
+// longer ranges are averaged per day
+const DAILY_AFTER: u128 = 7 * DAY;
+
+#[derive(Deserialize)]
+pub struct StationHistoryQuery {
+    pub fuel: Option<String>,
+    // milliseconds since the epoch, a week up to now by default
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PricePoint {
+    pub observed_at: u128,
+    pub price: f32,
+}
+
+#[derive(Serialize)]
+pub struct StationPriceHistory {
+    pub station_id: String,
+    pub from: u128,
+    pub to: u128,
+    pub resolution: Resolution,
+    pub petroleum_types: BTreeMap<PetroleumType, Vec<PricePoint>>,
+}
+
+#[derive(Deserialize)]
+pub struct StationsQuery {
+    pub district: Option<District>,
+    pub brand: Option<String>,
+    #[serde(default = "default_include_offline")]
+    pub include_offline: bool,
+}
+
+impl StationsQuery {
+    fn matches(&self, station: &RegisteredStation) -> bool {
+        let district = match self.district {
+            None | Some(District::All) => true,
+            district => station.district == district,
+        };
+        let brand = self
+            .brand
+            .as_ref()
+            .is_none_or(|brand| fold(brand) == fold(&station.brand));
+        district && brand && (self.include_offline || !station.offline)
+    }
+}
+
+/// District upstream lists `area` under, if any.
+pub fn district_of(areas: &AreasByDistrict, area: &str) -> Option<District> {
+    areas
+        .iter()
+        .find(|(_, district_areas)| district_areas.iter().any(|district_area| district_area == area))
+        .map(|(district, _)| *district)
+}
+
+fn register(station: MergedStation, areas: &AreasByDistrict) -> RegisteredStation {
+    RegisteredStation {
+        district: district_of(areas, &station.area),
+        petroleum_types: station.prices.keys().copied().collect(),
+        station_id: station.station_id,
+        brand: station.brand,
+        company: station.company,
+        address: station.address,
+        latitude: station.latitude,
+        longitude: station.longitude,
+        area: station.area,
+        offline: station.offline,
+        status: station.status,
+        status_since: station.status_since,
+        offline_since: station.offline_since,
+    }
+}
+
+/// Every known station once, whatever it is priced for, closed ones included.
+pub fn registry(
+    stations: Vec<MergedStation>,
+    areas: &AreasByDistrict,
+    query: &StationsQuery,
+) -> Vec<RegisteredStation> {
+    stations
+        .into_iter()
+        .map(|station| register(station, areas))
+        .filter(|station| query.matches(station))
+        .collect()
+}
+
+/// `station` with its current prices.
+pub fn priced(station: MergedStation, areas: &AreasByDistrict) -> PricedStation {
+    let prices = station.prices.clone();
+    PricedStation {
+        station: register(station, areas),
+        prices,
+    }
+}
+
+/// The station with `station_id` among `stations`, if listed.
+pub fn find(stations: Vec<MergedStation>, areas: &AreasByDistrict, station_id: &str) -> Option<PricedStation> {
+    let station = stations.into_iter().find(|station| station.station_id == station_id)?;
+    Some(priced(station, areas))
+}
+
+// closed ones included
+pub fn merged_stations(state: &AppStateWithPrices) -> Vec<MergedStation> {
+    state
+        .aggregates
+        .nationwide(true)
+        .map(|nationwide| nationwide.stations.clone())
+        .unwrap_or_default()
+}
+
+// closed stations have no prices to track
+pub fn open_stations(state: &AppStateWithPrices) -> &[MergedStation] {
+    state
+        .aggregates
+        .nationwide(false)
+        .map(|nationwide| nationwide.stations.as_slice())
+        .unwrap_or_default()
+}
+
+#[get("/stations")]
+pub async fn list_stations(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    query: web::Query<StationsQuery>,
+    flights: web::Data<SingleFlight<Vec<RegisteredStation>>>,
+) -> impl Responder {
+    let key = request_key(&req, data.read().version());
+    let data = data.get_ref().clone();
+    let query = query.into_inner();
+    // the key tells ?lang=en apart
+    let transliteration = lang::latin(&req);
+    let stations = flights.run(key, move || {
+        let state = data.read();
+        let mut stations = registry(merged_stations(&state), &state.areas, &query);
+        if let Some(transliteration) = transliteration {
+            lang::transliterate(&mut stations, &transliteration);
+        }
+        stations
+    });
+    match stations.await {
+        Ok(stations) => HttpResponse::Ok().json(&*stations),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[get("/stations/{id}")]
+pub async fn get_station(req: HttpRequest, data: web::Data<SharedState>, id: web::Path<String>) -> impl Responder {
+    let state = data.read();
+    match find(merged_stations(&state), &state.areas, &id) {
+        Some(station) => HttpResponse::Ok().json(lang::localized(&req, station)),
+        None => HttpResponse::NotFound().json(serde_json::json!({ "error": "Unknown station_id" })),
+    }
+}
+
+/// Observations grouped by petroleum type, in the order they came.
+pub fn series(observations: Vec<Observation>) -> BTreeMap<PetroleumType, Vec<PricePoint>> {
+    let mut series: BTreeMap<PetroleumType, Vec<PricePoint>> = BTreeMap::new();
+    for observation in observations {
+        series.entry(observation.petroleum_type).or_default().push(PricePoint {
+            observed_at: observation.observed_at,
+            price: observation.price,
+        });
+    }
+    series
+}
+
+#[get("/stations/{id}/history")]
+pub async fn station_history(
+    data: web::Data<SharedState>,
+    id: web::Path<String>,
+    query: web::Query<StationHistoryQuery>,
+) -> impl Responder {
+    let petroleum_type = match &query.fuel {
+        None => None,
+        Some(fuel) => match PetroleumType::from_name(fuel) {
+            Some(petroleum_type) => Some(petroleum_type),
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({ "error": format!("Unknown fuel {}", fuel) }))
+            }
+        },
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    let to = query.to.map_or(now, u128::from);
+    let from = query.from.map_or(to.saturating_sub(DAILY_AFTER), u128::from);
+    if from > to {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "from is after to" }));
+    }
+    let resolution = match to - from > DAILY_AFTER {
+        true => Resolution::Daily,
+        false => Resolution::Refresh,
+    };
+
+    let Some(database) = data.read().database.clone() else {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "Price history is not stored" }));
+    };
+    let station_id = id.into_inner();
+    let lookup = station_id.clone();
+    let observations =
+        web::block(move || database.station_history(&lookup, petroleum_type, from, to, resolution)).await;
+    match observations {
+        Ok(Ok(observations)) => HttpResponse::Ok().json(StationPriceHistory {
+            station_id,
+            from,
+            to,
+            resolution,
+            petroleum_types: series(observations),
+        }),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType};
+
+    use crate::fixtures::merged;
+    use crate::nationwide::MergedStation;
+    use crate::stations::{find, registry, StationsQuery};
+
+    #[test]
+    fn filters_by_district_and_brand() {
+        let areas = AreasByDistrict::from([(District::Paphos, vec!["Πέγεια".to_string()])]);
+        let stations = || {
+            [("a", "ΕΚΟ", "Πέγεια"), ("b", "Petrolina", "Πέγεια"), ("c", "Eko", "Στρόβολος")]
+                .map(|(station_id, brand, area)| MergedStation {
+                    brand: brand.to_string(),
+                    ..merged(station_id, area, &[(PetroleumType::DieselAuto, 1.4)])
+                })
+                .to_vec()
+        };
+
+        let all = registry(stations(), &areas, &StationsQuery { district: None, brand: None, include_offline: true });
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].district, Some(District::Paphos));
+        assert_eq!(all[2].district, None);
+        assert_eq!(all[0].petroleum_types, vec![PetroleumType::DieselAuto]);
+
+        let query = StationsQuery {
+            district: Some(District::Paphos),
+            brand: Some("petrolina".to_string()),
+            include_offline: true,
+        };
+        let paphos = registry(stations(), &areas, &query);
+        assert_eq!(paphos.len(), 1);
+        assert_eq!(paphos[0].station_id, "b");
+    }
+
+    #[test]
+    fn finds_station_with_all_prices() {
+        let mut station = merged("a", "Πέγεια", &[(PetroleumType::DieselAuto, 1.4)]);
+        station.prices.insert(PetroleumType::Unlead95, 1.35);
+        let areas = AreasByDistrict::new();
+
+        let found = find(vec![station.clone()], &areas, "a").unwrap();
+        assert_eq!(found.prices.len(), 2);
+        assert_eq!(found.prices[&PetroleumType::Unlead95], 1.35);
+        assert!(find(vec![station], &areas, "b").is_none());
+    }
+}
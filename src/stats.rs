@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::quality::{counted_prices, median};
+use cygaz_lib::{AreasByDistrict, District, PetroleumStation, PetroleumType, PriceUnit};
+use serde::Serialize;
+
+use crate::{PriceList, SharedState};
+
+#[derive(Clone, Serialize, PartialEq, Debug)]
+pub struct DistrictStats {
+    pub count: usize,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub avg: Option<f32>,
+    pub median: Option<f32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FuelStats {
+    pub updated_at: u128,
+    pub unit: PriceUnit,
+    pub districts: BTreeMap<District, DistrictStats>,
+}
+
+/// Price statistics of the latest refresh per petroleum type and district.
+#[derive(Clone, Default)]
+pub struct PriceStatistics {
+    fuels: BTreeMap<PetroleumType, FuelStats>,
+}
+
+impl PriceStatistics {
+    pub fn record(&mut self, list: &PriceList, areas: &AreasByDistrict) {
+        let mut districts = BTreeMap::from([(District::All, district_stats(list.stations.iter()))]);
+        for (district, district_areas) in areas {
+            let stations = list
+                .stations
+                .iter()
+                .filter(|station| district_areas.contains(&station.area));
+            districts.insert(*district, district_stats(stations));
+        }
+        self.fuels.insert(
+            list.petroleum_type,
+            FuelStats {
+                updated_at: list.updated_at,
+                unit: list.unit,
+                districts,
+            },
+        );
+    }
+
+    /// Statistics of `petroleum_type` in `district` as of the latest refresh, if any.
+    pub fn district(&self, petroleum_type: PetroleumType, district: District) -> Option<(&FuelStats, &DistrictStats)> {
+        let fuel = self.fuels.get(&petroleum_type)?;
+        Some((fuel, fuel.districts.get(&district)?))
+    }
+}
+
+/// Statistics over the prices of `stations`, leaving out closed stations and outliers.
+pub fn district_stats<'a>(stations: impl Iterator<Item = &'a PetroleumStation>) -> DistrictStats {
+    let mut prices = counted_prices(stations);
+    let median = median(&mut prices);
+    let count = prices.len();
+    DistrictStats {
+        count,
+        min: prices.first().copied(),
+        max: prices.last().copied(),
+        avg: median.map(|_| prices.iter().sum::<f32>() / count as f32),
+        median,
+    }
+}
+
+#[get("/stats")]
+pub async fn price_statistics(data: web::Data<SharedState>) -> impl Responder {
+    let state = data.read();
+    HttpResponse::Ok().json(&state.stats.fuels)
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::StationStatus;
+
+    use crate::fixtures::station;
+    use crate::stats::{district_stats, DistrictStats};
+
+    #[test]
+    fn skips_closed_stations_and_outliers() {
+        let mut closed = station("", "", 1.10);
+        closed.status = Some(StationStatus::Closed);
+        let mut outlier = station("", "", 0.14);
+        outlier.outlier = true;
+        let stations = [
+            station("a", "", 1.40),
+            station("b", "", 1.30),
+            closed,
+            outlier,
+            station("c", "", 1.36),
+            station("d", "", 1.50),
+        ];
+
+        let stats = district_stats(stations.iter());
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, Some(1.30));
+        assert_eq!(stats.max, Some(1.50));
+        assert_eq!(stats.median, Some(1.38));
+        assert_eq!(
+            district_stats([].iter()),
+            DistrictStats {
+                count: 0,
+                min: None,
+                max: None,
+                avg: None,
+                median: None,
+            }
+        );
+    }
+}
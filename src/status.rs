@@ -0,0 +1,172 @@
+use std::collections::{HashMap, HashSet};
+
+use cygaz_lib::normalize::fold;
+use cygaz_lib::{PetroleumStation, StationStatus};
+use serde::Deserialize;
+
+use crate::PriceList;
+
+pub type StationKey = (String, String, String);
+
+/// Identity of a station across fuel types and refreshes.
+pub fn station_key(station: &PetroleumStation) -> StationKey {
+    (
+        fold(&station.brand),
+        fold(&station.company),
+        fold(&station.address),
+    )
+}
+
+#[derive(Clone, Copy)]
+struct Record {
+    online_since: Option<u128>,
+    offline_since: Option<u128>,
+}
+
+/// Remembers since when every station has been online or offline, to tell a short
+/// outage apart from a station that closed down.
+#[derive(Clone)]
+pub struct StationHistory {
+    closed_after: u128,
+    records: HashMap<StationKey, Record>,
+}
+
+impl StationHistory {
+    pub fn new(closed_after_millis: u128) -> Self {
+        StationHistory {
+            closed_after: closed_after_millis,
+            records: HashMap::new(),
+        }
+    }
+
+    /// Records the offline flags of the latest refresh and stamps every station with its status.
+    pub fn observe(&mut self, lists: &mut [&mut PriceList], now: u128) {
+        // a station counts as offline when any of its fuel listings says so
+        let mut offline: HashMap<StationKey, bool> = HashMap::new();
+        for list in lists.iter() {
+            for station in &list.stations {
+                *offline.entry(station_key(station)).or_default() |= station.offline;
+            }
+        }
+
+        for (key, is_offline) in &offline {
+            let record = self.records.entry(key.clone()).or_insert(Record {
+                online_since: None,
+                offline_since: None,
+            });
+            if *is_offline {
+                record.online_since = None;
+                record.offline_since.get_or_insert(now);
+            } else {
+                record.offline_since = None;
+                record.online_since.get_or_insert(now);
+            }
+        }
+
+        // stations gone from upstream start with a clean slate if they return
+        let seen = offline.keys().collect::<HashSet<_>>();
+        self.records.retain(|key, _| seen.contains(key));
+
+        for list in lists.iter_mut() {
+            for station in list.stations.iter_mut() {
+                let record = self.records[&station_key(station)];
+                let (status, since) = self.classify(record, now);
+                station.status = Some(status);
+                station.status_since = Some(since);
+                station.offline_since = record.offline_since;
+            }
+        }
+    }
+
+    fn classify(&self, record: Record, now: u128) -> (StationStatus, u128) {
+        match (record.offline_since, record.online_since) {
+            (Some(since), _) if now.saturating_sub(since) >= self.closed_after => {
+                (StationStatus::Closed, since)
+            }
+            (Some(since), _) => (StationStatus::TemporarilyOffline, since),
+            (None, since) => (StationStatus::Open, since.unwrap_or(now)),
+        }
+    }
+}
+
+pub fn default_include_offline() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct StationFilter {
+    #[serde(default)]
+    pub include_closed: bool,
+    // false leaves out every station upstream flags offline, closed or not
+    #[serde(default = "default_include_offline")]
+    pub include_offline: bool,
+}
+
+impl Default for StationFilter {
+    fn default() -> Self {
+        StationFilter {
+            include_closed: false,
+            include_offline: default_include_offline(),
+        }
+    }
+}
+
+impl StationFilter {
+    pub fn apply(&self, list: &PriceList) -> PriceList {
+        let mut filtered = list.clone();
+        if !self.include_closed {
+            filtered
+                .stations
+                .retain(|station| station.status != Some(StationStatus::Closed));
+        }
+        if !self.include_offline {
+            filtered.stations.retain(|station| !station.offline);
+        }
+        filtered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{
+        PetroleumType, StationStatus,
+    };
+
+    use crate::fixtures::{list, station};
+    use crate::status::{StationFilter, StationHistory};
+
+    #[test]
+    fn offline_station_becomes_closed_after_threshold() {
+        let mut history = StationHistory::new(100);
+
+        let mut online = list(PetroleumType::Unlead95, 0, vec![station("a", "", 0.0).with_offline(false)]);
+        history.observe(&mut [&mut online], 10);
+        assert_eq!(online.stations[0].status, Some(StationStatus::Open));
+        assert_eq!(online.stations[0].status_since, Some(10));
+
+        let mut offline = list(PetroleumType::Unlead95, 0, vec![station("a", "", 0.0).with_offline(true)]);
+        history.observe(&mut [&mut offline], 20);
+        assert_eq!(offline.stations[0].status, Some(StationStatus::TemporarilyOffline));
+        assert_eq!(offline.stations[0].status_since, Some(20));
+        assert_eq!(offline.stations[0].offline_since, Some(20));
+        assert_eq!(StationFilter::default().apply(&offline).stations.len(), 1);
+        let online_only = StationFilter {
+            include_offline: false,
+            ..Default::default()
+        };
+        assert!(online_only.apply(&offline).stations.is_empty());
+
+        let mut offline = list(PetroleumType::Unlead95, 0, vec![station("a", "", 0.0).with_offline(true)]);
+        history.observe(&mut [&mut offline], 120);
+        assert_eq!(offline.stations[0].status, Some(StationStatus::Closed));
+        assert_eq!(offline.stations[0].status_since, Some(20));
+        assert!(StationFilter::default().apply(&offline).stations.is_empty());
+
+        let mut online = list(PetroleumType::Unlead95, 0, vec![station("a", "", 0.0).with_offline(false)]);
+        history.observe(&mut [&mut online], 130);
+        assert_eq!(online.stations[0].status, Some(StationStatus::Open));
+        assert_eq!(online.stations[0].status_since, Some(130));
+        assert!(online.stations[0].offline_since.is_none());
+        assert_eq!(online_only.apply(&online).stations.len(), 1);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashSet};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::{PetroleumStation, PetroleumType, PriceResult};
+use serde::Serialize;
+
+use crate::{PriceList, SharedState};
+
+#[derive(Clone, Serialize)]
+pub struct RefreshSummary {
+    pub updated_at: u128,
+    pub stations: usize,
+    pub warnings: usize,
+    // stations listed with their previous price because the new one was unusable
+    pub carried_forward: usize,
+    // refreshes in a row that found no stations, retried before the next scheduled one
+    pub failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failing_since: Option<u128>,
+    #[serde(flatten)]
+    pub scrape: Scrape,
+}
+
+/// How the latest fetch of a petroleum type from upstream went.
+#[derive(Clone, Default, Serialize, PartialEq, Debug)]
+pub struct Scrape {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Outcome of the latest refresh per petroleum type.
+#[derive(Clone, Default)]
+pub struct RefreshSummaries {
+    summaries: BTreeMap<PetroleumType, RefreshSummary>,
+}
+
+impl RefreshSummaries {
+    pub fn latest(&self) -> &BTreeMap<PetroleumType, RefreshSummary> {
+        &self.summaries
+    }
+
+    /// Whether the latest refresh of `petroleum_type` found no stations.
+    pub fn failing(&self, petroleum_type: PetroleumType) -> bool {
+        self.summaries
+            .get(&petroleum_type)
+            .is_some_and(|summary| summary.failures > 0)
+    }
+
+    /// Records the refreshed `list` and the `scrape` that refreshed it, `carried_forward` is None
+    /// when the listing did not change and the previous count still holds.
+    pub fn record(&mut self, list: &PriceList, carried_forward: Option<usize>, scrape: Scrape) {
+        let previous = self.summaries.get(&list.petroleum_type);
+        let (failures, failing_since) = match (carried_forward, previous) {
+            (Some(_), previous) if list.stations.is_empty() => (
+                previous.map(|summary| summary.failures).unwrap_or_default() + 1,
+                previous
+                    .and_then(|summary| summary.failing_since)
+                    .or(Some(list.updated_at)),
+            ),
+            (None, Some(previous)) => (previous.failures, previous.failing_since),
+            _ => (0, None),
+        };
+        let summary = RefreshSummary {
+            updated_at: list.updated_at,
+            stations: list.stations.len(),
+            warnings: list.warnings.len(),
+            carried_forward: carried_forward
+                .or(previous.map(|summary| summary.carried_forward))
+                .unwrap_or_default(),
+            failures,
+            failing_since,
+            scrape,
+        };
+        self.summaries.insert(list.petroleum_type, summary);
+    }
+
+    /// Records a refresh at `at` that failed and left `list` as it was, which counts as a refresh
+    /// that found no stations.
+    pub fn record_kept(&mut self, list: &PriceList, at: u128, scrape: Scrape) {
+        let previous = self.summaries.get(&list.petroleum_type);
+        let summary = RefreshSummary {
+            updated_at: at,
+            stations: list.stations.len(),
+            warnings: list.warnings.len(),
+            carried_forward: previous.map(|summary| summary.carried_forward).unwrap_or_default(),
+            failures: previous.map(|summary| summary.failures).unwrap_or_default() + 1,
+            failing_since: previous.and_then(|summary| summary.failing_since).or(Some(at)),
+            scrape,
+        };
+        self.summaries.insert(list.petroleum_type, summary);
+    }
+}
+
+/// Lists the stations whose new price was unusable with their `previous` one instead,
+/// returning how many were carried forward.
+pub fn carry_forward(previous: &[PetroleumStation], result: &mut PriceResult) -> usize {
+    let mut carried_forward = 0;
+    for warning in &result.warnings {
+        let Some(station_id) = &warning.station_id else {
+            continue;
+        };
+        // another row of the station had a usable price
+        if result.stations.iter().any(|s| &s.station_id == station_id) {
+            continue;
+        }
+        if let Some(station) = previous.iter().find(|s| &s.station_id == station_id) {
+            let mut station = station.clone();
+            station.carried_forward = true;
+            result.stations.push(station);
+            carried_forward += 1;
+        }
+    }
+    carried_forward
+}
+
+/// Keeps only the last listing of every station of `stations`, the one of this refresh when an
+/// earlier one lingers, returning the ids of the `previous` stations no longer listed.
+pub fn reconcile(previous: &[PetroleumStation], stations: &mut Vec<PetroleumStation>) -> Vec<String> {
+    let mut listed = HashSet::new();
+    stations.reverse();
+    stations.retain(|station| listed.insert(station.station_id.clone()));
+    stations.reverse();
+
+    previous
+        .iter()
+        .filter(|station| !listed.contains(&station.station_id))
+        .map(|station| station.station_id.clone())
+        .collect()
+}
+
+#[get("/status")]
+pub async fn refresh_status(data: web::Data<SharedState>) -> impl Responder {
+    let state = data.read();
+    HttpResponse::Ok().json(&state.summaries.summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{ParseWarning, PetroleumType, PriceResult};
+
+    use crate::fixtures::station;
+    use crate::summary::{carry_forward, reconcile, RefreshSummaries, Scrape};
+    use crate::PriceList;
+
+    #[test]
+    fn keeps_last_valid_price() {
+        let previous = vec![station("a", "", 1.30), station("b", "", 1.35)];
+        let warning =
+            |station_id: Option<&str>| ParseWarning::new(0, "Invalid price", "", station_id.map(|id| id.to_string()));
+        let mut result = PriceResult::default();
+        result.stations = vec![station("b", "", 1.36)];
+        result.warnings = vec![warning(Some("a")), warning(Some("new")), warning(None)];
+        result.total_rows = 4;
+
+        assert_eq!(carry_forward(&previous, &mut result), 1);
+        assert_eq!(result.stations.len(), 2);
+        assert_eq!(result.stations[1].station_id, "a");
+        assert_eq!(result.stations[1].price, 1.30);
+        assert!(result.stations[1].carried_forward);
+
+        // an unusable row next to a usable one of the same station
+        let mut result = PriceResult::default();
+        result.stations = vec![station("a", "", 1.31)];
+        result.warnings = vec![warning(Some("a"))];
+        assert_eq!(carry_forward(&previous, &mut result), 0);
+        assert_eq!(result.stations.len(), 1);
+    }
+
+    #[test]
+    fn drops_departed_stations_and_earlier_listings() {
+        let previous = vec![station("a", "", 1.30), station("b", "", 1.35), station("c", "", 1.40)];
+        // `b` left upstream, `a` was listed before the district refresh listed it again
+        let mut stations = vec![station("a", "", 1.30), station("c", "", 1.41), station("a", "", 1.29)];
+
+        assert_eq!(reconcile(&previous, &mut stations), vec!["b".to_string()]);
+        let listed = stations.iter().map(|s| (s.station_id.as_str(), s.price)).collect::<Vec<_>>();
+        assert_eq!(listed, vec![("c", 1.41), ("a", 1.29)]);
+    }
+
+    #[test]
+    fn counts_refreshes_without_stations() {
+        let mut list = PriceList {
+            stations: vec![],
+            ..PriceList::new(PetroleumType::Kerosene, 10, String::new())
+        };
+        let failed = Scrape {
+            ok: false,
+            error: Some("timed out".to_string()),
+            duration_ms: 5000,
+        };
+        let mut summaries = RefreshSummaries::default();
+        summaries.record(&list, Some(0), failed.clone());
+        list.updated_at = 20;
+        summaries.record(&list, Some(0), failed.clone());
+        // a listing that did not change says nothing new
+        summaries.record(&list, None, Scrape { ok: true, ..Scrape::default() });
+
+        let kerosene = &summaries.latest()[&PetroleumType::Kerosene];
+        assert_eq!((kerosene.failures, kerosene.failing_since), (2, Some(10)));
+        assert!(summaries.failing(PetroleumType::Kerosene));
+
+        list.stations = vec![station("a", "", 1.10)];
+        summaries.record(&list, Some(0), Scrape { ok: true, error: None, duration_ms: 300 });
+        assert!(!summaries.failing(PetroleumType::Kerosene));
+        let kerosene = &summaries.latest()[&PetroleumType::Kerosene];
+        assert_eq!(kerosene.failing_since, None);
+        assert_eq!(kerosene.scrape.duration_ms, 300);
+
+        // a failed district refresh keeps the stations, and fails all the same
+        summaries.record_kept(&list, 30, failed.clone());
+        assert!(summaries.failing(PetroleumType::Kerosene));
+        let kerosene = &summaries.latest()[&PetroleumType::Kerosene];
+        assert_eq!((kerosene.stations, kerosene.failing_since), (1, Some(30)));
+    }
+}
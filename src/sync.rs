@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use actix_web::http::header::{ETAG, IF_NONE_MATCH};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use cygaz_lib::{AreasByDistrict, District, PetroleumStation, PetroleumType};
+use serde::{Deserialize, Serialize};
+
+use crate::lang;
+use crate::nationwide::MergedStation;
+use crate::{PriceList, SharedState};
+
+// stations of one petroleum type in one district
+#[derive(Clone)]
+struct Bucket {
+    // versions before this one are unknown to the bucket, a delta from them has to be full
+    created_at: u128,
+    version: u128,
+    stations: HashMap<String, (u128, PetroleumStation)>,
+    removed: HashMap<String, u128>,
+}
+
+impl Bucket {
+    fn new(created_at: u128) -> Self {
+        Bucket {
+            created_at,
+            version: created_at,
+            stations: HashMap::new(),
+            removed: HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, stations: Vec<&PetroleumStation>, updated_at: u128) {
+        let mut changed = false;
+        let mut seen = HashSet::with_capacity(stations.len());
+
+        for station in stations {
+            seen.insert(station.station_id.as_str());
+            let unchanged = self
+                .stations
+                .get(&station.station_id)
+                .is_some_and(|(_, previous)| previous == station);
+            if !unchanged {
+                self.stations
+                    .insert(station.station_id.clone(), (updated_at, station.clone()));
+                self.removed.remove(&station.station_id);
+                changed = true;
+            }
+        }
+
+        let gone = self
+            .stations
+            .keys()
+            .filter(|station_id| !seen.contains(station_id.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        for station_id in gone {
+            self.stations.remove(&station_id);
+            self.removed.insert(station_id, updated_at);
+            changed = true;
+        }
+
+        if changed {
+            self.version = updated_at;
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Delta {
+    pub petroleum_type: PetroleumType,
+    pub district: District,
+    pub version: u128,
+    pub since_version: Option<u128>,
+    // every station of the bucket, the client drops what it had
+    pub full: bool,
+    pub stations: Vec<PetroleumStation>,
+    pub removed: Vec<String>,
+}
+
+/// Stations of a district whose prices changed after some version, in any petroleum type.
+#[derive(Debug, PartialEq)]
+pub struct Changes {
+    pub version: u128,
+    // every station of the district, the client drops what it had
+    pub full: bool,
+    pub changed: BTreeSet<String>,
+    // no longer listed for any petroleum type
+    pub removed: Vec<String>,
+}
+
+/// Versions of every petroleum type and district, where a version is the refresh time of the
+/// latest change, so versions keep growing across restarts.
+#[derive(Clone, Default)]
+pub struct SyncVersions {
+    buckets: BTreeMap<(PetroleumType, District), Bucket>,
+}
+
+impl SyncVersions {
+    pub fn update(&mut self, list: &PriceList, areas: &AreasByDistrict, updated_at: u128) {
+        let districts = [District::All].into_iter().chain(District::DISTRICTS);
+        for district in districts {
+            let stations = list
+                .stations
+                .iter()
+                .filter(|station| match district {
+                    District::All => true,
+                    district => areas
+                        .get(&district)
+                        .is_some_and(|district_areas| district_areas.contains(&station.area)),
+                })
+                .collect();
+            self.buckets
+                .entry((list.petroleum_type, district))
+                .or_insert_with(|| Bucket::new(updated_at))
+                .update(stations, updated_at);
+        }
+    }
+
+    /// Latest version of any of `petroleum_types` in `district`, `0` before their first refresh.
+    pub fn version(&self, petroleum_types: &[PetroleumType], district: District) -> u128 {
+        petroleum_types
+            .iter()
+            .filter_map(|petroleum_type| self.buckets.get(&(*petroleum_type, district)))
+            .map(|bucket| bucket.version)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Current version of every petroleum type in `district`, `None` before its first refresh.
+    pub fn versions(&self, district: District) -> Vec<(PetroleumType, Option<u128>)> {
+        PetroleumType::ALL
+            .into_iter()
+            .map(|petroleum_type| {
+                let version = self.buckets.get(&(petroleum_type, district)).map(|bucket| bucket.version);
+                (petroleum_type, version)
+            })
+            .collect()
+    }
+
+    pub fn delta(&self, petroleum_type: PetroleumType, district: District, since: Option<u128>) -> Option<Delta> {
+        let bucket = self.buckets.get(&(petroleum_type, district))?;
+        let full = since.is_none_or(|since| since < bucket.created_at);
+        let since_version = if full { 0 } else { since.unwrap_or_default() };
+
+        let mut stations = bucket
+            .stations
+            .values()
+            .filter(|(changed_at, _)| full || *changed_at > since_version)
+            .map(|(_, station)| station.clone())
+            .collect::<Vec<_>>();
+        stations.sort_by(|a, b| a.station_id.cmp(&b.station_id));
+        let mut removed = match full {
+            true => vec![],
+            false => bucket
+                .removed
+                .iter()
+                .filter(|(_, removed_at)| **removed_at > since_version)
+                .map(|(station_id, _)| station_id.clone())
+                .collect(),
+        };
+        removed.sort();
+
+        Some(Delta {
+            petroleum_type,
+            district,
+            version: bucket.version,
+            since_version: since,
+            full,
+            stations,
+            removed,
+        })
+    }
+
+    /// What changed in `district` after `since`, None before its first refresh.
+    pub fn changes(&self, district: District, since: Option<u128>) -> Option<Changes> {
+        let buckets = PetroleumType::ALL
+            .iter()
+            .filter_map(|petroleum_type| self.buckets.get(&(*petroleum_type, district)))
+            .collect::<Vec<_>>();
+        let version = buckets.iter().map(|bucket| bucket.version).max()?;
+        let full = since.is_none_or(|since| buckets.iter().any(|bucket| since < bucket.created_at));
+        let since = if full { 0 } else { since.unwrap_or_default() };
+
+        let mut changed = buckets
+            .iter()
+            .flat_map(|bucket| bucket.stations.iter())
+            .filter(|(_, (changed_at, _))| full || *changed_at > since)
+            .map(|(station_id, _)| station_id.clone())
+            .collect::<BTreeSet<_>>();
+        let mut removed = BTreeSet::new();
+        if !full {
+            let gone = buckets
+                .iter()
+                .flat_map(|bucket| bucket.removed.iter())
+                .filter(|(_, removed_at)| **removed_at > since);
+            for (station_id, _) in gone {
+                // dropped from one petroleum type but listed for another, so its prices changed
+                match buckets.iter().any(|bucket| bucket.stations.contains_key(station_id)) {
+                    true => changed.insert(station_id.clone()),
+                    false => removed.insert(station_id.clone()),
+                };
+            }
+        }
+
+        Some(Changes {
+            version,
+            full,
+            changed,
+            removed: removed.into_iter().collect(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct PriceChanges {
+    pub district: District,
+    pub version: u128,
+    pub since: Option<u128>,
+    pub full: bool,
+    // with their current price of every petroleum type they list
+    pub stations: Vec<MergedStation>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    // an earlier `version`, query strings cannot carry a u128
+    pub since: Option<u64>,
+    pub district: Option<District>,
+}
+
+// 304 while the client has `etag`
+fn not_modified(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|if_none_match| if_none_match.split(',').any(|tag| tag.trim() == etag))
+}
+
+#[get("/prices/changes")]
+pub async fn price_changes(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    query: web::Query<ChangesQuery>,
+) -> impl Responder {
+    let district = query.district.unwrap_or(District::All);
+    let since = query.since.map(u128::from);
+
+    let state = data.read();
+    let Some(changes) = state.sync.changes(district, since) else {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "No prices yet" }));
+    };
+    let etag = format!("\"changes-{:?}-{}\"", district, changes.version);
+    if not_modified(&req, &etag) {
+        return HttpResponse::NotModified().insert_header((ETAG, etag)).finish();
+    }
+
+    let stations = state
+        .aggregates
+        .nationwide(true)
+        .map(|nationwide| {
+            nationwide
+                .stations
+                .iter()
+                .filter(|station| changes.changed.contains(&station.station_id))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let changes = PriceChanges {
+        district,
+        version: changes.version,
+        since,
+        full: changes.full,
+        stations,
+        removed: changes.removed,
+    };
+    HttpResponse::Ok().insert_header((ETAG, etag)).json(lang::localized(&req, changes))
+}
+
+#[derive(Deserialize)]
+pub struct DeltaQuery {
+    // query strings cannot carry a u128
+    pub since_version: Option<u64>,
+    pub district: Option<District>,
+}
+
+#[get("/prices/{id}/delta")]
+pub async fn price_delta(
+    req: HttpRequest,
+    data: web::Data<SharedState>,
+    id: web::Path<i32>,
+    query: web::Query<DeltaQuery>,
+) -> impl Responder {
+    let Some(petroleum_type) = PetroleumType::from_id(id.into_inner()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let district = query.district.unwrap_or(District::All);
+
+    let state = data.read();
+    let Some(delta) = state.sync.delta(petroleum_type, district, query.since_version.map(u128::from)) else {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": "No prices yet" }));
+    };
+
+    let etag = format!("\"{:?}-{:?}-{}\"", petroleum_type, district, delta.version);
+    if not_modified(&req, &etag) {
+        return HttpResponse::NotModified().insert_header((ETAG, etag)).finish();
+    }
+    HttpResponse::Ok().insert_header((ETAG, etag)).json(lang::localized(&req, delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType};
+
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    use crate::fixtures::prices;
+    use crate::sync::{DeltaQuery, SyncVersions};
+
+    #[test]
+    fn districts_change_versions_independently() {
+        let areas = AreasByDistrict::from([
+            (District::Nicosia, vec!["Strovolos".to_string()]),
+            (District::Paphos, vec!["Peyia".to_string()]),
+        ]);
+        let list = |listed: &[(&str, &str, f32)]| prices(PetroleumType::Unlead95, listed);
+        let mut sync = SyncVersions::default();
+        sync.update(&list(&[("a", "Strovolos", 1.40), ("b", "Peyia", 1.45)]), &areas, 10);
+        sync.update(&list(&[("a", "Strovolos", 1.38)]), &areas, 20);
+
+        let nicosia = sync.delta(PetroleumType::Unlead95, District::Nicosia, Some(10)).unwrap();
+        assert_eq!(nicosia.version, 20);
+        assert!(!nicosia.full);
+        assert_eq!(nicosia.stations[0].price, 1.38);
+
+        let paphos = sync.delta(PetroleumType::Unlead95, District::Paphos, Some(10)).unwrap();
+        assert!(paphos.stations.is_empty());
+        assert_eq!(paphos.removed, vec!["b"]);
+
+        sync.update(&list(&[("a", "Strovolos", 1.38)]), &areas, 30);
+        let nicosia = sync.delta(PetroleumType::Unlead95, District::Nicosia, Some(20)).unwrap();
+        assert_eq!(nicosia.version, 20);
+        assert!(nicosia.stations.is_empty());
+        assert_eq!(sync.version(&[PetroleumType::Unlead95], District::Nicosia), 20);
+        assert_eq!(sync.version(&PetroleumType::ALL, District::All), 20);
+        assert_eq!(sync.version(&[PetroleumType::Kerosene], District::All), 0);
+
+        // older than anything the service knows, maybe from before a restart
+        assert!(sync.delta(PetroleumType::Unlead95, District::All, Some(5)).unwrap().full);
+        assert!(sync.delta(PetroleumType::Unlead95, District::All, None).unwrap().full);
+    }
+
+    #[actix_web::test]
+    async fn reads_versions_from_the_query_string() {
+        let app = init_service(App::new().route(
+            "/prices/{id}/delta",
+            web::get().to(|query: web::Query<DeltaQuery>| async move {
+                HttpResponse::Ok().body(format!("{:?} {:?}", query.since_version, query.district))
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/prices/1/delta?since_version=1791981541557&district=Paphos")
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(read_body(res).await, "Some(1791981541557) Some(Paphos)");
+
+        let req = TestRequest::get().uri("/prices/1/delta?since_version=soon").to_request();
+        assert_eq!(call_service(&app, req).await.status().as_u16(), 400);
+    }
+
+    #[test]
+    fn changes_across_petroleum_types() {
+        let areas = AreasByDistrict::new();
+        let list = |listed: &[(&str, &str, f32)]| prices(PetroleumType::Unlead95, listed);
+        let diesel = |listed: &[(&str, &str, f32)]| prices(PetroleumType::DieselAuto, listed);
+        let mut sync = SyncVersions::default();
+        assert!(sync.changes(District::All, None).is_none());
+        sync.update(&list(&[("a", "", 1.40), ("b", "", 1.45), ("c", "", 1.50)]), &areas, 10);
+        sync.update(&diesel(&[("a", "", 1.50), ("b", "", 1.55)]), &areas, 10);
+
+        sync.update(&list(&[("a", "", 1.38), ("b", "", 1.45)]), &areas, 20);
+        sync.update(&diesel(&[("a", "", 1.50)]), &areas, 20);
+        let changes = sync.changes(District::All, Some(10)).unwrap();
+        assert_eq!(changes.version, 20);
+        assert!(!changes.full);
+        // b is still listed for unleaded 95, only c is gone
+        assert_eq!(changes.changed.into_iter().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(changes.removed, vec!["c"]);
+
+        let unchanged = sync.changes(District::All, Some(20)).unwrap();
+        assert!(unchanged.changed.is_empty() && unchanged.removed.is_empty());
+        let full = sync.changes(District::All, Some(5)).unwrap();
+        assert!(full.full);
+        assert_eq!(full.changed.len(), 2);
+        assert!(full.removed.is_empty());
+    }
+}
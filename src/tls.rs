@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use log::{info, warn};
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+
+/// Reads the certificate chain at `cert_path` and its private key at `key_path`, both PEM.
+pub fn load(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, String> {
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| format!("{}: {}", cert_path.display(), err))?;
+    if chain.is_empty() {
+        return Err(format!("{}: no certificate", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|err| format!("{}: {}", key_path.display(), err))?;
+    let key = ring::sign::any_supported_type(&key).map_err(|err| format!("{}: {}", key_path.display(), err))?;
+    let certified = CertifiedKey::new(chain, key);
+    // one of the files replaced and not the other yet
+    certified
+        .keys_match()
+        .map_err(|err| format!("{}: {}", key_path.display(), err))?;
+    Ok(certified)
+}
+
+// when either file last changed, to reload only what was replaced
+fn modified(paths: [&Path; 2]) -> Option<SystemTime> {
+    paths
+        .into_iter()
+        .filter_map(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .max()
+}
+
+/// The certificate served, swapped for the one on disk when its files change.
+#[derive(Debug)]
+pub struct Certificate {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<(Arc<CertifiedKey>, Option<SystemTime>)>,
+}
+
+impl Certificate {
+    pub fn new(cert_path: PathBuf, key_path: PathBuf) -> Result<Self, String> {
+        let modified = modified([&cert_path, &key_path]);
+        let key = load(&cert_path, &key_path)?;
+        Ok(Certificate {
+            cert_path,
+            key_path,
+            current: RwLock::new((Arc::new(key), modified)),
+        })
+    }
+
+    /// Loads the certificate again if its files changed since, keeping the one served when the
+    /// new one does not load. Whether it was replaced.
+    pub fn reload(&self) -> bool {
+        let modified = modified([&self.cert_path, &self.key_path]);
+        if modified == self.current.read().unwrap().1 {
+            return false;
+        }
+        match load(&self.cert_path, &self.key_path) {
+            Ok(key) => {
+                *self.current.write().unwrap() = (Arc::new(key), modified);
+                info!("reloaded certificate {}", self.cert_path.display());
+                true
+            }
+            Err(err) => {
+                warn!("keeping the certificate served, failed to reload it: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Reloads the certificate every `interval` for as long as the service runs.
+    pub fn watch(self: Arc<Self>, interval: Duration) {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            self.reload();
+        });
+    }
+}
+
+impl ResolvesServerCert for Certificate {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().0.clone())
+    }
+}
+
+/// Serves `certificate` to every client.
+pub fn server_config(certificate: Arc<Certificate>) -> Result<ServerConfig, String> {
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?
+        .with_no_client_auth()
+        .with_cert_resolver(certificate);
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::tls::Certificate;
+
+    #[test]
+    fn names_the_file_that_does_not_load() {
+        let path = std::env::temp_dir().join(format!("cygaz-tls-{}.pem", std::process::id()));
+        std::fs::write(&path, "not a certificate").unwrap();
+
+        let err = Certificate::new(path.clone(), path.clone()).unwrap_err();
+        assert_eq!(err, format!("{}: no certificate", path.display()));
+        let err = Certificate::new(Path::new("/nonexistent/cert.pem").into(), path.clone()).unwrap_err();
+        assert!(err.starts_with("/nonexistent/cert.pem: "));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cygaz_lib::{District, PetroleumType, PriceUnit, CURRENCY};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{Observation, DAY};
+use crate::stations::{district_of, merged_stations};
+use crate::{request_id, SharedState};
+
+const DEFAULT_WINDOW: &str = "7d";
+
+const MAX_WINDOW_DAYS: u32 = 90;
+
+// what /trends covers without `from`
+const DEFAULT_RANGE: u128 = 30 * DAY;
+
+// moving averages closer than half a cent are not going anywhere
+const STEADY: f32 = 0.005;
+
+#[derive(Deserialize)]
+pub struct TrendsQuery {
+    pub fuel: Option<String>,
+    // nationwide by default
+    pub district: Option<District>,
+    // days the moving average spans, like `7d`
+    pub window: Option<String>,
+    // milliseconds since the epoch, the last 30 days up to now by default
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Rising,
+    Falling,
+    Steady,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct TrendPoint {
+    // the start of the UTC day
+    pub day: u128,
+    // of the daily average of every station priced that day
+    pub avg: f32,
+    pub moving_avg: f32,
+    pub stations: usize,
+}
+
+#[derive(Serialize)]
+pub struct PriceTrend {
+    pub petroleum_type: PetroleumType,
+    pub district: District,
+    pub window_days: u32,
+    pub from: u128,
+    pub to: u128,
+    pub currency: &'static str,
+    pub unit: PriceUnit,
+    // the latest moving average against the one a window earlier, None without both
+    pub change: Option<f32>,
+    pub direction: Option<Direction>,
+    pub days: Vec<TrendPoint>,
+}
+
+/// The days a window like `7d` spans.
+pub fn window_days(window: &str) -> Result<u32, String> {
+    let days = window
+        .strip_suffix('d')
+        .and_then(|days| days.parse::<u32>().ok())
+        .ok_or(format!("window {} takes days, like {}", window, DEFAULT_WINDOW))?;
+    match days {
+        1..=MAX_WINDOW_DAYS => Ok(days),
+        _ => Err(format!("window must be 1d to {}d", MAX_WINDOW_DAYS)),
+    }
+}
+
+/// Daily averages of the per station `daily` prices of `stations`, every station when None, from
+/// the day of `from` on, with their moving average over the `window_days` days up to each.
+pub fn trend(
+    daily: &[Observation],
+    stations: Option<&HashSet<String>>,
+    from: u128,
+    window_days: u32,
+) -> Vec<TrendPoint> {
+    let mut days: BTreeMap<u128, (f32, usize)> = BTreeMap::new();
+    for observation in daily {
+        if stations.is_some_and(|stations| !stations.contains(&observation.station_id)) {
+            continue;
+        }
+        let (sum, count) = days.entry(observation.observed_at).or_default();
+        *sum += observation.price;
+        *count += 1;
+    }
+    let averages = days
+        .into_iter()
+        .map(|(day, (sum, count))| (day, sum / count as f32, count))
+        .collect::<Vec<_>>();
+
+    let span = window_days as u128 * DAY;
+    averages
+        .iter()
+        .filter(|(day, _, _)| *day >= from / DAY * DAY)
+        .map(|&(day, avg, stations)| {
+            let window = averages
+                .iter()
+                .filter(|(other, _, _)| *other <= day && day - *other < span)
+                .map(|(_, avg, _)| *avg)
+                .collect::<Vec<_>>();
+            TrendPoint {
+                day,
+                avg,
+                moving_avg: window.iter().sum::<f32>() / window.len() as f32,
+                stations,
+            }
+        })
+        .collect()
+}
+
+/// How much the latest moving average of `days` moved since the one `window_days` days earlier,
+/// or the earliest after it.
+pub fn change(days: &[TrendPoint], window_days: u32) -> Option<(f32, Direction)> {
+    let latest = days.last()?;
+    let earlier_than = latest.day.saturating_sub(window_days as u128 * DAY);
+    let earlier = days.iter().find(|point| point.day >= earlier_than && point.day < latest.day)?;
+    let change = latest.moving_avg - earlier.moving_avg;
+    let direction = match change {
+        change if change >= STEADY => Direction::Rising,
+        change if change <= -STEADY => Direction::Falling,
+        _ => Direction::Steady,
+    };
+    Some((change, direction))
+}
+
+/// The per station daily prices of a fuel stored over some days, what trends and forecasts go by.
+pub struct StoredDays {
+    pub unit: PriceUnit,
+    pub daily: Vec<Observation>,
+    // of the district asked for, by the areas they are in now, None nationwide
+    pub stations: Option<HashSet<String>>,
+}
+
+impl StoredDays {
+    /// The days of `petroleum_type` in `district` from `since` to `to`, or the response telling why not.
+    pub async fn fetch(
+        data: &SharedState,
+        petroleum_type: PetroleumType,
+        district: District,
+        since: u128,
+        to: u128,
+    ) -> Result<Self, HttpResponse> {
+        let state = data.read();
+        let Some(database) = state.database.clone() else {
+            let error = "Price history is not stored";
+            return Err(HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": error })));
+        };
+        let Some(unit) = state.price_list(petroleum_type).map(|list| list.unit) else {
+            let error = format!("No prices of {:?}", petroleum_type);
+            return Err(HttpResponse::NotFound().json(serde_json::json!({ "error": error })));
+        };
+        // stations gone since are left out
+        let stations = (district != District::All).then(|| {
+            merged_stations(&state)
+                .into_iter()
+                .filter(|station| district_of(&state.areas, &station.area) == Some(district))
+                .map(|station| station.station_id)
+                .collect::<HashSet<_>>()
+        });
+        drop(state);
+
+        match request_id::block(move || database.daily_prices(petroleum_type, since, to)).await {
+            Ok(Ok(daily)) => Ok(StoredDays { unit, daily, stations }),
+            _ => Err(HttpResponse::InternalServerError().finish()),
+        }
+    }
+}
+
+#[get("/trends")]
+pub async fn price_trends(data: web::Data<SharedState>, query: web::Query<TrendsQuery>) -> impl Responder {
+    let checked = query.fuel.as_deref().ok_or("fuel is required".to_string()).and_then(|fuel| {
+        let petroleum_type = PetroleumType::from_name(fuel).ok_or(format!("Unknown fuel {}", fuel))?;
+        Ok((petroleum_type, window_days(query.window.as_deref().unwrap_or(DEFAULT_WINDOW))?))
+    });
+    let (petroleum_type, window_days) = match checked {
+        Ok(checked) => checked,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let district = query.district.unwrap_or(District::All);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    let to = query.to.map_or(now, u128::from);
+    let from = query.from.map_or(to.saturating_sub(DEFAULT_RANGE), u128::from);
+    if from > to {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "from is after to" }));
+    }
+
+    // the days before `from` the first moving averages take in
+    let since = (from / DAY * DAY).saturating_sub((window_days as u128 - 1) * DAY);
+    let stored = match StoredDays::fetch(&data, petroleum_type, district, since, to).await {
+        Ok(stored) => stored,
+        Err(res) => return res,
+    };
+    let days = trend(&stored.daily, stored.stations.as_ref(), from, window_days);
+    let change = change(&days, window_days);
+    HttpResponse::Ok().json(PriceTrend {
+        petroleum_type,
+        district,
+        window_days,
+        from,
+        to,
+        currency: CURRENCY,
+        unit: stored.unit,
+        change: change.map(|(change, _)| change),
+        direction: change.map(|(_, direction)| direction),
+        days,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use cygaz_lib::PetroleumType;
+
+    use crate::database::{Observation, DAY};
+    use crate::trends::{change, trend, window_days, Direction, TrendPoint};
+
+    fn daily(station_id: &str, day: u128, price: f32) -> Observation {
+        Observation {
+            station_id: station_id.to_string(),
+            petroleum_type: PetroleumType::Unlead95,
+            price,
+            observed_at: day * DAY,
+        }
+    }
+
+    #[test]
+    fn averages_days_and_windows_of_them() {
+        assert_eq!(window_days("7d"), Ok(7));
+        assert!(window_days("7").is_err());
+        assert!(window_days("0d").is_err());
+        assert!(window_days("365d").is_err());
+
+        let observations = vec![
+            daily("a", 1, 1.30),
+            daily("b", 1, 1.40),
+            daily("a", 2, 1.40),
+            daily("a", 3, 1.50),
+            daily("c", 3, 2.00),
+        ];
+        // the first day only counts towards the moving average
+        let days = trend(&observations, None, 2 * DAY + 10, 2);
+        let days_of = |days: &[TrendPoint]| days.iter().map(|d| d.day / DAY).collect::<Vec<_>>();
+        assert_eq!(days_of(&days), vec![2, 3]);
+        assert!((days[0].avg - 1.40).abs() < 1e-6);
+        assert!((days[0].moving_avg - 1.375).abs() < 1e-6);
+        assert!((days[1].avg - 1.75).abs() < 1e-6);
+        assert_eq!(days[1].stations, 2);
+
+        let stations = HashSet::from(["a".to_string()]);
+        let days = trend(&observations, Some(&stations), 0, 2);
+        assert_eq!(days_of(&days), vec![1, 2, 3]);
+        assert!((days[2].moving_avg - 1.45).abs() < 1e-6);
+
+        let (rise, direction) = change(&days, 2).unwrap();
+        assert!((rise - 0.15).abs() < 1e-6);
+        assert_eq!(direction, Direction::Rising);
+        assert!(change(&days[..1], 2).is_none());
+    }
+}
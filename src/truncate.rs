@@ -0,0 +1,270 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header::{HttpDate, IfModifiedSince, LastModified, ETAG, IF_MATCH, IF_NONE_MATCH};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::age::DataAge;
+use crate::pagination::{decode_versioned_cursor, encode_versioned_cursor};
+
+/// Where a page of stations starts, by the `cursor` of the previous page or by `offset`, and at most
+/// how many it has, never more than `MAX_RESPONSE_STATIONS`.
+#[derive(Deserialize, Default)]
+pub struct TruncateQuery {
+    pub cursor: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Truncated<T> {
+    #[serde(flatten)]
+    pub body: T,
+    pub truncated: bool,
+    // stations of the whole response, before any were skipped or left out
+    pub total: usize,
+    // absent unless stations were left out
+    pub next_cursor: Option<String>,
+    // changes whenever the stations behind the response do
+    pub consistency_token: String,
+    // only when older than STALE_AFTER
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+/// Identifies the stations at sync `version`, which only changes with them.
+pub fn consistency_token(version: u128) -> String {
+    version.to_string()
+}
+
+// true unless If-Match names tokens that all differ from `token`
+fn matches(req: &HttpRequest, token: &str) -> bool {
+    let Some(if_match) = req.headers().get(IF_MATCH).and_then(|value| value.to_str().ok()) else {
+        return true;
+    };
+    if_match
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+        .any(|tag| tag == "*" || tag == token)
+}
+
+// true when the client's copy is current, by If-None-Match or, only without it, If-Modified-Since
+fn not_modified(req: &HttpRequest, token: &str, updated_at: u128) -> bool {
+    if let Some(if_none_match) = req.headers().get(IF_NONE_MATCH).and_then(|value| value.to_str().ok()) {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+            .any(|tag| tag == "*" || tag == token);
+    }
+    let Some(IfModifiedSince(since)) = req.get_header::<IfModifiedSince>() else {
+        return false;
+    };
+    // HTTP dates have whole seconds
+    let since = SystemTime::from(since).duration_since(UNIX_EPOCH).unwrap_or_default();
+    updated_at / 1000 <= since.as_secs() as u128
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PageError {
+    Invalid(String),
+    // the cursor points into an older version of the stations
+    Stale,
+}
+
+/// Upper bound of stations in a single unpaginated response, `0` means unlimited.
+#[derive(Clone, Copy)]
+pub struct StationLimit(pub usize);
+
+impl StationLimit {
+    /// Keeps the page of `stations` at `version` asked for by `query`, at most the limit of them.
+    /// Returns the cursor of the first station left out.
+    pub fn apply<S>(
+        &self,
+        stations: &mut Vec<S>,
+        query: &TruncateQuery,
+        version: u128,
+    ) -> Result<Option<String>, PageError> {
+        let offset = match (&query.cursor, query.offset) {
+            (Some(_), Some(_)) => return Err(PageError::Invalid("Pass either cursor or offset".to_string())),
+            (Some(cursor), None) => match decode_versioned_cursor(cursor) {
+                Ok((offset, cursor_version)) if cursor_version == version => offset as usize,
+                Ok(_) => return Err(PageError::Stale),
+                Err(err) => return Err(PageError::Invalid(err.to_string())),
+            },
+            (None, offset) => offset.unwrap_or_default(),
+        };
+        let limit = match (query.limit, self.0) {
+            (Some(0), _) => return Err(PageError::Invalid("Limit has to be at least 1".to_string())),
+            (Some(limit), 0) => limit,
+            (Some(limit), max) => limit.min(max),
+            (None, max) => max,
+        };
+        stations.drain(..offset.min(stations.len()));
+
+        if limit == 0 || stations.len() <= limit {
+            return Ok(None);
+        }
+        stations.truncate(limit);
+        Ok(Some(encode_versioned_cursor((offset + limit) as u128, version)))
+    }
+
+    /// Answers with the limited `body` of stations at `version`, or `412 Precondition Failed` when
+    /// the request's `If-Match` or cursor names an older snapshot, so a client paging with cursors
+    /// can start over. A client whose copy is still current gets `304 Not Modified`.
+    pub fn respond<T: Serialize, S>(
+        &self,
+        req: &HttpRequest,
+        mut body: T,
+        updated_at: u128,
+        version: u128,
+        stations: fn(&mut T) -> &mut Vec<S>,
+        query: &TruncateQuery,
+    ) -> HttpResponse {
+        let token = consistency_token(version);
+        let last_modified = LastModified(HttpDate::from(UNIX_EPOCH + Duration::from_millis(updated_at as u64)));
+        let changed = || {
+            HttpResponse::PreconditionFailed()
+                .insert_header((ETAG, format!("\"{}\"", token)))
+                .json(serde_json::json!({
+                    "error": "Snapshot changed",
+                    "consistency_token": token,
+                }))
+        };
+        if !matches(req, &token) {
+            return changed();
+        }
+
+        if not_modified(req, &token, updated_at) {
+            return HttpResponse::NotModified()
+                .insert_header((ETAG, format!("\"{}\"", token)))
+                .insert_header(last_modified)
+                .finish();
+        }
+
+        let total = stations(&mut body).len();
+        let age = DataAge::of(req, updated_at);
+        match self.apply(stations(&mut body), query, version) {
+            Ok(next_cursor) => {
+                let mut res = HttpResponse::Ok()
+                    .insert_header((ETAG, format!("\"{}\"", token)))
+                    .insert_header(last_modified)
+                    .json(Truncated {
+                        body,
+                        truncated: next_cursor.is_some(),
+                        total,
+                        next_cursor,
+                        consistency_token: token.clone(),
+                        stale: age.stale,
+                    });
+                age.insert(res.headers_mut());
+                res
+            }
+            Err(PageError::Stale) => changed(),
+            Err(PageError::Invalid(err)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use crate::truncate::{consistency_token, matches, not_modified, PageError, StationLimit, TruncateQuery};
+
+    fn from_cursor(cursor: Option<String>) -> TruncateQuery {
+        TruncateQuery {
+            cursor,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn truncates_and_continues_from_cursor() {
+        let limit = StationLimit(2);
+
+        let mut stations = (1..=5).collect::<Vec<_>>();
+        let cursor = limit.apply(&mut stations, &from_cursor(None), 10).unwrap();
+        assert_eq!(stations, vec![1, 2]);
+
+        let mut stations = (1..=5).collect::<Vec<_>>();
+        let cursor = limit.apply(&mut stations, &from_cursor(cursor), 10).unwrap();
+        assert_eq!(stations, vec![3, 4]);
+
+        let mut stations = (1..=5).collect::<Vec<_>>();
+        let stale = limit.apply(&mut stations.clone(), &from_cursor(cursor.clone()), 11);
+        assert_eq!(stale, Err(PageError::Stale));
+        let cursor = limit.apply(&mut stations, &from_cursor(cursor), 10).unwrap();
+        assert_eq!(stations, vec![5]);
+        assert!(cursor.is_none());
+
+        let mut stations = (1..=5).collect::<Vec<_>>();
+        assert!(StationLimit(0).apply(&mut stations, &from_cursor(None), 10).unwrap().is_none());
+        assert_eq!(stations.len(), 5);
+    }
+
+    #[test]
+    fn pages_by_offset_and_limit_up_to_the_maximum() {
+        let page = |limit: StationLimit, offset, count| {
+            let mut stations = (1..=5).collect::<Vec<_>>();
+            let query = TruncateQuery {
+                cursor: None,
+                offset,
+                limit: count,
+            };
+            limit.apply(&mut stations, &query, 10).map(|next_cursor| (stations, next_cursor.is_some()))
+        };
+        assert_eq!(page(StationLimit(0), Some(1), Some(2)), Ok((vec![2, 3], true)));
+        assert_eq!(page(StationLimit(2), Some(3), Some(4)), Ok((vec![4, 5], false)));
+        assert_eq!(page(StationLimit(0), Some(9), None), Ok((vec![], false)));
+        assert!(page(StationLimit(0), None, Some(0)).is_err());
+
+        let mut stations = vec![1];
+        let query = TruncateQuery {
+            cursor: Some("djE6MQ".to_string()),
+            offset: Some(1),
+            limit: None,
+        };
+        assert!(StationLimit(0).apply(&mut stations, &query, 10).is_err());
+    }
+
+    #[test]
+    fn if_match_compares_consistency_tokens() {
+        let token = consistency_token(10);
+        assert_ne!(token, consistency_token(11));
+
+        assert!(matches(&TestRequest::default().to_http_request(), &token));
+        let req = TestRequest::default()
+            .insert_header(("If-Match", format!("\"{}\"", token)))
+            .to_http_request();
+        assert!(matches(&req, &token));
+        let req = TestRequest::default()
+            .insert_header(("If-Match", "\"11\""))
+            .to_http_request();
+        assert!(!matches(&req, &token));
+    }
+
+    #[test]
+    fn not_modified_by_etag_or_date() {
+        let token = consistency_token(1647710214169);
+        assert!(!not_modified(&TestRequest::default().to_http_request(), &token, 1647710214169));
+
+        let req = TestRequest::default()
+            .insert_header(("If-None-Match", format!("W/\"other\", \"{}\"", token)))
+            .to_http_request();
+        assert!(not_modified(&req, &token, 1647710214169));
+
+        // 2022-03-19 17:16:54 UTC
+        let req = TestRequest::default()
+            .insert_header(("If-Modified-Since", "Sat, 19 Mar 2022 17:16:54 GMT"))
+            .to_http_request();
+        assert!(not_modified(&req, &token, 1647710214169));
+        assert!(!not_modified(&req, &token, 1647710215000));
+
+        // an etag that differs wins over a date that matches
+        let req = TestRequest::default()
+            .insert_header(("If-None-Match", "\"10\""))
+            .insert_header(("If-Modified-Since", "Sat, 19 Mar 2022 17:16:54 GMT"))
+            .to_http_request();
+        assert!(!not_modified(&req, &token, 1647710214169));
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(any(feature = "grpc", feature = "realtime"))]
+use std::sync::Arc;
+
+use cygaz_lib::{District, PetroleumType};
+use serde::Serialize;
+#[cfg(any(feature = "grpc", feature = "realtime"))]
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+#[cfg(feature = "realtime")]
+use crate::events::EventLog;
+use crate::sync::{Delta, SyncVersions};
+
+// updates a slow client may fall behind by before it is told it missed some
+#[cfg(any(feature = "grpc", feature = "realtime"))]
+const BACKLOG: usize = 16;
+
+/// Pushed to every listener once a refresh is done.
+#[derive(Serialize)]
+pub struct PriceUpdate {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub updated_at: u128,
+    // nationwide, only petroleum types with changed or removed stations
+    pub changes: Vec<Delta>,
+}
+
+impl PriceUpdate {
+    /// What changed since the `before` versions, see `SyncVersions::versions`.
+    pub fn new(sync: &SyncVersions, before: &[(PetroleumType, Option<u128>)], updated_at: u128) -> Self {
+        let changes = before
+            .iter()
+            .filter_map(|(petroleum_type, version)| sync.delta(*petroleum_type, District::All, *version))
+            .filter(|delta| !delta.stations.is_empty() || !delta.removed.is_empty())
+            .collect();
+        PriceUpdate {
+            kind: "refresh_completed",
+            updated_at,
+            changes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChangeCounts {
+    pub changed: usize,
+    pub removed: usize,
+}
+
+/// Hands every update to whoever listens at the time, serialized once for all WebSocket
+/// listeners, converted once for all gRPC listeners and summed up for the event stream.
+#[derive(Clone)]
+pub struct Updates {
+    #[cfg(feature = "realtime")]
+    sender: Sender<Arc<String>>,
+    #[cfg(feature = "realtime")]
+    events: Arc<EventLog>,
+    #[cfg(feature = "grpc")]
+    messages: Sender<Arc<crate::grpc::proto::PriceUpdate>>,
+}
+
+// with neither feature there is nothing to default
+#[cfg_attr(not(any(feature = "grpc", feature = "realtime")), allow(clippy::derivable_impls))]
+impl Default for Updates {
+    fn default() -> Self {
+        Updates {
+            #[cfg(feature = "realtime")]
+            sender: broadcast::channel(BACKLOG).0,
+            #[cfg(feature = "realtime")]
+            events: Arc::new(EventLog::default()),
+            #[cfg(feature = "grpc")]
+            messages: broadcast::channel(BACKLOG).0,
+        }
+    }
+}
+
+impl Updates {
+    pub fn refresh_started(&self, _started_at: u128) {
+        #[cfg(feature = "realtime")]
+        self.events.refresh_started(_started_at);
+    }
+
+    pub fn publish(&self, _update: &PriceUpdate) {
+        #[cfg(feature = "realtime")]
+        self.events.refresh_completed(_update);
+        #[cfg(feature = "grpc")]
+        if self.messages.receiver_count() > 0 {
+            let _ = self.messages.send(Arc::new(crate::grpc::proto::PriceUpdate::from(_update)));
+        }
+        #[cfg(feature = "realtime")]
+        if self.sender.receiver_count() > 0 {
+            if let Ok(json) = serde_json::to_string(_update) {
+                let _ = self.sender.send(Arc::new(json));
+            }
+        }
+    }
+
+    #[cfg(feature = "realtime")]
+    pub fn events(&self) -> Arc<EventLog> {
+        self.events.clone()
+    }
+
+    #[cfg(feature = "realtime")]
+    pub fn subscribe(&self) -> Receiver<Arc<String>> {
+        self.sender.subscribe()
+    }
+
+    #[cfg(feature = "grpc")]
+    pub fn subscribe_messages(&self) -> Receiver<Arc<crate::grpc::proto::PriceUpdate>> {
+        self.messages.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cygaz_lib::{AreasByDistrict, District, PetroleumType};
+
+    use crate::fixtures::prices;
+    use crate::sync::SyncVersions;
+    use crate::updates::PriceUpdate;
+
+    #[test]
+    fn carries_only_changed_petroleum_types() {
+        let areas = AreasByDistrict::new();
+        let mut sync = SyncVersions::default();
+        sync.update(&prices(PetroleumType::Unlead95, &[("a", "", 1.40), ("b", "", 1.45)]), &areas, 10);
+        sync.update(&prices(PetroleumType::Kerosene, &[("a", "", 1.10)]), &areas, 10);
+
+        let before = sync.versions(District::All);
+        sync.update(&prices(PetroleumType::Unlead95, &[("a", "", 1.38)]), &areas, 20);
+        sync.update(&prices(PetroleumType::Kerosene, &[("a", "", 1.10)]), &areas, 20);
+
+        let update = PriceUpdate::new(&sync, &before, 20);
+        assert_eq!(update.changes.len(), 1);
+        assert_eq!(update.changes[0].petroleum_type, PetroleumType::Unlead95);
+        assert_eq!(update.changes[0].stations[0].price, 1.38);
+        assert_eq!(update.changes[0].removed, vec!["b"]);
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use cygaz_lib::{District, PetroleumType};
+use hmac::{Hmac, KeyInit, Mac};
+use log::{debug, error, warn};
+use reqwest::Url;
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::request_id;
+use crate::sync::SyncVersions;
+use crate::updates::ChangeCounts;
+
+pub const SIGNATURE_HEADER: &str = "X-Cygaz-Signature";
+
+// waits before every retry, a delivery failing all of them is logged as dead
+const RETRY_DELAYS: [Duration; 3] = [Duration::from_secs(1), Duration::from_secs(10), Duration::from_secs(60)];
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    // `config` for WEBHOOK_URLS, `admin` for the ones added through the admin API
+    pub source: &'static str,
+    #[serde(skip)]
+    secret: String,
+}
+
+/// Stations changed by a refresh, counted per district and petroleum type. `All` is nationwide.
+#[derive(Serialize)]
+pub struct ChangeSummary {
+    pub event: &'static str,
+    pub updated_at: u128,
+    pub districts: BTreeMap<District, BTreeMap<PetroleumType, ChangeCounts>>,
+}
+
+/// `SyncVersions::versions` of every district.
+pub type DistrictVersions = Vec<(District, Vec<(PetroleumType, Option<u128>)>)>;
+
+/// Versions of every district to summarize changes since.
+pub fn versions(sync: &SyncVersions) -> DistrictVersions {
+    [District::All]
+        .into_iter()
+        .chain(District::DISTRICTS)
+        .map(|district| (district, sync.versions(district)))
+        .collect()
+}
+
+impl ChangeSummary {
+    pub fn new(sync: &SyncVersions, before: &DistrictVersions, updated_at: u128) -> Self {
+        let districts = before
+            .iter()
+            .map(|(district, versions)| {
+                let changes = versions
+                    .iter()
+                    .filter_map(|(petroleum_type, version)| sync.delta(*petroleum_type, *district, *version))
+                    .filter(|delta| !delta.stations.is_empty() || !delta.removed.is_empty())
+                    .map(|delta| {
+                        let counts = ChangeCounts {
+                            changed: delta.stations.len(),
+                            removed: delta.removed.len(),
+                        };
+                        (delta.petroleum_type, counts)
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                (*district, changes)
+            })
+            .filter(|(_, changes)| !changes.is_empty())
+            .collect();
+        ChangeSummary {
+            event: "prices_changed",
+            updated_at,
+            districts,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.districts.is_empty()
+    }
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `body`, what receivers compare the signature header to.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex = digest.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    format!("sha256={}", hex)
+}
+
+/// Where changed prices are posted after a refresh.
+pub struct Webhooks {
+    hooks: Mutex<Vec<Webhook>>,
+    // signs the webhooks added without a secret of their own
+    secret: Option<String>,
+}
+
+impl Webhooks {
+    /// Takes the comma separated `WEBHOOK_URLS`, which need `secret` to be signed with.
+    pub fn new(urls: &str, secret: Option<String>) -> Result<Self, String> {
+        let webhooks = Webhooks {
+            hooks: Mutex::new(vec![]),
+            secret,
+        };
+        for url in urls.split(',').map(str::trim).filter(|url| !url.is_empty()) {
+            webhooks.add(url, None, "config")?;
+        }
+        Ok(webhooks)
+    }
+
+    pub fn list(&self) -> Vec<Webhook> {
+        self.hooks.lock().unwrap().clone()
+    }
+
+    pub fn add(&self, url: &str, secret: Option<String>, source: &'static str) -> Result<Webhook, String> {
+        let webhook = self.target(url, secret, source)?;
+        self.hooks.lock().unwrap().push(webhook.clone());
+        Ok(webhook)
+    }
+
+    /// A webhook to post to without registering it, signed with `secret` or `WEBHOOK_SECRET`.
+    pub fn target(&self, url: &str, secret: Option<String>, source: &'static str) -> Result<Webhook, String> {
+        let url = Url::parse(url).map_err(|err| format!("{}: {}", url, err))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("{}: not an http or https URL", url));
+        }
+        let Some(secret) = secret.or_else(|| self.secret.clone()).filter(|secret| !secret.is_empty()) else {
+            return Err(format!("{}: no secret to sign with, WEBHOOK_SECRET not set", url));
+        };
+        Ok(Webhook {
+            id: Uuid::new_v4().to_string(),
+            url: url.to_string(),
+            source,
+            secret,
+        })
+    }
+
+    /// Whether a webhook with `id` was there to remove.
+    pub fn remove(&self, id: &str) -> bool {
+        let mut hooks = self.hooks.lock().unwrap();
+        let before = hooks.len();
+        hooks.retain(|webhook| webhook.id != id);
+        hooks.len() < before
+    }
+
+    /// Posts `summary` to every webhook off the calling thread, unless nothing changed.
+    pub fn deliver(&self, summary: &ChangeSummary) {
+        let hooks = self.list();
+        if hooks.is_empty() || summary.is_empty() {
+            return;
+        }
+        let Ok(body) = serde_json::to_string(summary) else {
+            return;
+        };
+        let body = Arc::new(body);
+        for webhook in hooks {
+            send(webhook, body.clone());
+        }
+    }
+}
+
+/// Posts `body` to `webhook` off the calling thread, retrying before giving up on it.
+pub fn send(webhook: Webhook, body: Arc<String>) {
+    request_id::spawn(move || deliver(&webhook, &body));
+}
+
+fn deliver(webhook: &Webhook, body: &str) {
+    let client = match reqwest::blocking::Client::builder().timeout(TIMEOUT).build() {
+        Ok(client) => client,
+        Err(err) => {
+            error!("webhook {} dead-lettered, no client: {} {}", webhook.url, err, body);
+            return;
+        }
+    };
+    let signature = sign(&webhook.secret, body.as_bytes());
+
+    let attempts = RETRY_DELAYS.len() + 1;
+    for attempt in 1..=attempts {
+        let sent = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.to_string())
+            .send();
+        let failure = match sent {
+            Ok(res) if res.status().is_success() => {
+                debug!("webhook {} delivered", webhook.url);
+                return;
+            }
+            Ok(res) => format!("status {}", res.status()),
+            Err(err) => err.to_string(),
+        };
+        match RETRY_DELAYS.get(attempt - 1) {
+            Some(delay) => {
+                warn!("webhook {} attempt {} failed, retrying in {:?}: {}", webhook.url, attempt, delay, failure);
+                thread::sleep(*delay);
+            }
+            None => error!("webhook {} dead-lettered after {} attempts: {} {}", webhook.url, attempts, failure, body),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::webhooks::{sign, Webhooks};
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn adds_only_signed_http_webhooks() {
+        let webhooks = Webhooks::new(" https://example.com/a ,", Some("secret".to_string())).unwrap();
+        assert_eq!(webhooks.list().len(), 1);
+        assert_eq!(webhooks.list()[0].source, "config");
+        assert!(webhooks.add("ftp://example.com", None, "admin").is_err());
+        assert!(Webhooks::new("https://example.com/a", None).is_err());
+
+        let added = webhooks.add("https://example.com/b", None, "admin").unwrap();
+        assert!(webhooks.remove(&added.id));
+        assert!(!webhooks.remove(&added.id));
+    }
+}